@@ -0,0 +1,32 @@
+#version 150 core
+
+in vec2 v_Uv;
+out vec4 Target0;
+
+uniform sampler2D t_Source;
+
+layout (std140) uniform b_BlurLocals {
+  vec2 u_Direction;
+  float u_Threshold;
+};
+
+const int TAPS = 5;
+const float WEIGHTS[TAPS] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+vec3 bright(vec2 uv) {
+  vec3 color = texture(t_Source, uv).rgb;
+  if (u_Threshold > 0.0) {
+    color = max(color - vec3(u_Threshold), vec3(0.0));
+  }
+  return color;
+}
+
+void main() {
+  vec3 color = bright(v_Uv) * WEIGHTS[0];
+  for (int i = 1; i < TAPS; i++) {
+    vec2 offset = u_Direction * float(i);
+    color += bright(v_Uv + offset) * WEIGHTS[i];
+    color += bright(v_Uv - offset) * WEIGHTS[i];
+  }
+  Target0 = vec4(color, 1.0);
+}
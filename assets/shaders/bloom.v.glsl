@@ -0,0 +1,10 @@
+#version 150 core
+
+in vec2 a_Pos;
+in vec2 a_BufPos;
+out vec2 v_Uv;
+
+void main() {
+  v_Uv = a_BufPos;
+  gl_Position = vec4(a_Pos, 0.0, 1.0);
+}
@@ -1,7 +1,8 @@
 #version 150 core
 
+in vec4 v_Tint;
 out vec4 Target0;
 
 void main() {
-  Target0 = vec4(0.5, 0.2, 0.2, 0.8);
+  Target0 = v_Tint;
 }
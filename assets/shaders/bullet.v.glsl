@@ -0,0 +1,24 @@
+#version 150 core
+
+in vec3 a_Pos;
+in vec2 a_Translate;
+in float a_Depth;
+in float a_Rotation;
+in vec4 a_Tint;
+out vec4 v_Tint;
+
+uniform b_VsLocals {
+  mat4 u_Model;
+  mat4 u_View;
+  mat4 u_Proj;
+};
+
+void main() {
+  vec3 rot_pos = mat3(cos(a_Rotation),  -sin(a_Rotation),  0.0,
+                      sin(a_Rotation),  cos(a_Rotation),   0.0,
+                      0.0,              0.0,               1.0) * a_Pos;
+
+  v_Tint = a_Tint;
+  gl_Position = vec4(a_Translate, 0.0, 0.0) + vec4(rot_pos, 1.0) * u_Proj * u_View * u_Model;
+  gl_Position.z = a_Depth * gl_Position.w;
+}
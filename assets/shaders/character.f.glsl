@@ -1,6 +1,8 @@
 #version 150 core
 
 in vec2 v_BufPos;
+in vec2 v_ScreenPos;
+in vec4 v_Tint;
 out vec4 Target0;
 
 uniform sampler2D t_CharacterSheet;
@@ -13,5 +15,6 @@ void main() {
   tex.r = smoothstep(0.1, 1.0, tex.r);
   tex.g = smoothstep(0.1, 1.0, tex.g);
   tex.b = smoothstep(0.1, 1.0, tex.b);
-  Target0 = tex;
+  tex.rgb *= lighting(v_ScreenPos);
+  Target0 = tex * v_Tint;
 }
@@ -0,0 +1,26 @@
+#version 150 core
+
+in vec3 a_Pos;
+in vec2 a_BufPos;
+in vec2 a_Translate;
+in float a_Depth;
+in vec4 a_Sprite;
+in vec4 a_Tint;
+out vec2 v_BufPos;
+out vec2 v_ScreenPos;
+out vec4 v_Tint;
+
+uniform b_VsLocals {
+  mat4 u_Model;
+  mat4 u_View;
+  mat4 u_Proj;
+};
+
+void main() {
+  v_BufPos = a_Sprite.xy + a_BufPos * a_Sprite.zw;
+  v_Tint = a_Tint;
+
+  gl_Position = vec4(a_Translate, 0.0, 0.0) + u_Proj * u_View * u_Model * vec4(a_Pos, 1.0);
+  gl_Position.z = a_Depth * gl_Position.w;
+  v_ScreenPos = gl_Position.xy / gl_Position.w;
+}
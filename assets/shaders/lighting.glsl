@@ -0,0 +1,34 @@
+struct Light {
+  vec4 position;
+  vec4 color;
+  vec4 direction;
+};
+
+const int MAX_LIGHTS = 16;
+
+layout (std140) uniform b_Lights {
+  Light u_Lights[MAX_LIGHTS];
+};
+
+layout (std140) uniform b_LightLocals {
+  vec4 u_Ambient;
+  int u_LightCount;
+  float u_Aspect;
+};
+
+// Light position is xy in clip space, z radius and w cone cutoff cosine, -1 for point lights
+vec3 lighting(vec2 screenPos) {
+  vec3 light = u_Ambient.rgb;
+  for (int i = 0; i < u_LightCount; i++) {
+    vec4 pos = u_Lights[i].position;
+    vec2 toFragment = (screenPos - pos.xy) * vec2(u_Aspect, 1.0);
+    float dist = length(toFragment);
+    float attenuation = 1.0 - smoothstep(0.0, pos.z, dist);
+    if (pos.w > -1.0) {
+      float cosAngle = dist > 0.0 ? dot(toFragment / dist, u_Lights[i].direction.xy) : 1.0;
+      attenuation *= smoothstep(pos.w, mix(pos.w, 1.0, 0.3), cosAngle);
+    }
+    light += u_Lights[i].color.rgb * attenuation;
+  }
+  return light;
+}
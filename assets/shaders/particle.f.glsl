@@ -0,0 +1,15 @@
+#version 150 core
+
+in vec2 v_Uv;
+in vec4 v_Color;
+out vec4 Target0;
+
+uniform sampler2D t_ParticleSheet;
+
+void main() {
+  vec4 tex = texture(t_ParticleSheet, v_Uv) * v_Color;
+  if(tex.a < 0.01) {
+    discard;
+  }
+  Target0 = tex;
+}
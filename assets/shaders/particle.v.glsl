@@ -0,0 +1,24 @@
+#version 150 core
+
+in vec2 a_Pos;
+in vec2 a_BufPos;
+in vec2 a_Offset;
+in float a_Size;
+in float a_Frame;
+in vec4 a_Color;
+
+out vec2 v_Uv;
+out vec4 v_Color;
+
+uniform b_VsLocals {
+  mat4 u_Model;
+  mat4 u_View;
+  mat4 u_Proj;
+};
+
+void main() {
+  v_Uv = vec2((a_BufPos.x + a_Frame) / 2.0, a_BufPos.y);
+  v_Color = a_Color;
+  gl_Position = vec4(a_Offset, 0.0, 0.0) + vec4(a_Pos * a_Size, 0.0, 1.0) * u_Proj * u_View * u_Model;
+  gl_Position.z = 0.05 * gl_Position.w;
+}
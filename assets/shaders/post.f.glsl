@@ -0,0 +1,51 @@
+#version 150 core
+
+in vec2 v_Uv;
+out vec4 Target0;
+
+uniform sampler2D t_Scene;
+uniform sampler2D t_Bloom;
+uniform sampler2D t_Lut;
+
+layout (std140) uniform b_PostLocals {
+  float u_Bloom;
+  float u_Vignette;
+  float u_Danger;
+  float u_Night;
+  float u_Grain;
+  float u_Time;
+};
+
+const float LUT_SIZE = 16.0;
+const vec3 dangerColor = vec3(0.45, 0.0, 0.0);
+
+vec3 grade(vec3 color) {
+  vec3 c = clamp(color, 0.0, 1.0) * (LUT_SIZE - 1.0);
+  float slice = floor(c.b);
+  float blend = c.b - slice;
+  vec2 uv = vec2((c.r + 0.5) / (LUT_SIZE * LUT_SIZE), (c.g + 0.5) / LUT_SIZE);
+  vec3 low = texture(t_Lut, uv + vec2(slice / LUT_SIZE, 0.0)).rgb;
+  vec3 high = texture(t_Lut, uv + vec2(min(slice + 1.0, LUT_SIZE - 1.0) / LUT_SIZE, 0.0)).rgb;
+  return mix(low, high, blend);
+}
+
+float noise(vec2 uv) {
+  return fract(sin(dot(uv, vec2(12.9898, 78.233)) + u_Time) * 43758.5453);
+}
+
+void main() {
+  vec3 color = texture(t_Scene, v_Uv).rgb;
+  color += texture(t_Bloom, v_Uv).rgb * u_Bloom;
+
+  if (u_Night > 0.0) {
+    color = mix(color, grade(color), u_Night);
+  }
+
+  float edge = smoothstep(0.35, 0.95, length(v_Uv - vec2(0.5)) * 1.4);
+  color *= 1.0 - edge * u_Vignette;
+  color = mix(color, dangerColor, edge * u_Danger * 0.6);
+
+  color += (noise(v_Uv) - 0.5) * u_Grain;
+
+  Target0 = vec4(clamp(color, 0.0, 1.0), 1.0);
+}
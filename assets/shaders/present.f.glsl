@@ -0,0 +1,10 @@
+#version 150 core
+
+in vec2 v_Uv;
+out vec4 Target0;
+
+uniform sampler2D t_Frame;
+
+void main() {
+  Target0 = vec4(texture(t_Frame, v_Uv).rgb, 1.0);
+}
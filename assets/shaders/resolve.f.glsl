@@ -0,0 +1,15 @@
+#version 150 core
+
+out vec4 Target0;
+
+uniform sampler2DMS t_Scene;
+uniform int u_Samples;
+
+void main() {
+  ivec2 texel = ivec2(gl_FragCoord.xy);
+  vec4 color = vec4(0.0);
+  for (int i = 0; i < u_Samples; i++) {
+    color += texelFetch(t_Scene, texel, i);
+  }
+  Target0 = color / float(u_Samples);
+}
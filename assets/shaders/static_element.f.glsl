@@ -0,0 +1,17 @@
+#version 150 core
+
+in vec2 v_BufPos;
+in vec2 v_ScreenPos;
+in vec4 v_Tint;
+out vec4 Target0;
+
+uniform sampler2D t_StaticElementSheet;
+
+void main() {
+  vec4 tex = texture(t_StaticElementSheet, v_BufPos);
+  tex.rgb *= lighting(v_ScreenPos);
+  if(tex.a < 0.1) {
+    discard;
+  }
+  Target0 = tex * v_Tint;
+}
@@ -1,6 +1,7 @@
 #version 150 core
 
 in vec2 v_BufPos;
+in vec2 v_ScreenPos;
 out vec4 Target0;
 
 struct TileMapData {
@@ -20,24 +21,7 @@ layout (std140) uniform b_PsLocals {
 
 uniform sampler2D t_TileSheet;
 
-uniform b_TimeModulo {
-  float a_time;
-};
-
-const float PI = 3.14159;
-const vec3 lightOrigPos = vec3(-200.0, 150.0, 0.0);
-const vec3 Normal = vec3(0.0, 1.0, 0.0);
-const vec3 lightColor = vec3(0.8, 0.5, 0.5);
-const vec3 ambientColor = vec3(0.15, 0.15, 0.15);
-
 void main() {
-  float lightAngle = (a_time + 1) * 4;
-  float lightAngleRad = lightAngle * PI / 180.0;
-
-  vec3 lightPos = mat3(cos(lightAngleRad),  -sin(lightAngleRad),  0.0,
-                        sin(lightAngleRad),  cos(lightAngleRad),  0.0,
-                        0.0,       0.0,       1.0) * lightOrigPos;
-
   vec2 bufTileCoords = floor(v_BufPos);
   vec2 rawUvOffsets = vec2(v_BufPos.x - bufTileCoords.x, 1.0 - (v_BufPos.y - bufTileCoords.y));
 
@@ -59,14 +43,8 @@ void main() {
   }
   vec2 uvCoords = (coords.xy + rawUvOffsets) / u_TilesheetSize.xy;
 
-  vec3 norm = normalize(Normal);
-  vec3 lightDir = normalize(lightPos - vec3(v_BufPos, 0.0));
-
-  float diff = max(dot(norm, lightDir), 0.0);
-  vec3 diffuse = diff * lightColor;
-
   vec4 tex = texture(t_TileSheet, uvCoords);
-  tex *= vec4(diffuse + ambientColor, 1.0);
+  tex.rgb *= lighting(v_ScreenPos);
   if(tex.a < 0.1) {
     discard;
   }
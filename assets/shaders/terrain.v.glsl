@@ -3,6 +3,7 @@
 in vec2 a_Pos;
 in vec2 a_BufPos;
 out vec2 v_BufPos;
+out vec2 v_ScreenPos;
 
 uniform b_VsLocals {
   mat4 u_Model;
@@ -17,4 +18,5 @@ uniform b_TileMapPosition {
 void main() {
   v_BufPos = a_BufPos;
   gl_Position = vec4(a_position, 0.0, 0.0) + u_Proj * u_View * u_Model * vec4(a_Pos, 0.0, 1.0);
+  v_ScreenPos = gl_Position.xy / gl_Position.w;
 }
@@ -0,0 +1,15 @@
+#version 150 core
+
+in vec2 v_Uv;
+in vec4 v_Color;
+out vec4 Target0;
+
+uniform sampler2D t_TextSheet;
+
+void main() {
+  float alpha = texture(t_TextSheet, v_Uv).a;
+  if(alpha < 0.1) {
+    discard;
+  }
+  Target0 = vec4(v_Color.rgb, v_Color.a * alpha);
+}
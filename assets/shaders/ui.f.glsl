@@ -1,12 +1,13 @@
 #version 150 core
 
-in vec2 v_BufPos;
+in vec2 v_Uv;
+in vec4 v_Color;
 out vec4 Target0;
 
-uniform sampler2D t_TextSheet;
+uniform sampler2D t_UiSheet;
 
 void main() {
-  vec4 tex = texture(t_TextSheet, v_BufPos).rgba;
+  vec4 tex = texture(t_UiSheet, v_Uv) * v_Color;
   if(tex.a < 0.1) {
     discard;
   }
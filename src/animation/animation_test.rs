@@ -0,0 +1,56 @@
+#[test]
+fn clip_from_json_test() {
+  use crate::animation::{AnimationClips, LoopMode};
+
+  let sheet = json::parse(r#"{"meta": {"animations": {
+    "walk": {"row": 1, "offset": 32, "direction_stride": 8, "frames": 8, "fps": 20},
+    "death": {"row": 0, "offset": 64, "direction_stride": 6, "frames": 6, "fps": 20, "loop": "once", "hold": 2.5}
+  }}}"#).unwrap();
+  let clips = AnimationClips::from_json(&sheet).expect("Animation clips should parse");
+  let walk = clips.get("walk").expect("Walk clip missing");
+  assert_eq!(LoopMode::Loop, walk.mode, "Loop mode should default to loop");
+  assert_eq!((1, 32 + 3 * 8 + 7), walk.cell(3, 10), "Frame should be clamped to the clip length");
+  assert_eq!(2.5, clips.get("death").expect("Death clip missing").hold);
+
+  let invalid = json::parse(r#"{"meta": {"animations": {"run": {"row": 0, "offset": 0, "direction_stride": 1, "frames": 0, "fps": 10}}}}"#).unwrap();
+  assert!(AnimationClips::from_json(&invalid).is_err(), "Empty clip should be rejected");
+}
+
+#[test]
+fn looping_animator_test() {
+  use crate::animation::{AnimationClips, Animator};
+
+  let sheet = json::parse(r#"{"meta": {"animations": {
+    "still": {"row": 1, "offset": 0, "direction_stride": 4, "frames": 4, "fps": 10}
+  }}}"#).unwrap();
+  let clips = AnimationClips::from_json(&sheet).unwrap();
+  let mut animator = Animator::default();
+  assert_eq!(None, animator.cell(0), "Animator without a clip has no cell");
+
+  animator.play(&clips, "still");
+  assert_eq!(None, animator.update(0.25));
+  assert_eq!(2, animator.frame, "Two frames should pass in 0.25s at 10 fps");
+  animator.update(0.2);
+  assert_eq!(0, animator.frame, "Looping clip should wrap around");
+
+  animator.update(0.1);
+  animator.play(&clips, "still");
+  assert_eq!(1, animator.frame, "Playing the current clip should not restart it");
+}
+
+#[test]
+fn once_animator_finish_event_test() {
+  use crate::animation::{AnimationClips, AnimationEvent, Animator};
+
+  let sheet = json::parse(r#"{"meta": {"animations": {
+    "death": {"row": 0, "offset": 0, "direction_stride": 3, "frames": 3, "fps": 10, "loop": "once", "hold": 1.0}
+  }}}"#).unwrap();
+  let clips = AnimationClips::from_json(&sheet).unwrap();
+  let mut animator = Animator::default();
+  animator.play(&clips, "death");
+
+  assert_eq!(None, animator.update(0.5));
+  assert_eq!(Some((0, 2 * 3 + 2)), animator.cell(2), "Once clip should stop on its last frame");
+  assert_eq!(Some(AnimationEvent::Finished), animator.update(0.9), "Clip should finish after the hold time");
+  assert_eq!(None, animator.update(1.0), "Finish event should only fire once");
+}
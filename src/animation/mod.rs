@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use json::JsonValue;
+use specs::prelude::{Read, WriteStorage};
+
+use crate::assets::AssetManager;
+use crate::data;
+use crate::error::GameResult;
+use crate::game::constants::SPRITE_SHEETS;
+use crate::graphics::DeltaTime;
+
+mod animation_test;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoopMode {
+  Loop,
+  Once,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationClip {
+  pub row: usize,
+  pub offset: usize,
+  pub direction_stride: usize,
+  pub frames: usize,
+  pub fps: f64,
+  pub mode: LoopMode,
+  pub hold: f64,
+}
+
+fn read_usize(clip: &JsonValue, key: &str, name: &str) -> Result<usize, String> {
+  clip[key].as_usize().ok_or_else(|| format!("Animation {} {} missing", name, key))
+}
+
+impl AnimationClip {
+  pub fn from_json(name: &str, clip: &JsonValue) -> Result<AnimationClip, String> {
+    let mode = match clip["loop"].as_str() {
+      Some("loop") | None => LoopMode::Loop,
+      Some("once") => LoopMode::Once,
+      Some(other) => return Err(format!("Animation {} unknown loop mode {}", name, other)),
+    };
+    let frames = read_usize(clip, "frames", name)?;
+    let fps = clip["fps"].as_f64().ok_or_else(|| format!("Animation {} fps missing", name))?;
+    if frames == 0 || fps <= 0.0 {
+      return Err(format!("Animation {} needs at least one frame and positive fps", name));
+    }
+    Ok(AnimationClip {
+      row: read_usize(clip, "row", name)?,
+      offset: read_usize(clip, "offset", name)?,
+      direction_stride: read_usize(clip, "direction_stride", name)?,
+      frames,
+      fps,
+      mode,
+      hold: clip["hold"].as_f64().unwrap_or(0.0),
+    })
+  }
+
+  pub fn cell(&self, direction: usize, frame: usize) -> (usize, usize) {
+    (self.row, self.offset + direction * self.direction_stride + frame.min(self.frames - 1))
+  }
+}
+
+#[derive(Clone, Default)]
+pub struct AnimationClips {
+  clips: HashMap<String, AnimationClip>,
+}
+
+impl AnimationClips {
+  pub fn from_json(sheet: &JsonValue) -> Result<AnimationClips, String> {
+    let clips = sheet["meta"]["animations"].entries()
+      .map(|(name, clip)| AnimationClip::from_json(name, clip).map(|c| (name.to_string(), c)))
+      .collect::<Result<HashMap<String, AnimationClip>, String>>()?;
+    Ok(AnimationClips { clips })
+  }
+
+  pub fn get(&self, name: &str) -> Option<&AnimationClip> {
+    self.clips.get(name)
+  }
+}
+
+#[derive(Default)]
+pub struct AnimationLibrary {
+  sheets: HashMap<String, AnimationClips>,
+  empty: AnimationClips,
+}
+
+impl AnimationLibrary {
+  pub fn load(assets: &AssetManager) -> GameResult<AnimationLibrary> {
+    let mut library = AnimationLibrary::default();
+    for (sheet, json_path) in SPRITE_SHEETS.iter() {
+      let sprite_sheet = data::load_sprite_sheet(assets, json_path)?;
+      library.sheets.insert(sheet.to_string(), sprite_sheet.animations);
+    }
+    Ok(library)
+  }
+
+  pub fn clips(&self, sheet: &str) -> &AnimationClips {
+    self.sheets.get(sheet).unwrap_or(&self.empty)
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AnimationEvent {
+  Finished,
+}
+
+#[derive(Clone, Default)]
+pub struct Animator {
+  name: String,
+  clip: Option<AnimationClip>,
+  frame: usize,
+  elapsed: f64,
+  is_finished: bool,
+}
+
+impl Animator {
+  pub fn play(&mut self, clips: &AnimationClips, name: &str) {
+    let clip = clips.get(name);
+    if self.name != name || self.clip.as_ref() != clip {
+      self.name = name.to_string();
+      self.clip = clip.cloned();
+      self.frame = 0;
+      self.elapsed = 0.0;
+      self.is_finished = false;
+    }
+  }
+
+  pub fn update(&mut self, delta: f64) -> Option<AnimationEvent> {
+    let clip = self.clip.as_ref()?;
+    if self.is_finished {
+      return None;
+    }
+    let frame_time = 1.0 / clip.fps;
+    self.elapsed += delta;
+    match clip.mode {
+      LoopMode::Loop => {
+        while self.elapsed >= frame_time {
+          self.elapsed -= frame_time;
+          self.frame = (self.frame + 1) % clip.frames;
+        }
+        None
+      }
+      LoopMode::Once => {
+        while self.frame + 1 < clip.frames && self.elapsed >= frame_time {
+          self.elapsed -= frame_time;
+          self.frame += 1;
+        }
+        if self.frame + 1 == clip.frames && self.elapsed >= frame_time + clip.hold {
+          self.is_finished = true;
+          Some(AnimationEvent::Finished)
+        } else {
+          None
+        }
+      }
+    }
+  }
+
+  pub fn cell(&self, direction: usize) -> Option<(usize, usize)> {
+    self.clip.as_ref().map(|clip| clip.cell(direction, self.frame))
+  }
+}
+
+impl specs::prelude::Component for Animator {
+  type Storage = specs::storage::VecStorage<Animator>;
+}
+
+pub struct AnimationSystem;
+
+impl<'a> specs::prelude::System<'a> for AnimationSystem {
+  type SystemData = (WriteStorage<'a, Animator>,
+                     Read<'a, DeltaTime>);
+
+  fn run(&mut self, (mut animators, d): Self::SystemData) {
+    use specs::join::Join;
+
+    for animator in (&mut animators).join() {
+      animator.update(d.0);
+    }
+  }
+}
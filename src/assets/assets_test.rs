@@ -0,0 +1,43 @@
+#[test]
+fn asset_kind_test() {
+  use crate::assets::{asset_kind, AssetKind};
+
+  assert_eq!(Some(AssetKind::Terrain), asset_kind("maps/tilemap.tmx"));
+  assert_eq!(Some(AssetKind::Terrain), asset_kind("maps/terrain.png"));
+  assert_eq!(Some(AssetKind::Sprites), asset_kind("zombie.json"));
+  assert_eq!(Some(AssetKind::Sprites), asset_kind("maps/house.png"));
+  assert_eq!(None, asset_kind("audio/pistol.ogg"), "Audio is read on demand and needs no reload");
+}
+
+#[test]
+fn asset_manager_test() {
+  use std::path::Path;
+
+  use crate::assets::AssetManager;
+
+  let assets = AssetManager::from_options(Some("mods/hinterland"));
+  assert_eq!(Path::new("mods/hinterland/maps/tilemap.tmx"), assets.path("maps/tilemap.tmx"));
+
+  let error = assets.read_string("missing.json").expect_err("Missing asset should fail to load");
+  assert!(error.to_string().contains("missing.json"), "Error should name the missing file");
+}
+
+#[test]
+fn asset_watcher_test() {
+  use std::fs;
+
+  use crate::assets::AssetWatcher;
+
+  let root = std::env::temp_dir().join(format!("hinterland_assets_test_{}", std::process::id()));
+  fs::create_dir_all(root.join("maps")).unwrap();
+  fs::write(root.join("zombie.json"), "{}").unwrap();
+
+  let mut watcher = AssetWatcher::new(&root);
+  assert!(watcher.changed_assets().is_empty(), "Nothing should change without writes");
+
+  fs::write(root.join("maps/house.png"), "").unwrap();
+  assert_eq!(vec!["maps/house.png"], watcher.changed_assets());
+  assert!(watcher.changed_assets().is_empty(), "Changes should be reported once");
+
+  fs::remove_dir_all(&root).unwrap();
+}
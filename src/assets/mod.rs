@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+use image::RgbaImage;
+use specs::prelude::Write;
+
+use crate::error::{GameError, GameResult};
+use crate::game::constants::{ASSET_DIR_ENV, ASSET_POLL_INTERVAL, DEFAULT_ASSET_DIR, MAP_FILE_PATH, TERRAIN_TEXTURE_PATH};
+use crate::graphics::AssetReload;
+
+mod assets_test;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AssetKind {
+  Sprites,
+  Terrain,
+}
+
+pub fn asset_kind(name: &str) -> Option<AssetKind> {
+  let name = name.replace('\\', "/");
+  if name == MAP_FILE_PATH || name == TERRAIN_TEXTURE_PATH {
+    return Some(AssetKind::Terrain);
+  }
+  match Path::new(&name).extension().and_then(|e| e.to_str()) {
+    Some("json") | Some("png") => Some(AssetKind::Sprites),
+    _ => None,
+  }
+}
+
+#[derive(Clone)]
+pub struct AssetManager {
+  root: PathBuf,
+}
+
+impl AssetManager {
+  pub fn new<P: Into<PathBuf>>(root: P) -> AssetManager {
+    AssetManager { root: root.into() }
+  }
+
+  pub fn from_options(asset_dir: Option<&str>) -> AssetManager {
+    match asset_dir {
+      Some(dir) => AssetManager::new(dir),
+      None => AssetManager::new(std::env::var(ASSET_DIR_ENV).unwrap_or_else(|_| DEFAULT_ASSET_DIR.to_string())),
+    }
+  }
+
+  pub fn root(&self) -> &Path {
+    &self.root
+  }
+
+  pub fn path(&self, name: &str) -> PathBuf {
+    self.root.join(name)
+  }
+
+  pub fn read_bytes(&self, name: &str) -> GameResult<Vec<u8>> {
+    let path = self.path(name);
+    fs::read(&path).map_err(|e| GameError::Io(path.display().to_string(), e))
+  }
+
+  pub fn read_string(&self, name: &str) -> GameResult<String> {
+    let path = self.path(name);
+    fs::read_to_string(&path).map_err(|e| GameError::Io(path.display().to_string(), e))
+  }
+
+  pub fn load_image(&self, name: &str) -> GameResult<RgbaImage> {
+    let path = self.path(name);
+    image::open(&path)
+      .map(|i| i.to_rgba())
+      .map_err(|e| GameError::Asset(path.display().to_string(), format!("image loading error {:?}", e)))
+  }
+}
+
+impl Default for AssetManager {
+  fn default() -> AssetManager {
+    AssetManager::new(DEFAULT_ASSET_DIR)
+  }
+}
+
+fn scan_files(dir: &Path, files: &mut HashMap<PathBuf, SystemTime>) {
+  if let Ok(entries) = fs::read_dir(dir) {
+    for entry in entries.filter_map(|e| e.ok()) {
+      let path = entry.path();
+      match entry.metadata() {
+        Ok(ref metadata) if metadata.is_dir() => scan_files(&path, files),
+        Ok(metadata) => {
+          if let Ok(modified) = metadata.modified() {
+            files.insert(path, modified);
+          }
+        }
+        Err(_) => (),
+      }
+    }
+  }
+}
+
+pub struct AssetWatcher {
+  root: PathBuf,
+  files: HashMap<PathBuf, SystemTime>,
+  last_poll: Instant,
+}
+
+impl AssetWatcher {
+  pub fn new(root: &Path) -> AssetWatcher {
+    let mut files = HashMap::new();
+    scan_files(root, &mut files);
+    AssetWatcher {
+      root: root.to_path_buf(),
+      files,
+      last_poll: Instant::now(),
+    }
+  }
+
+  pub fn poll(&mut self) -> Vec<String> {
+    if self.last_poll.elapsed().as_secs_f64() < ASSET_POLL_INTERVAL {
+      return Vec::new();
+    }
+    self.last_poll = Instant::now();
+    self.changed_assets()
+  }
+
+  pub fn changed_assets(&mut self) -> Vec<String> {
+    let mut files = HashMap::new();
+    scan_files(&self.root, &mut files);
+    let mut changed = files.iter()
+      .filter(|(path, modified)| self.files.get(*path) != Some(modified))
+      .filter_map(|(path, _)| path.strip_prefix(&self.root).ok())
+      .map(|path| path.to_string_lossy().replace('\\', "/"))
+      .collect::<Vec<String>>();
+    changed.sort();
+    self.files = files;
+    changed
+  }
+}
+
+pub struct AssetWatchSystem {
+  watcher: AssetWatcher,
+}
+
+impl AssetWatchSystem {
+  pub fn new(assets: &AssetManager) -> AssetWatchSystem {
+    println!("Watching {} for asset changes", assets.root().display());
+    AssetWatchSystem {
+      watcher: AssetWatcher::new(assets.root()),
+    }
+  }
+}
+
+impl<'a> specs::prelude::System<'a> for AssetWatchSystem {
+  type SystemData = Write<'a, AssetReload>;
+
+  fn run(&mut self, mut asset_reload: Self::SystemData) {
+    for name in self.watcher.poll() {
+      if let Some(kind) = asset_kind(&name) {
+        println!("Asset {} changed, reloading", name);
+        asset_reload.request(kind);
+      }
+    }
+  }
+}
@@ -1,11 +1,14 @@
-use std::{fs::File, io::BufReader};
+use std::{fs::File, io::BufReader, path::{Path, PathBuf}};
 
 use crossbeam_channel as channel;
 use rodio::Sink;
-use specs::prelude::ReadStorage;
+use specs::prelude::{Read, ReadStorage};
 
+use crate::assets::AssetManager;
 use crate::character::{CharacterDrawable, controls::CharacterInputState};
+use crate::error::{GameError, GameResult};
 use crate::game::constants::PISTOL_AUDIO_PATH;
+use crate::settings::Settings;
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum Effects {
@@ -15,37 +18,46 @@ pub enum Effects {
 
 pub struct AudioSystem {
   effects: Effects,
-  sink: Sink,
+  sink: Option<Sink>,
+  pistol_path: PathBuf,
   queue: channel::Receiver<Effects>,
 }
 
 impl AudioSystem {
-  pub fn new() -> (AudioSystem, channel::Sender<Effects>) {
+  pub fn new(assets: &AssetManager) -> (AudioSystem, channel::Sender<Effects>) {
     #[allow(deprecated)]
       let (tx, rx) = channel::unbounded();
-    let endpoint = rodio::default_output_device().unwrap();
+    let sink = rodio::default_output_device().map(|endpoint| Sink::new(&endpoint));
+    if sink.is_none() {
+      println!("{}", GameError::Audio("No output device found, running without audio".to_string()));
+    }
 
     (AudioSystem {
       effects: Effects::None,
-      sink: Sink::new(&endpoint),
+      sink,
+      pistol_path: assets.path(PISTOL_AUDIO_PATH),
       queue: rx,
     }, tx)
   }
 
-  fn play_effect(&mut self) {
-    let file = File::open(PISTOL_AUDIO_PATH).unwrap();
-    let pistol_data = rodio::Decoder::new(BufReader::new(file)).unwrap();
-    if self.sink.empty() {
-      self.sink.append(pistol_data);
+  fn play_effect(sink: &Sink, path: &Path) -> GameResult<()> {
+    if sink.empty() {
+      let file = File::open(path)
+        .map_err(|e| GameError::Io(path.display().to_string(), e))?;
+      let pistol_data = rodio::Decoder::new(BufReader::new(file))
+        .map_err(|e| GameError::Audio(format!("{} decoding error {:?}", path.display(), e)))?;
+      sink.append(pistol_data);
     }
+    Ok(())
   }
 }
 
 impl<'a> specs::prelude::System<'a> for AudioSystem {
   type SystemData = (ReadStorage<'a, CharacterInputState>,
-                     ReadStorage<'a, CharacterDrawable>);
+                     ReadStorage<'a, CharacterDrawable>,
+                     Read<'a, Settings>);
 
-  fn run(&mut self, (character_input, character_drawable): Self::SystemData) {
+  fn run(&mut self, (character_input, character_drawable, settings): Self::SystemData) {
     use specs::join::Join;
 
     while let Ok(effect) = self.queue.try_recv() {
@@ -55,9 +67,16 @@ impl<'a> specs::prelude::System<'a> for AudioSystem {
       }
     }
 
+    if let Some(ref sink) = self.sink {
+      sink.set_volume(settings.effects_volume());
+    }
+
     for (ci, cd) in (&character_input, &character_drawable).join() {
-      if let Effects::PistolFire = self.effects {
-        if ci.is_shooting && cd.stats.ammunition > 0 { self.play_effect() }
+      if self.effects == Effects::PistolFire && ci.is_shooting && cd.stats.ammunition > 0 {
+        if let Err(e) = self.sink.as_ref().map_or(Ok(()), |sink| AudioSystem::play_effect(sink, &self.pistol_path)) {
+          println!("{}, disabling audio", e);
+          self.sink = None;
+        }
       }
     }
   }
@@ -2,23 +2,28 @@ use std::f32;
 use std::f32::consts::PI;
 
 use cgmath::Point2;
-use specs::prelude::{Read, ReadStorage, WriteStorage};
+use specs::prelude::{Read, ReadStorage, Write, WriteStorage};
 
+use crate::assets::AssetManager;
 use crate::bullet::bullets::Bullets;
 use crate::bullet::collision::Collision;
 use crate::character::controls::CharacterInputState;
-use crate::game::constants::{ASPECT_RATIO, BULLET_SPEED, VIEW_DISTANCE};
-use crate::gfx_app::{ColorFormat, DepthFormat};
+use crate::error::{GameError, GameResult};
+use crate::game::constants::{ASPECT_RATIO, BULLET_COLOR, BULLET_SPEED, VIEW_DISTANCE};
+use crate::gfx_app::{DepthFormat, HdrColorFormat};
 use crate::graphics::{camera::CameraInputState, can_move, dimensions::{Dimensions, get_projection, get_view_matrix}};
 use crate::graphics::can_move_to_tile;
 use crate::graphics::mesh::PlainMesh;
-use crate::shaders::{bullet_pipeline, Position, Projection, Rotation};
+use crate::graphics::render_target::{RenderTargets, TargetViews};
+use crate::graphics::shader::{create_pipeline_simple, reload_pipeline, ShaderProgram};
+use crate::graphics::sprite_batch::{create_instance_buffer, SpriteBatch};
+use crate::particle::{EmitterKind, ParticleEvents};
+use crate::shaders::{bullet_pipeline, Position, Projection, SpriteInstance};
 
 pub mod bullets;
 pub mod collision;
 
-const SHADER_VERT: &[u8] = include_bytes!("../shaders/bullet.v.glsl");
-const SHADER_FRAG: &[u8] = include_bytes!("../shaders/bullet.f.glsl");
+const SHADER: ShaderProgram = ShaderProgram::new("bullet");
 
 const SCALING_FACTOR: f32 = 5.0 / 3.0;
 
@@ -26,7 +31,7 @@ const SCALING_FACTOR: f32 = 5.0 / 3.0;
 pub struct BulletDrawable {
   projection: Projection,
   pub position: Position,
-  pub rotation: Rotation,
+  pub rotation: f32,
   previous_position: Position,
   offset_delta: Position,
   pub movement_direction: Point2<f32>,
@@ -37,11 +42,10 @@ impl BulletDrawable {
   pub fn new(position: Position, movement_direction: Point2<f32>, direction: f32) -> BulletDrawable {
     let view = get_view_matrix(VIEW_DISTANCE);
     let projection = get_projection(view, ASPECT_RATIO);
-    let rotation = Rotation::new(direction * PI / 180.0);
     BulletDrawable {
       projection,
       position,
-      rotation,
+      rotation: direction * PI / 180.0,
       previous_position: Position::origin(),
       offset_delta: Position::origin(),
       movement_direction,
@@ -50,6 +54,9 @@ impl BulletDrawable {
   }
 
   pub fn update(&mut self, world_to_clip: &Projection, ci: &CharacterInputState) {
+    if self.status != Collision::Flying {
+      return;
+    }
     self.projection = *world_to_clip;
 
     self.offset_delta =
@@ -81,42 +88,62 @@ impl BulletDrawable {
 
 pub struct BulletDrawSystem<R: gfx::Resources> {
   bundle: gfx::pso::bundle::Bundle<R, bullet_pipeline::Data<R>>,
+  batch: SpriteBatch,
 }
 
 impl<R: gfx::Resources> BulletDrawSystem<R> {
   pub fn new<F>(factory: &mut F,
-                rtv: gfx::handle::RenderTargetView<R, ColorFormat>,
-                dsv: gfx::handle::DepthStencilView<R, DepthFormat>) -> BulletDrawSystem<R>
+                rtv: gfx::handle::RenderTargetView<R, HdrColorFormat>,
+                dsv: gfx::handle::DepthStencilView<R, DepthFormat>,
+                assets: &AssetManager) -> GameResult<BulletDrawSystem<R>>
     where F: gfx::Factory<R> {
     use gfx::traits::FactoryExt;
 
     let mesh = PlainMesh::new_with_data(factory, Point2::new(2.4, 0.8), None, None, None);
 
-    let pso = factory.create_pipeline_simple(SHADER_VERT, SHADER_FRAG, bullet_pipeline::new())
-      .expect("Bullet shader loading error");
+    let pso = create_pipeline_simple(factory, &SHADER.load(assets)?, bullet_pipeline::new())
+      .map_err(GameError::Shader)?;
 
     let pipeline_data = bullet_pipeline::Data {
       vbuf: mesh.vertex_buffer,
+      instances: create_instance_buffer(factory),
       projection_cb: factory.create_constant_buffer(1),
-      position_cb: factory.create_constant_buffer(1),
-      rotation_cb: factory.create_constant_buffer(1),
       out_color: rtv,
       out_depth: dsv,
     };
 
-    BulletDrawSystem {
+    Ok(BulletDrawSystem {
       bundle: gfx::Bundle::new(mesh.slice, pso, pipeline_data),
-    }
+      batch: SpriteBatch::default(),
+    })
+  }
+
+  pub fn reload_shaders<F>(&mut self, factory: &mut F, assets: &AssetManager) -> Result<(), String>
+    where F: gfx::Factory<R> {
+    reload_pipeline(factory, assets, SHADER, bullet_pipeline::new(), &mut self.bundle.pso)
+  }
+
+  pub fn push(&mut self, drawable: &BulletDrawable, depth: f32) {
+    let instance = SpriteInstance {
+      rotation: drawable.rotation,
+      tint: BULLET_COLOR,
+      ..SpriteInstance::new(drawable.position, depth)
+    };
+    self.batch.push(&drawable.projection, instance);
   }
 
-  pub fn draw<C>(&mut self,
-                 drawable: &BulletDrawable,
-                 encoder: &mut gfx::Encoder<R, C>)
+  pub fn draw<C>(&mut self, encoder: &mut gfx::Encoder<R, C>)
     where C: gfx::CommandBuffer<R> {
-    encoder.update_constant_buffer(&self.bundle.data.projection_cb, &drawable.projection);
-    encoder.update_constant_buffer(&self.bundle.data.position_cb, &drawable.position);
-    encoder.update_constant_buffer(&self.bundle.data.rotation_cb, &drawable.rotation);
-    self.bundle.encode(encoder);
+    if let Some(count) = self.batch.upload(&self.bundle.data.projection_cb, &self.bundle.data.instances, encoder) {
+      self.bundle.slice.instances = Some((count, 0));
+      self.bundle.encode(encoder);
+    }
+  }
+}
+
+impl<R: gfx::Resources> RenderTargets<R, HdrColorFormat> for BulletDrawSystem<R> {
+  fn targets(&mut self) -> Vec<TargetViews<'_, R, HdrColorFormat>> {
+    vec![(&mut self.bundle.data.out_color, &mut self.bundle.data.out_depth)]
   }
 }
 
@@ -126,16 +153,21 @@ impl<'a> specs::prelude::System<'a> for PreDrawSystem {
   type SystemData = (ReadStorage<'a, CameraInputState>,
                      WriteStorage<'a, Bullets>,
                      ReadStorage<'a, CharacterInputState>,
+                     Write<'a, ParticleEvents>,
                      Read<'a, Dimensions>);
 
-  fn run(&mut self, (camera_input, mut bullets, character_input, dim): Self::SystemData) {
+  fn run(&mut self, (camera_input, mut bullets, character_input, mut particle_events, dim): Self::SystemData) {
     use specs::join::Join;
 
     for (camera, bs, ci) in (&camera_input, &mut bullets, &character_input).join() {
       let world_to_clip = dim.world_to_projection(camera);
 
       for b in &mut bs.bullets {
+        let was_flying = b.status == Collision::Flying;
         b.update(&world_to_clip, ci);
+        if was_flying && b.status == Collision::Hit {
+          particle_events.emit(EmitterKind::Dust, ci.movement - b.position, Point2::new(-b.movement_direction.x, b.movement_direction.y));
+        }
       }
     }
   }
@@ -1,7 +1,11 @@
+use crate::game::constants::CHARACTER_MAX_HEALTH;
+
 #[derive(Clone, Default)]
 pub struct CharacterStats {
   pub ammunition: usize,
   pub magazines: usize,
+  pub health: f32,
+  pub is_invulnerable: bool,
 }
 
 impl CharacterStats {
@@ -9,6 +13,15 @@ impl CharacterStats {
     CharacterStats {
       ammunition: 10,
       magazines: 1,
+      health: CHARACTER_MAX_HEALTH,
+      is_invulnerable: cfg!(feature = "godmode"),
+    }
+  }
+
+  pub fn take_damage(&mut self, damage: f32) -> bool {
+    if !self.is_invulnerable {
+      self.health = (self.health - damage).max(0.0);
     }
+    self.health <= 0.0
   }
 }
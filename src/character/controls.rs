@@ -1,13 +1,15 @@
+use cgmath::Point2;
 use crossbeam_channel as channel;
 use specs::prelude::{Read, WriteStorage};
 
 use crate::character::CharacterDrawable;
-use crate::game::constants::{CHARACTER_X_SPEED, CHARACTER_Y_SPEED};
-use crate::graphics::{camera::CameraInputState, can_move_to_tile, DeltaTime, orientation::{Orientation, Stance}};
+use crate::game::constants::{CHARACTER_ACCELERATION, CHARACTER_DECELERATION, CHARACTER_MAX_SPEED, MAGAZINE_CAPACITY};
+use crate::graphics::{accelerate_towards, camera::CameraInputState, can_move_to_tile, DeltaTime, input_to_velocity, orientation::{Orientation, Stance}, velocity_to_orientation};
 use crate::shaders::Position;
 
 pub struct CharacterInputState {
   pub movement: Position,
+  pub velocity: Point2<f32>,
   pub orientation: Orientation,
   pub is_colliding: bool,
   pub is_shooting: bool,
@@ -17,56 +19,32 @@ impl CharacterInputState {
   pub fn new() -> CharacterInputState {
     CharacterInputState {
       movement: Position::origin(),
+      velocity: Point2::new(0.0, 0.0),
       orientation: Orientation::Normal,
       is_colliding: false,
       is_shooting: false,
     }
   }
 
-  pub fn update(&mut self, camera: &mut CameraInputState, css: &CharacterControlSystem) {
-    if css.y_move.is_none() && css.x_move.is_none() {
-      self.orientation = Orientation::Normal;
-    } else if css.x_move.is_none() {                  // Horizontal/vertical movement
-      if let Some(y) = css.y_move {
-        let vertical_movement = self.movement + Position::new(0.0, y);
-        if !self.is_colliding || can_move_to_tile(vertical_movement) {
-          self.movement = vertical_movement;
-          camera.movement = camera.movement - Position::new(0.0, y);
-          self.orientation = match y {
-            y if y < 0.0 => Orientation::Up,
-            y if y > 0.0 => Orientation::Down,
-            _ => Orientation::Normal,
-          };
-        }
-      }
-    } else if let Some(x) = css.x_move {        // Diagonal movement
-      let horizontal_move = self.movement + Position::new(x, 0.0);
-      if let Some(y) = css.y_move {
-        let horizontal_movement = Position::new(x / 1.5, 0.0);
-        let vertical_movement = Position::new(0.0, y / 1.666);
-        if !self.is_colliding || can_move_to_tile(self.movement + horizontal_movement + vertical_movement) {
-          self.movement = self.movement + horizontal_movement + vertical_movement;
-          camera.movement = camera.movement + horizontal_movement - vertical_movement;
-
-          self.orientation = match (x, y) {
-            (x, y) if x > 0.0 && y > 0.0 => Orientation::DownLeft,
-            (x, y) if x > 0.0 && y < 0.0 => Orientation::UpLeft,
-            (x, y) if x < 0.0 && y > 0.0 => Orientation::DownRight,
-            (x, y) if x < 0.0 && y < 0.0 => Orientation::UpRight,
-            _ => Orientation::Normal,
-          };
-        }
-      } else if css.y_move.is_none() && !self.is_colliding || can_move_to_tile(horizontal_move) {
-        let horizontal_movement = Position::new(x, 0.0);
-        self.movement = self.movement + horizontal_movement;
-        camera.movement = camera.movement + horizontal_movement;
-        self.orientation = match x {
-          x if x < 0.0 => Orientation::Right,
-          x if x > 0.0 => Orientation::Left,
-          _ => Orientation::Normal,
-        };
-      }
+  pub fn update(&mut self, camera: &mut CameraInputState, css: &CharacterControlSystem, delta: f32) {
+    let target_velocity = input_to_velocity(css.x_move, css.y_move, CHARACTER_MAX_SPEED);
+    let rate = if css.x_move.is_none() && css.y_move.is_none() {
+      CHARACTER_DECELERATION
+    } else {
+      CHARACTER_ACCELERATION
+    };
+    self.velocity = accelerate_towards(self.velocity, target_velocity, rate, delta);
+
+    let step = Position::new(self.velocity.x * delta, self.velocity.y * delta);
+    let next_movement = self.movement + step;
+    if !self.is_colliding || can_move_to_tile(next_movement) {
+      self.movement = next_movement;
+      camera.movement = camera.movement + Position::new(step.x(), -step.y());
+    } else {
+      self.velocity = Point2::new(0.0, 0.0);
     }
+
+    self.orientation = velocity_to_orientation(self.velocity);
     self.is_shooting = css.is_ctrl_pressed;
   }
 }
@@ -81,6 +59,7 @@ impl specs::prelude::Component for CharacterInputState {
   type Storage = specs::storage::VecStorage<CharacterInputState>;
 }
 
+#[derive(Clone, Copy)]
 pub enum CharacterControl {
   Left,
   Right,
@@ -98,7 +77,6 @@ pub struct CharacterControlSystem {
   queue: channel::Receiver<CharacterControl>,
   x_move: Option<f32>,
   y_move: Option<f32>,
-  cool_down: f64,
   is_ctrl_pressed: bool,
   is_reloading: bool,
 }
@@ -110,7 +88,6 @@ impl CharacterControlSystem {
       queue: rx,
       x_move: None,
       y_move: None,
-      cool_down: 1.0,
       is_ctrl_pressed: false,
       is_reloading: false,
     }, tx)
@@ -126,35 +103,30 @@ impl<'a> specs::prelude::System<'a> for CharacterControlSystem {
   fn run(&mut self, (mut character_input, mut character, mut camera_input, d): Self::SystemData) {
     use specs::join::Join;
 
-    let delta = d.0;
+    let delta = d.0 as f32;
 
-    if self.cool_down == 0.0 {
-      self.cool_down += 0.1;
-    } else {
-      self.cool_down = (self.cool_down - delta).max(0.0);
-      while let Ok(control) = self.queue.try_recv() {
-        match control {
-          CharacterControl::Up => self.y_move = Some(-CHARACTER_Y_SPEED),
-          CharacterControl::Down => self.y_move = Some(CHARACTER_Y_SPEED),
-          CharacterControl::YMoveStop => self.y_move = None,
-          CharacterControl::Right => self.x_move = Some(-CHARACTER_X_SPEED),
-          CharacterControl::Left => self.x_move = Some(CHARACTER_X_SPEED),
-          CharacterControl::XMoveStop => self.x_move = None,
-          CharacterControl::CtrlPressed => self.is_ctrl_pressed = true,
-          CharacterControl::CtrlReleased => self.is_ctrl_pressed = false,
-          CharacterControl::ReloadPressed => self.is_reloading = true,
-          CharacterControl::ReloadReleased => self.is_reloading = false,
-        }
+    while let Ok(control) = self.queue.try_recv() {
+      match control {
+        CharacterControl::Up => self.y_move = Some(-1.0),
+        CharacterControl::Down => self.y_move = Some(1.0),
+        CharacterControl::YMoveStop => self.y_move = None,
+        CharacterControl::Right => self.x_move = Some(-1.0),
+        CharacterControl::Left => self.x_move = Some(1.0),
+        CharacterControl::XMoveStop => self.x_move = None,
+        CharacterControl::CtrlPressed => self.is_ctrl_pressed = true,
+        CharacterControl::CtrlReleased => self.is_ctrl_pressed = false,
+        CharacterControl::ReloadPressed => self.is_reloading = true,
+        CharacterControl::ReloadReleased => self.is_reloading = false,
       }
+    }
 
-      for (ci, c, camera) in (&mut character_input, &mut character, &mut camera_input).join() {
-        if c.stance != Stance::NormalDeath {
-          ci.update(camera, self);
-        }
-        if self.is_reloading && c.stats.magazines > 0 && c.stats.ammunition < 10 {
-          c.stats.ammunition = 10;
-          c.stats.magazines -= 1;
-        }
+    for (ci, c, camera) in (&mut character_input, &mut character, &mut camera_input).join() {
+      if c.stance != Stance::NormalDeath {
+        ci.update(camera, self, delta);
+      }
+      if self.is_reloading && c.stats.magazines > 0 && c.stats.ammunition < MAGAZINE_CAPACITY {
+        c.stats.ammunition = MAGAZINE_CAPACITY;
+        c.stats.magazines -= 1;
       }
     }
   }
@@ -1,24 +1,35 @@
 use cgmath::Point2;
 use specs::prelude::{Read, ReadStorage, WriteStorage};
+use specs::shred::World;
+use specs::world::WorldExt;
 
+use crate::assets::AssetManager;
+use crate::bullet::bullets::Bullets;
+use crate::animation::{AnimationLibrary, Animator};
 use crate::character::{character_stats::CharacterStats, controls::CharacterInputState};
-use crate::critter::{CharacterSprite, CritterData};
+use crate::console::registry::{CommandRegistry, CommandResult, parse_arg};
+use crate::critter::sheet_rows;
 use crate::data;
-use crate::game::constants::{AMMO_POSITIONS, ASPECT_RATIO, CHARACTER_SHEET_TOTAL_WIDTH, RUN_SPRITE_OFFSET, SPRITE_OFFSET, VIEW_DISTANCE, SMALL_HILLS};
-use crate::gfx_app::{ColorFormat, DepthFormat};
+use crate::error::{GameError, GameResult};
+use crate::game::constants::{AMMO_POSITIONS, ASPECT_RATIO, CHARACTER_JSON_PATH, CHARACTER_MAX_HEALTH, CHARACTER_SHEET, MAGAZINE_CAPACITY, VIEW_DISTANCE, SMALL_HILLS};
+use crate::gfx_app::{DepthFormat, HdrColorFormat};
 use crate::gfx_app::mouse_controls::MouseInputState;
-use crate::graphics::{camera::CameraInputState, dimensions::{Dimensions, get_projection, get_view_matrix}, get_orientation_from_center, orientation::{Orientation, Stance}, overlaps, texture::load_texture, check_terrain_elevation};
+use crate::graphics::{camera::CameraInputState, can_move_to_tile, dimensions::{Dimensions, get_projection, get_view_matrix}, get_orientation_from_center, orientation::{Orientation, Stance}, overlaps, check_terrain_elevation, tile_to_coords};
+use crate::graphics::atlas::{SpriteAtlas, TextureAtlas};
 use crate::graphics::mesh::{RectangularTexturedMesh, Geometry};
+use crate::graphics::render_target::{RenderTargets, TargetViews};
+use crate::graphics::shader::{create_pipeline_simple, reload_pipeline, ShaderProgram};
+use crate::graphics::sprite_batch::{create_instance_buffer, SpriteBatch};
 use crate::graphics::texture::Texture;
-use crate::shaders::{CharacterSheet, critter_pipeline, Position, Projection};
+use crate::lighting::LightBuffers;
+use crate::shaders::{critter_pipeline, Position, Projection, SpriteInstance};
 use crate::terrain_object::{terrain_objects::TerrainObjects, TerrainObjectDrawable, TerrainTexture};
 use crate::zombie::{ZombieDrawable, zombies::Zombies};
 
 pub mod controls;
 mod character_stats;
 
-const SHADER_VERT: &[u8] = include_bytes!("../shaders/character.v.glsl");
-const SHADER_FRAG: &[u8] = include_bytes!("../shaders/character.f.glsl");
+const SHADER: ShaderProgram = ShaderProgram::lit("character");
 
 #[derive(Clone)]
 pub struct CharacterDrawable {
@@ -49,6 +60,10 @@ impl CharacterDrawable {
                 dimensions: &Dimensions, objs: &mut Vec<TerrainObjectDrawable>, zombies: &[ZombieDrawable]) {
     self.projection = *world_to_clip;
 
+    if self.stance == Stance::NormalDeath {
+      return;
+    }
+
     self.position.position[1] = check_terrain_elevation(ci.movement - self.position, &SMALL_HILLS);
 
     fn zombie_not_dead(z: &ZombieDrawable) -> bool {
@@ -60,17 +75,17 @@ impl CharacterDrawable {
       self.ammo_pick_up(ci.movement, objs, idx);
     }
 
-    if !cfg!(feature = "godmode") &&
-      zombies.iter()
-        .any(|z|
-          zombie_not_dead(z) &&
-            overlaps(ci.movement,
-                     ci.movement - z.position,
-                     15.0,
-                     30.0)) {
+    let is_touching_zombie = zombies.iter()
+      .any(|z|
+        zombie_not_dead(z) &&
+          overlaps(ci.movement,
+                   ci.movement - z.position,
+                   15.0,
+                   30.0));
+    if is_touching_zombie && self.stats.take_damage(CHARACTER_MAX_HEALTH) {
       self.stance = Stance::NormalDeath;
       println!("Player died");
-      std::process::exit(0);
+      return;
     }
 
     if ci.is_shooting && mouse_input.left_click_point.is_some() && !ci.is_colliding {
@@ -84,6 +99,19 @@ impl CharacterDrawable {
     }
   }
 
+  pub fn orientation(&self) -> Orientation {
+    self.orientation
+  }
+
+  fn animation_clip(&self) -> &'static str {
+    match self.stance {
+      Stance::Walking if self.orientation == Orientation::Normal => "idle",
+      Stance::Walking => "run",
+      Stance::Firing => "fire",
+      _ => "aim",
+    }
+  }
+
   fn ammo_pick_up(&mut self, movement: Position, objs: &mut Vec<TerrainObjectDrawable>, idx: usize) {
     if objs.len() > idx && objs[idx].object_type == TerrainTexture::Ammo && overlaps(movement, movement - objs[idx].position, 20.0, 20.0) {
       self.stats.magazines = 2;
@@ -102,77 +130,156 @@ impl specs::prelude::Component for CharacterDrawable {
   type Storage = specs::storage::VecStorage<CharacterDrawable>;
 }
 
+pub fn register_commands(registry: &mut CommandRegistry) {
+  registry.register("give", GIVE_USAGE, give);
+  registry.register("god", "god", god);
+  registry.register("teleport", TELEPORT_USAGE, teleport);
+}
+
+const GIVE_USAGE: &str = "give ammo [magazines]";
+const TELEPORT_USAGE: &str = "teleport <tile x> <tile y>";
+
+fn give(world: &mut World, args: &[&str]) -> CommandResult {
+  use specs::join::Join;
+
+  if args.first() != Some(&"ammo") {
+    return Err(format!("Usage: {}", GIVE_USAGE));
+  }
+  let magazines = if args.len() > 1 { parse_arg::<usize>(args, 1, GIVE_USAGE)? } else { 1 };
+  let mut character = world.write_storage::<CharacterDrawable>();
+  for c in (&mut character).join() {
+    c.stats.ammunition = MAGAZINE_CAPACITY;
+    c.stats.magazines += magazines;
+  }
+  Ok(format!("Added {} magazines", magazines))
+}
+
+fn god(world: &mut World, _args: &[&str]) -> CommandResult {
+  use specs::join::Join;
+
+  let mut character = world.write_storage::<CharacterDrawable>();
+  let mut is_invulnerable = false;
+  for c in (&mut character).join() {
+    c.stats.is_invulnerable = !c.stats.is_invulnerable;
+    is_invulnerable = c.stats.is_invulnerable;
+  }
+  Ok(format!("God mode {}", if is_invulnerable { "on" } else { "off" }))
+}
+
+fn teleport(world: &mut World, args: &[&str]) -> CommandResult {
+  use specs::join::Join;
+
+  let tile = Point2::new(parse_arg::<i32>(args, 0, TELEPORT_USAGE)?, parse_arg::<i32>(args, 1, TELEPORT_USAGE)?);
+  let movement = tile_to_coords(Point2::new(tile.x as f32 + 0.5, tile.y as f32 + 0.5));
+  if !can_move_to_tile(movement) {
+    return Err(format!("Tile {}, {} is blocked", tile.x, tile.y));
+  }
+
+  let mut character_input = world.write_storage::<CharacterInputState>();
+  let mut camera_input = world.write_storage::<CameraInputState>();
+  let mut bullets = world.write_storage::<Bullets>();
+  for (ci, camera, bs) in (&mut character_input, &mut camera_input, &mut bullets).join() {
+    ci.movement = movement;
+    ci.velocity = Point2::new(0.0, 0.0);
+    camera.movement = Position::new(movement.x(), -movement.y());
+    bs.bullets.clear();
+  }
+  Ok(format!("Teleported to tile {}, {}", tile.x, tile.y))
+}
+
 pub struct CharacterDrawSystem<R: gfx::Resources> {
   bundle: gfx::pso::bundle::Bundle<R, critter_pipeline::Data<R>>,
-  data: Vec<CritterData>,
+  frames: Vec<Vec<[f32; 4]>>,
+  batch: SpriteBatch,
+}
+
+fn character_frames(atlas: &TextureAtlas, assets: &AssetManager) -> GameResult<Vec<Vec<[f32; 4]>>> {
+  data::load_sprite_sheet(assets, CHARACTER_JSON_PATH)
+    .map(|sheet| sheet_rows(atlas, CHARACTER_SHEET, &sheet.frames))
 }
 
 impl<R: gfx::Resources> CharacterDrawSystem<R> {
   pub fn new<F>(factory: &mut F,
-                rtv: gfx::handle::RenderTargetView<R, ColorFormat>,
-                dsv: gfx::handle::DepthStencilView<R, DepthFormat>) -> CharacterDrawSystem<R>
+                rtv: gfx::handle::RenderTargetView<R, HdrColorFormat>,
+                dsv: gfx::handle::DepthStencilView<R, DepthFormat>,
+                light_buffers: &LightBuffers<R>,
+                sprite_atlas: &SpriteAtlas<R>,
+                assets: &AssetManager) -> GameResult<CharacterDrawSystem<R>>
     where F: gfx::Factory<R> {
     use gfx::traits::FactoryExt;
 
-    let charter_bytes = &include_bytes!("../../assets/character.png")[..];
-    let char_texture = load_texture(factory, charter_bytes);
-
     let rect_mesh =
-      RectangularTexturedMesh::new(factory, Texture::new(char_texture, None), Geometry::Rectangle, Point2::new(20.0, 28.0), None, None, None);
+      RectangularTexturedMesh::new(factory, Texture::new(sprite_atlas.texture.clone(), None), Geometry::Rectangle, Point2::new(20.0, 28.0), None, None, None);
 
-    let pso = factory.create_pipeline_simple(SHADER_VERT, SHADER_FRAG, critter_pipeline::new())
-      .expect("Character shader loading error");
+    let pso = create_pipeline_simple(factory, &SHADER.load(assets)?, critter_pipeline::new())
+      .map_err(GameError::Shader)?;
 
     let pipeline_data = critter_pipeline::Data {
       vbuf: rect_mesh.mesh.vertex_buffer,
+      instances: create_instance_buffer(factory),
       projection_cb: factory.create_constant_buffer(1),
-      position_cb: factory.create_constant_buffer(1),
-      character_sprite_cb: factory.create_constant_buffer(1),
       charactersheet: (rect_mesh.mesh.texture.raw, factory.create_sampler_linear()),
+      lights: light_buffers.lights.clone(),
+      light_locals: light_buffers.locals.clone(),
       out_color: rtv,
       out_depth: dsv,
     };
 
-    let data = data::load_character();
-
-    CharacterDrawSystem {
+    Ok(CharacterDrawSystem {
       bundle: gfx::Bundle::new(rect_mesh.mesh.slice, pso, pipeline_data),
-      data,
+      frames: character_frames(&sprite_atlas.atlas, assets)?,
+      batch: SpriteBatch::default(),
+    })
+  }
+
+  pub fn reload_shaders<F>(&mut self, factory: &mut F, assets: &AssetManager) -> Result<(), String>
+    where F: gfx::Factory<R> {
+    reload_pipeline(factory, assets, SHADER, critter_pipeline::new(), &mut self.bundle.pso)
+  }
+
+  pub fn reload_data(&mut self, atlas: &TextureAtlas, assets: &AssetManager) {
+    match character_frames(atlas, assets) {
+      Ok(frames) => self.frames = frames,
+      Err(e) => println!("Character sprite reload error {}", e),
     }
   }
 
-  fn get_next_sprite(&self, character_idx: usize, character_fire_idx: usize, drawable: &mut CharacterDrawable) -> CharacterSheet {
-    let sprite_idx =
+  fn get_next_sprite(&self, drawable: &mut CharacterDrawable, animator: &Animator) -> [f32; 4] {
+    let direction =
       if drawable.orientation == Orientation::Normal && drawable.stance == Stance::Walking {
-        drawable.direction as usize * 28 + RUN_SPRITE_OFFSET
+        drawable.direction
       } else if drawable.stance == Stance::Walking {
         drawable.direction = drawable.orientation;
-        drawable.orientation as usize * 28 + character_idx + RUN_SPRITE_OFFSET
+        drawable.orientation
       } else {
-        drawable.orientation as usize * 8 + character_fire_idx
-      } as usize;
-
-    let elements_x = CHARACTER_SHEET_TOTAL_WIDTH / (self.data[sprite_idx].data[2] + SPRITE_OFFSET);
-    CharacterSheet {
-      x_div: elements_x,
-      y_div: 0.0,
-      row_idx: 0,
-      index: sprite_idx as f32,
-    }
+        drawable.orientation
+      };
+
+    animator.cell(direction as usize)
+      .and_then(|(row, cell)| self.frames.get(row)?.get(cell).cloned())
+      .unwrap_or([0.0, 0.0, 0.0, 0.0])
+  }
+
+  pub fn push(&mut self, mut drawable: &mut CharacterDrawable, animator: &Animator, depth: f32) {
+    let instance = SpriteInstance {
+      sprite: self.get_next_sprite(&mut drawable, animator),
+      ..SpriteInstance::new(drawable.position, depth)
+    };
+    self.batch.push(&drawable.projection, instance);
   }
 
-  pub fn draw<C>(&mut self,
-                 mut drawable: &mut CharacterDrawable,
-                 character: &CharacterSprite,
-                 encoder: &mut gfx::Encoder<R, C>)
+  pub fn draw<C>(&mut self, encoder: &mut gfx::Encoder<R, C>)
     where C: gfx::CommandBuffer<R> {
-    encoder.update_constant_buffer(&self.bundle.data.projection_cb, &drawable.projection);
-    encoder.update_constant_buffer(&self.bundle.data.position_cb, &drawable.position);
-    encoder.update_constant_buffer(&self.bundle.data.character_sprite_cb,
-                                   &self.get_next_sprite(character.character_idx,
-                                                         character.character_fire_idx,
-                                                         &mut drawable));
-    self.bundle.encode(encoder);
+    if let Some(count) = self.batch.upload(&self.bundle.data.projection_cb, &self.bundle.data.instances, encoder) {
+      self.bundle.slice.instances = Some((count, 0));
+      self.bundle.encode(encoder);
+    }
+  }
+}
+
+impl<R: gfx::Resources> RenderTargets<R, HdrColorFormat> for CharacterDrawSystem<R> {
+  fn targets(&mut self) -> Vec<TargetViews<'_, R, HdrColorFormat>> {
+    vec![(&mut self.bundle.data.out_color, &mut self.bundle.data.out_depth)]
   }
 }
 
@@ -185,15 +292,18 @@ impl<'a> specs::prelude::System<'a> for PreDrawSystem {
                      ReadStorage<'a, MouseInputState>,
                      WriteStorage<'a, TerrainObjects>,
                      ReadStorage<'a, Zombies>,
+                     WriteStorage<'a, Animator>,
+                     Read<'a, AnimationLibrary>,
                      Read<'a, Dimensions>);
 
-  fn run(&mut self, (mut character, camera_input, character_input, mouse_input, mut terrain_objects, zombies, dim): Self::SystemData) {
+  fn run(&mut self, (mut character, camera_input, character_input, mouse_input, mut terrain_objects, zombies, mut animators, animations, dim): Self::SystemData) {
     use specs::join::Join;
 
-    for (c, camera, ci, mi, to, zs) in
-        (&mut character, &camera_input, &character_input, &mouse_input, &mut terrain_objects, &zombies).join() {
+    for (c, camera, ci, mi, to, zs, animator) in
+        (&mut character, &camera_input, &character_input, &mouse_input, &mut terrain_objects, &zombies, &mut animators).join() {
       let world_to_clip = dim.world_to_projection(camera);
       c.update(&world_to_clip, ci, mi, &dim, &mut to.objects, &zs.zombies);
+      animator.play(animations.clips(CHARACTER_SHEET), c.animation_clip());
     }
   }
 }
@@ -0,0 +1,70 @@
+#[test]
+fn history_navigation_test() {
+  use crate::console::{Console, ConsoleControl};
+  use crate::console::registry::CommandRegistry;
+
+  let mut console = Console::new(&CommandRegistry::new());
+  for line in &["god", "give ammo"] {
+    for c in line.chars().chain("\r".chars()) {
+      console.apply(ConsoleControl::Character(c));
+    }
+  }
+  assert_eq!(vec!["god", "give ammo"], console.take_pending());
+
+  console.apply(ConsoleControl::HistoryPrevious);
+  assert_eq!("give ammo", console.input);
+  console.apply(ConsoleControl::HistoryPrevious);
+  console.apply(ConsoleControl::HistoryPrevious);
+  assert_eq!("god", console.input, "History should stop at the oldest entry");
+  console.apply(ConsoleControl::HistoryNext);
+  console.apply(ConsoleControl::HistoryNext);
+  assert_eq!("", console.input, "Stepping past the newest entry should clear input");
+}
+
+#[test]
+fn autocomplete_test() {
+  use specs::shred::World;
+  use crate::console::{Console, ConsoleControl};
+  use crate::console::registry::{CommandRegistry, CommandResult};
+
+  fn noop(_world: &mut World, _args: &[&str]) -> CommandResult {
+    Ok(String::new())
+  }
+
+  let mut registry = CommandRegistry::new();
+  registry.register("spawn", "spawn", noop);
+  registry.register("teleport", "teleport", noop);
+  registry.register("timescale", "timescale", noop);
+  let mut console = Console::new(&registry);
+
+  console.apply(ConsoleControl::Character('s'));
+  console.apply(ConsoleControl::Complete);
+  assert_eq!("spawn ", console.input, "Unique match should complete the command");
+
+  console.input = "t".to_string();
+  console.apply(ConsoleControl::Complete);
+  assert_eq!("t", console.input, "Ambiguous match should keep the common prefix");
+
+  console.input = "te".to_string();
+  console.apply(ConsoleControl::Complete);
+  assert_eq!("teleport ", console.input);
+}
+
+#[test]
+fn execute_command_test() {
+  use specs::shred::World;
+  use specs::world::WorldExt;
+  use crate::console::registry::{CommandRegistry, CommandResult, parse_arg};
+
+  fn double(_world: &mut World, args: &[&str]) -> CommandResult {
+    parse_arg::<u32>(args, 0, "double <n>").map(|n| (n * 2).to_string())
+  }
+
+  let mut world: World = WorldExt::new();
+  let mut registry = CommandRegistry::new();
+  registry.register("double", "double <n>", double);
+
+  assert_eq!(Ok("8".to_string()), registry.execute(&mut world, "double 4"));
+  assert_eq!(Err("Usage: double <n>".to_string()), registry.execute(&mut world, "double four"));
+  assert_eq!(Err("Unknown command triple".to_string()), registry.execute(&mut world, "triple 4"));
+}
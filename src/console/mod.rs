@@ -0,0 +1,261 @@
+use std::collections::VecDeque;
+
+use cgmath::Point2;
+use crossbeam_channel as channel;
+use specs::prelude::{Read, Write};
+use specs::shred::World;
+use specs::world::WorldExt;
+
+use crate::assets::AssetKind;
+use crate::console::registry::{CommandRegistry, CommandResult, parse_arg};
+use crate::game::constants::{CONSOLE_BACKGROUND_COLOR, CONSOLE_ERROR_COLOR, CONSOLE_FONT_SIZE, CONSOLE_HEIGHT, CONSOLE_HISTORY_SIZE,
+                             CONSOLE_LINE_HEIGHT, CONSOLE_OUTPUT_LINES, CONSOLE_TEXT_COLOR, MAX_TIME_SCALE};
+use crate::graphics::{AssetReload, dimensions::Dimensions, TimeScale};
+use crate::hud::layout::{Anchor, ScreenLayout, UiRect};
+use crate::hud::TextDrawable;
+use crate::hud::ui::UiBar;
+
+pub mod registry;
+mod console_test;
+
+const PROMPT: &str = "> ";
+
+#[derive(Clone, Copy)]
+pub enum ConsoleControl {
+  Toggle,
+  Character(char),
+  HistoryPrevious,
+  HistoryNext,
+  Complete,
+}
+
+#[derive(Default)]
+pub struct Console {
+  is_open: bool,
+  input: String,
+  history: Vec<String>,
+  history_idx: Option<usize>,
+  output: VecDeque<(String, bool)>,
+  pending: Vec<String>,
+  commands: Vec<(String, &'static str)>,
+  texts: Vec<TextDrawable>,
+  background: Vec<UiBar>,
+}
+
+impl Console {
+  pub fn new(registry: &CommandRegistry) -> Console {
+    Console {
+      commands: registry.names().into_iter().zip(registry.usages()).collect(),
+      ..Console::default()
+    }
+  }
+
+  pub fn apply(&mut self, control: ConsoleControl) {
+    match control {
+      ConsoleControl::Toggle => self.is_open = !self.is_open,
+      ConsoleControl::Character(character) => self.enter_character(character),
+      ConsoleControl::HistoryPrevious => self.previous_history(),
+      ConsoleControl::HistoryNext => self.next_history(),
+      ConsoleControl::Complete => self.complete(),
+    }
+  }
+
+  fn enter_character(&mut self, character: char) {
+    match character {
+      '\u{8}' => {
+        self.input.pop();
+      }
+      '\r' | '\n' => self.submit(),
+      '`' => (),
+      c if !c.is_control() => self.input.push(c),
+      _ => (),
+    }
+  }
+
+  fn submit(&mut self) {
+    let line = self.input.trim().to_string();
+    self.input.clear();
+    self.history_idx = None;
+    if line.is_empty() {
+      return;
+    }
+    self.print(format!("{}{}", PROMPT, line), false);
+    if self.history.last() != Some(&line) {
+      self.history.push(line.clone());
+      if self.history.len() > CONSOLE_HISTORY_SIZE {
+        self.history.remove(0);
+      }
+    }
+    self.pending.push(line);
+  }
+
+  fn previous_history(&mut self) {
+    if self.history.is_empty() {
+      return;
+    }
+    let idx = match self.history_idx {
+      Some(idx) => idx.saturating_sub(1),
+      None => self.history.len() - 1,
+    };
+    self.history_idx = Some(idx);
+    self.input = self.history[idx].clone();
+  }
+
+  fn next_history(&mut self) {
+    match self.history_idx {
+      Some(idx) if idx + 1 < self.history.len() => {
+        self.history_idx = Some(idx + 1);
+        self.input = self.history[idx + 1].clone();
+      }
+      _ => {
+        self.history_idx = None;
+        self.input.clear();
+      }
+    }
+  }
+
+  fn complete(&mut self) {
+    if self.input.contains(' ') {
+      return;
+    }
+    let candidates = self.commands.iter()
+      .map(|(name, _)| name.clone())
+      .filter(|name| name.starts_with(self.input.as_str()))
+      .collect::<Vec<String>>();
+    match candidates.len() {
+      0 => (),
+      1 => self.input = format!("{} ", candidates[0]),
+      _ => {
+        self.input = common_prefix(&candidates);
+        self.print(candidates.join("  "), false);
+      }
+    }
+  }
+
+  pub fn print(&mut self, line: String, is_error: bool) {
+    for l in line.lines() {
+      if self.output.len() == CONSOLE_OUTPUT_LINES {
+        self.output.pop_front();
+      }
+      self.output.push_back((l.to_string(), is_error));
+    }
+  }
+
+  pub fn print_result(&mut self, result: CommandResult) {
+    match result {
+      Ok(ref message) if message.is_empty() => (),
+      Ok(message) => self.print(message, false),
+      Err(error) => self.print(error, true),
+    }
+  }
+
+  pub fn take_pending(&mut self) -> Vec<String> {
+    self.pending.drain(..).collect()
+  }
+
+  fn update_layout(&mut self, layout: &ScreenLayout) {
+    self.texts.clear();
+    self.background.clear();
+    if !self.is_open {
+      return;
+    }
+    let width = layout.screen_size.x / layout.scale;
+    self.background.push(UiBar::new(UiRect::new(Anchor::TopLeft, Point2::new(0.0, 0.0), Point2::new(width, CONSOLE_HEIGHT)),
+                                    CONSOLE_BACKGROUND_COLOR));
+    for (idx, (line, is_error)) in self.output.iter().enumerate() {
+      let color = if *is_error { CONSOLE_ERROR_COLOR } else { CONSOLE_TEXT_COLOR };
+      let offset = Point2::new(12.0, 8.0 + idx as f32 * CONSOLE_LINE_HEIGHT);
+      self.texts.push(TextDrawable::new(line, Anchor::TopLeft, offset, CONSOLE_FONT_SIZE, color));
+    }
+    let input = format!("{}{}_", PROMPT, self.input);
+    let offset = Point2::new(12.0, CONSOLE_HEIGHT - CONSOLE_LINE_HEIGHT - 8.0);
+    self.texts.push(TextDrawable::new(&input, Anchor::TopLeft, offset, CONSOLE_FONT_SIZE, CONSOLE_TEXT_COLOR));
+  }
+
+  pub fn texts(&self) -> impl Iterator<Item=&TextDrawable> {
+    self.texts.iter()
+  }
+
+  pub fn bars(&self) -> impl Iterator<Item=&UiBar> {
+    self.background.iter()
+  }
+}
+
+fn common_prefix(words: &[String]) -> String {
+  let first = &words[0];
+  let length = words.iter()
+    .map(|word| first.chars().zip(word.chars()).take_while(|(a, b)| a == b).count())
+    .min()
+    .unwrap_or(0);
+  first.chars().take(length).collect()
+}
+
+pub fn execute_pending(world: &mut World, registry: &CommandRegistry) {
+  let pending = world.write_resource::<Console>().take_pending();
+  for line in pending {
+    let result = registry.execute(world, &line);
+    world.write_resource::<Console>().print_result(result);
+  }
+}
+
+pub fn register_commands(registry: &mut CommandRegistry) {
+  registry.register("help", "help", help);
+  registry.register("clear", "clear", clear);
+  registry.register("timescale", "timescale <scale>", timescale);
+  registry.register("reload_assets", "reload_assets [sprites|terrain]", reload_assets);
+}
+
+fn help(world: &mut World, _args: &[&str]) -> CommandResult {
+  let console = world.read_resource::<Console>();
+  Ok(console.commands.iter().map(|(_, usage)| *usage).collect::<Vec<&str>>().join("\n"))
+}
+
+fn clear(world: &mut World, _args: &[&str]) -> CommandResult {
+  world.write_resource::<Console>().output.clear();
+  Ok(String::new())
+}
+
+fn timescale(world: &mut World, args: &[&str]) -> CommandResult {
+  let scale = parse_arg::<f64>(args, 0, "timescale <scale>")?;
+  if scale <= 0.0 || scale > MAX_TIME_SCALE {
+    return Err(format!("Time scale must be between 0 and {}", MAX_TIME_SCALE));
+  }
+  world.write_resource::<TimeScale>().0 = scale;
+  Ok(format!("Time scale set to {}", scale))
+}
+
+fn reload_assets(world: &mut World, args: &[&str]) -> CommandResult {
+  let mut asset_reload = world.write_resource::<AssetReload>();
+  match args.first() {
+    None => asset_reload.request_all(),
+    Some(&"sprites") => asset_reload.request(AssetKind::Sprites),
+    Some(&"terrain") => asset_reload.request(AssetKind::Terrain),
+    Some(other) => return Err(format!("Unknown asset kind {}, usage: reload_assets [sprites|terrain]", other)),
+  }
+  Ok(format!("Reloading {}", args.first().unwrap_or(&"assets")))
+}
+
+pub struct ConsoleSystem {
+  queue: channel::Receiver<ConsoleControl>,
+}
+
+impl ConsoleSystem {
+  pub fn new() -> (ConsoleSystem, channel::Sender<ConsoleControl>) {
+    let (tx, rx) = channel::unbounded();
+    (ConsoleSystem {
+      queue: rx,
+    }, tx)
+  }
+}
+
+impl<'a> specs::prelude::System<'a> for ConsoleSystem {
+  type SystemData = (Write<'a, Console>,
+                     Read<'a, Dimensions>);
+
+  fn run(&mut self, (mut console, dim): Self::SystemData) {
+    while let Ok(control) = self.queue.try_recv() {
+      console.apply(control);
+    }
+    console.update_layout(&ScreenLayout::new(&dim));
+  }
+}
@@ -0,0 +1,54 @@
+use std::collections::BTreeMap;
+
+use specs::shred::World;
+
+pub type CommandResult = Result<String, String>;
+pub type CommandFn = fn(&mut World, &[&str]) -> CommandResult;
+
+pub struct Command {
+  pub usage: &'static str,
+  run: CommandFn,
+}
+
+pub struct CommandRegistry {
+  commands: BTreeMap<&'static str, Command>,
+}
+
+impl CommandRegistry {
+  pub fn new() -> CommandRegistry {
+    CommandRegistry {
+      commands: BTreeMap::new(),
+    }
+  }
+
+  pub fn register(&mut self, name: &'static str, usage: &'static str, run: CommandFn) {
+    if self.commands.insert(name, Command { usage, run }).is_some() {
+      panic!("Console command {} registered twice", name);
+    }
+  }
+
+  pub fn names(&self) -> Vec<String> {
+    self.commands.keys().map(|name| name.to_string()).collect()
+  }
+
+  pub fn usages(&self) -> Vec<&'static str> {
+    self.commands.values().map(|command| command.usage).collect()
+  }
+
+  pub fn execute(&self, world: &mut World, line: &str) -> CommandResult {
+    let words = line.split_whitespace().collect::<Vec<&str>>();
+    match words.split_first() {
+      Some((name, args)) => match self.commands.get(name) {
+        Some(command) => (command.run)(world, args),
+        None => Err(format!("Unknown command {}", name)),
+      },
+      None => Ok(String::new()),
+    }
+  }
+}
+
+pub fn parse_arg<T: std::str::FromStr>(args: &[&str], idx: usize, usage: &str) -> Result<T, String> {
+  args.get(idx)
+    .and_then(|arg| arg.parse::<T>().ok())
+    .ok_or_else(|| format!("Usage: {}", usage))
+}
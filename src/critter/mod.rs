@@ -1,44 +1,28 @@
-pub struct CharacterSprite {
-  pub character_idx: usize,
-  pub character_fire_idx: usize,
-}
-
-impl CharacterSprite {
-  pub fn new() -> CharacterSprite {
-    CharacterSprite {
-      character_idx: 0,
-      character_fire_idx: 0,
-    }
-  }
-
-  pub fn update_run(&mut self) {
-    if self.character_idx < 12 {
-      self.character_idx += 1;
-    } else {
-      self.character_idx = 0;
-    }
-    self.character_fire_idx = 0;
-  }
-
-  pub fn update_fire(&mut self) {
-    if self.character_fire_idx < 3 {
-      self.character_fire_idx += 1;
-    } else {
-      self.character_fire_idx = 0;
-    }
-  }
-}
-
-impl specs::prelude::Component for CharacterSprite {
-  type Storage = specs::storage::VecStorage<CharacterSprite>;
-}
+use crate::graphics::atlas::TextureAtlas;
 
 pub struct CritterData {
+  pub name: String,
   pub data: [f32; 4]
 }
 
 impl CritterData {
-  pub fn new(data: [f32; 4]) -> CritterData {
-    CritterData { data }
+  pub fn new(name: &str, data: [f32; 4]) -> CritterData {
+    CritterData { name: name.to_string(), data }
+  }
+}
+
+pub fn sheet_rows(atlas: &TextureAtlas, sheet: &str, frames: &[CritterData]) -> Vec<Vec<[f32; 4]>> {
+  let mut rows: Vec<Vec<[f32; 4]>> = vec![];
+  let mut row_y = None;
+  for frame in frames {
+    if row_y != Some(frame.data[1]) {
+      row_y = Some(frame.data[1]);
+      rows.push(vec![]);
+    }
+    let uv = atlas.sheet_uv_rect(sheet, &frame.name).unwrap_or([0.0, 0.0, 0.0, 0.0]);
+    if let Some(row) = rows.last_mut() {
+      row.push(uv);
+    }
   }
+  rows
 }
@@ -0,0 +1,72 @@
+#[test]
+fn parse_hash_sprite_sheet_test() {
+  use crate::data::parse_sprite_sheet;
+
+  let sheet = json::parse(r#"{
+    "frames": {
+      "walk_1": {"frame": {"x": 34, "y": 1, "w": 32, "h": 32}, "rotated": false},
+      "walk_0": {"frame": {"x": 1, "y": 1, "w": 32, "h": 32}, "rotated": false},
+      "death_0": {"frame": {"x": 1, "y": 34, "w": 32, "h": 32}, "rotated": false}
+    },
+    "meta": {"image": "ghoul.png", "size": {"w": 128, "h": 128},
+             "animations": {"walk": {"row": 0, "offset": 0, "direction_stride": 2, "frames": 2, "fps": 10}}}
+  }"#).unwrap();
+  let sprite_sheet = parse_sprite_sheet("character.json", &sheet).expect("Sprite sheet should parse");
+  assert_eq!("ghoul.png", sprite_sheet.image);
+  assert_eq!((128, 128), (sprite_sheet.size.x, sprite_sheet.size.y));
+  let names = sprite_sheet.frames.iter().map(|f| f.name.as_str()).collect::<Vec<_>>();
+  assert_eq!(vec!["walk_0", "walk_1", "death_0"], names, "Frames should be in sheet order");
+  assert!(sprite_sheet.animations.get("walk").is_some(), "Animation tags should be read from meta");
+}
+
+#[test]
+fn parse_array_sprite_sheet_test() {
+  use crate::data::parse_sprite_sheet;
+
+  let sheet = json::parse(r#"{
+    "frames": [
+      {"filename": "shotgun_0", "frame": {"x": 0, "y": 0, "w": 16, "h": 8}},
+      {"filename": "shotgun_1", "frame": {"x": 16, "y": 0, "w": 16, "h": 8}}
+    ],
+    "meta": {"image": "shotgun.png", "size": {"w": 32, "h": 8}}
+  }"#).unwrap();
+  let sprite_sheet = parse_sprite_sheet("character.json", &sheet).expect("Array sprite sheet should parse");
+  assert_eq!(2, sprite_sheet.frames.len());
+  assert_eq!([16.0, 0.0, 16.0, 8.0], sprite_sheet.frames[1].data);
+}
+
+#[test]
+fn invalid_sprite_sheet_test() {
+  use crate::data::parse_sprite_sheet;
+
+  let missing_field = json::parse(r#"{
+    "frames": {"run_0": {"frame": {"x": 0, "y": 0, "w": 16}}},
+    "meta": {"image": "run.png", "size": {"w": 16, "h": 16}}
+  }"#).unwrap();
+  assert!(parse_sprite_sheet("character.json", &missing_field).is_err(), "Frame without height should be rejected");
+
+  let outside = json::parse(r#"{
+    "frames": {"run_0": {"frame": {"x": 8, "y": 0, "w": 16, "h": 16}}},
+    "meta": {"image": "run.png", "size": {"w": 16, "h": 16}}
+  }"#).unwrap();
+  assert!(parse_sprite_sheet("character.json", &outside).is_err(), "Frame outside of sheet should be rejected");
+
+  let no_meta = json::parse(r#"{"frames": {}}"#).unwrap();
+  assert!(parse_sprite_sheet("character.json", &no_meta).is_err(), "Sheet without meta should be rejected");
+}
+
+#[test]
+fn map_objects_test() {
+  use cgmath::Point2;
+  use crate::data::get_map_objects;
+
+  let map = tiled::parse(r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.2" orientation="isometric" renderorder="left-down" width="128" height="128" tilewidth="32" tileheight="32" nextlayerid="3" nextobjectid="3">
+ <objectgroup id="2" name="lights">
+  <object id="1" type="lamp" x="2000" y="1968"/>
+  <object id="2" type="sign" x="64" y="64"/>
+ </objectgroup>
+</map>"#.as_bytes()).expect("Map should parse");
+  assert_eq!(vec![Point2::new(62.5, 66.5)], get_map_objects(&map, "lamp"));
+  assert!(get_map_objects(&map, "tree").is_empty());
+}
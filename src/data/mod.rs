@@ -1,117 +1,125 @@
-use std::{fs::File, io::BufReader, io::prelude::*, path::Path, string::String, vec::Vec};
+use std::{fs::File, io::BufReader, path::Path, string::String, vec::Vec};
 
+use cgmath::Point2;
 use json::JsonValue;
 use tiled::Map;
 
+use crate::animation::AnimationClips;
+use crate::assets::AssetManager;
 use crate::critter::CritterData;
-use crate::game::constants::{CHARACTER_BUF_LENGTH, CHARACTER_JSON_PATH, ZOMBIE_JSON_PATH};
+use crate::error::{GameError, GameResult};
 
-pub fn load_map_file(filename: &str) -> Map {
-  let file = match File::open(&Path::new(&filename)) {
-    Ok(f) => f,
-    Err(e) => panic!("File {} not found: {}", filename, e),
-  };
+mod data_test;
+
+pub fn load_map_file(assets: &AssetManager, filename: &str) -> GameResult<Map> {
+  let path = assets.path(filename);
+  let file = File::open(&path)
+    .map_err(|e| GameError::Io(path.display().to_string(), e))?;
   let reader = BufReader::new(file);
-  match tiled::parse(reader) {
-    Ok(m) => m,
-    Err(e) => panic!("Map parse error {:?}", e)
-  }
+  tiled::parse(reader)
+    .map_err(|e| GameError::Map(format!("{} parse error {:?}", filename, e)))
 }
 
-pub fn get_map_tile(map: &Map, layer_index: usize, x: usize, y: usize) -> u32 {
-  let layer = match map.layers.get(layer_index) {
-    None => panic!("Layer_index value out of index {:?}", map.layers),
-    Some(l) => l
-  };
-  let y_index = match layer.tiles.iter().rev().nth(y) {
-    None => panic!("X value out of index {:?}", map.layers[0]),
-    Some(x) => x
-  };
-  match y_index.get(x) {
-    None => panic!("Y value out of index {:?}", layer.tiles[y]),
-    Some(val) => *val
-  }
+pub fn get_map_tile(map: &Map, layer_index: usize, x: usize, y: usize) -> GameResult<u32> {
+  let layer = map.layers.get(layer_index)
+    .ok_or_else(|| GameError::Map(format!("Layer index {} out of {} layers", layer_index, map.layers.len())))?;
+  let row = layer.tiles.iter().rev().nth(y)
+    .ok_or_else(|| GameError::Map(format!("Y value {} out of {} rows", y, layer.tiles.len())))?;
+  row.get(x)
+    .cloned()
+    .ok_or_else(|| GameError::Map(format!("X value {} out of {} columns", x, row.len())))
 }
 
-fn read_sprite_file(filename: &str) -> String {
-  let path = Path::new(&filename);
-  let mut file = match File::open(&path) {
-    Ok(f) => f,
-    Err(e) => panic!("File {} not found: {}", filename, e),
-  };
-  let mut buf = String::new();
-  match file.read_to_string(&mut buf) {
-    Ok(_) => buf,
-    Err(e) => panic!("read file {} error {}", filename, e),
-  }
+pub fn get_map_objects(map: &Map, object_type: &str) -> Vec<Point2<f32>> {
+  // Isometric object coordinates are measured in tile heights, map rows are read bottom up
+  let tile_size = map.tile_height as f32;
+  map.object_groups.iter()
+    .flat_map(|group| group.objects.iter())
+    .filter(|object| object.obj_type == object_type)
+    .map(|object| Point2::new(object.x / tile_size, map.height as f32 - object.y / tile_size))
+    .collect()
 }
 
-fn get_frame_data(character: &JsonValue, key: &str) -> CritterData {
-  CritterData::new([
-    character["frames"][key]["frame"]["x"].as_f32().unwrap(),
-    character["frames"][key]["frame"]["y"].as_f32().unwrap(),
-    character["frames"][key]["frame"]["w"].as_f32().unwrap(),
-    character["frames"][key]["frame"]["h"].as_f32().unwrap(),
-  ])
+pub struct SpriteSheet {
+  pub image: String,
+  pub size: Point2<u32>,
+  pub frames: Vec<CritterData>,
+  pub animations: AnimationClips,
 }
 
-pub fn load_character() -> Vec<CritterData> {
-  let mut sprites = Vec::with_capacity(CHARACTER_BUF_LENGTH + 64);
-  let character_json = read_sprite_file(CHARACTER_JSON_PATH);
-  let character = match json::parse(&character_json) {
-    Ok(res) => res,
-    Err(e) => panic!("Character {} parse error {:?}", CHARACTER_JSON_PATH, e),
-  };
+fn read_f32(value: &JsonValue, key: &str, name: &str) -> Result<f32, String> {
+  value[key].as_f32().ok_or_else(|| format!("Frame {} {} missing", name, key))
+}
 
-  for x in 0..16 {
-    for y in 0..14 {
-      let key = &format!("run_{}_{}", x, y);
-      sprites.push(get_frame_data(&character, key));
-    }
+fn get_frame_data(name: &str, frame: &JsonValue) -> Result<CritterData, String> {
+  let rect = &frame["frame"];
+  if frame["rotated"].as_bool() == Some(true) {
+    return Err(format!("Frame {} is rotated, rotated frames are not supported", name));
   }
+  Ok(CritterData::new(name, [
+    read_f32(rect, "x", name)?,
+    read_f32(rect, "y", name)?,
+    read_f32(rect, "w", name)?,
+    read_f32(rect, "h", name)?,
+  ]))
+}
 
-  for x in 0..15 {
-    for y in 0..4 {
-      let key = &format!("fire_{}_{}", x, y);
-      sprites.push(get_frame_data(&character, key));
-    }
+fn sort_sheet_order(sprites: &mut Vec<CritterData>) {
+  sprites.sort_by(|a, b| {
+    (a.data[1], a.data[0]).partial_cmp(&(b.data[1], b.data[0]))
+      .expect("Sprite frame sorting failed")
+  });
+}
+
+fn parse_sheet(sheet: &JsonValue) -> Result<SpriteSheet, String> {
+  let frames = &sheet["frames"];
+  let mut sprites =
+    if frames.is_object() {
+      frames.entries()
+        .map(|(name, frame)| get_frame_data(name, frame))
+        .collect::<Result<Vec<CritterData>, String>>()?
+    } else if frames.is_array() {
+      frames.members()
+        .map(|frame| {
+          let name = frame["filename"].as_str().ok_or_else(|| "Frame filename missing".to_string())?;
+          get_frame_data(name, frame)
+        })
+        .collect::<Result<Vec<CritterData>, String>>()?
+    } else {
+      return Err("Sprite sheet frames missing".to_string());
+    };
+
+  let meta = &sheet["meta"];
+  let image = meta["image"].as_str().ok_or_else(|| "Sprite sheet meta image missing".to_string())?;
+  let size = match (meta["size"]["w"].as_u32(), meta["size"]["h"].as_u32()) {
+    (Some(w), Some(h)) => Point2::new(w, h),
+    _ => return Err("Sprite sheet meta size missing".to_string()),
+  };
+  if let Some(frame) = sprites.iter().find(|f| f.data[0] + f.data[2] > size.x as f32 || f.data[1] + f.data[3] > size.y as f32) {
+    return Err(format!("Frame {} outside of {}x{} sheet", frame.name, size.x, size.y));
   }
 
-  sprites
+  sort_sheet_order(&mut sprites);
+  Ok(SpriteSheet {
+    image: image.to_string(),
+    size,
+    frames: sprites,
+    animations: AnimationClips::from_json(sheet)?,
+  })
 }
 
-pub fn load_zombie() -> Vec<CritterData> {
-  let mut sprites = Vec::with_capacity(256);
-  let zombie_json = read_sprite_file(ZOMBIE_JSON_PATH);
-  let zombie = match json::parse(&zombie_json) {
-    Ok(res) => res,
-    Err(e) => panic!("Zombie {} parse error {:?}", ZOMBIE_JSON_PATH, e),
-  };
+pub fn parse_sprite_sheet(filename: &str, sheet: &JsonValue) -> GameResult<SpriteSheet> {
+  parse_sheet(sheet)
+    .map_err(|e| GameError::Asset(filename.to_string(), e))
+}
 
-  for x in 0..7 {
-    for y in 0..7 {
-      let key = &format!("critical_{}_{}", x, y);
-      sprites.push(get_frame_data(&zombie, key));
-    }
-  }
-  for x in 0..7 {
-    for y in 0..5 {
-      let key = &format!("normal_{}_{}", x, y);
-      sprites.push(get_frame_data(&zombie, key));
-    }
-  }
-  for x in 0..7 {
-    for y in 0..4 {
-      let key = &format!("still_{}_{}", x, y);
-      sprites.push(get_frame_data(&zombie, key));
-    }
-  }
+pub fn sheet_image_path(filename: &str, sheet: &SpriteSheet) -> String {
+  Path::new(filename).with_file_name(&sheet.image).to_string_lossy().replace('\\', "/")
+}
 
-  for x in 0..7 {
-    for y in 0..7 {
-      let key = &format!("walk_{}_{}", x, y);
-      sprites.push(get_frame_data(&zombie, key));
-    }
-  }
-  sprites
+pub fn load_sprite_sheet(assets: &AssetManager, filename: &str) -> GameResult<SpriteSheet> {
+  let sheet_json = assets.read_string(filename)?;
+  let sheet = json::parse(&sheet_json)
+    .map_err(|e| GameError::Asset(filename.to_string(), format!("parse error {:?}", e)))?;
+  parse_sprite_sheet(filename, &sheet)
 }
@@ -0,0 +1,295 @@
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+use cgmath::Point2;
+use crossbeam_channel as channel;
+use specs::prelude::{Read, ReadStorage, WriteStorage};
+
+use crate::assets::AssetManager;
+use crate::bullet::bullets::Bullets;
+use crate::character::{CharacterDrawable, controls::CharacterInputState};
+use crate::error::{GameError, GameResult};
+use crate::game::constants::{DEBUG_FRAME_SAMPLES, DEBUG_GRID_RADIUS, HUD_FONT_SIZE, HUD_TEXT_COLOR, MAX_DEBUG_VERTICES, TERRAIN_OBJECTS,
+                             ZOMBIE_AGGRO_RADIUS};
+use crate::gfx_app::ColorFormat;
+use crate::graphics::{camera::CameraInputState, can_move_to_tile, coords_to_tile, DeltaTime, dimensions::{Dimensions, world_to_clip_space},
+                      orientation::Stance, shader::{create_pipeline, ShaderCode, ShaderProgram}, tile_to_coords};
+use crate::hud::layout::{Anchor, ScreenLayout, UiRect};
+use crate::hud::TextDrawable;
+use crate::shaders::{debug_pipeline, DebugVertex, Position, Projection};
+use crate::terrain::path_finding::calc_route;
+use crate::zombie::zombies::Zombies;
+
+const SHADER: ShaderProgram = ShaderProgram::new("debug");
+
+const GRID_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.25];
+const BLOCKED_COLOR: [f32; 4] = [1.0, 0.2, 0.2, 0.8];
+const COLLIDER_COLOR: [f32; 4] = [0.2, 1.0, 0.2, 0.9];
+const ROUTE_COLOR: [f32; 4] = [0.2, 0.6, 1.0, 0.9];
+const AGGRO_COLOR: [f32; 4] = [1.0, 0.6, 0.1, 0.4];
+const GRAPH_COLOR: [f32; 4] = [1.0, 1.0, 0.2, 0.9];
+const GRAPH_TARGET_COLOR: [f32; 4] = [0.2, 1.0, 0.2, 0.5];
+// Milliseconds
+const GRAPH_MAX_FRAME_TIME: f32 = 50.0;
+const GRAPH_TARGET_FRAME_TIME: f32 = 1000.0 / 60.0;
+
+struct LineBuilder<'b> {
+  projection: &'b Projection,
+  movement: Position,
+  vertices: Vec<DebugVertex>,
+}
+
+impl<'b> LineBuilder<'b> {
+  fn clip(&self, position: Position) -> [f32; 2] {
+    let clip = world_to_clip_space(self.projection, position);
+    [clip.x, clip.y]
+  }
+
+  fn line(&mut self, start: Position, end: Position, color: [f32; 4]) {
+    let (start, end) = (self.clip(start), self.clip(end));
+    self.vertices.push(DebugVertex::new(start, color));
+    self.vertices.push(DebugVertex::new(end, color));
+  }
+
+  fn map_line(&mut self, start: Position, end: Position, color: [f32; 4]) {
+    self.line(self.movement - start, self.movement - end, color);
+  }
+
+  fn tile(&mut self, tile: Point2<i32>, color: [f32; 4], is_crossed: bool) {
+    let corner = |x: i32, y: i32| tile_to_coords(Point2::new(x as f32, y as f32));
+    let corners = [corner(tile.x, tile.y), corner(tile.x + 1, tile.y), corner(tile.x + 1, tile.y + 1), corner(tile.x, tile.y + 1)];
+    for idx in 0..4 {
+      self.map_line(corners[idx], corners[(idx + 1) % 4], color);
+    }
+    if is_crossed {
+      self.map_line(corners[0], corners[2], color);
+      self.map_line(corners[1], corners[3], color);
+    }
+  }
+
+  fn rect(&mut self, center: Position, half_size: Point2<f32>, color: [f32; 4]) {
+    let corners = [
+      center + Position::new(-half_size.x, -half_size.y),
+      center + Position::new(half_size.x, -half_size.y),
+      center + Position::new(half_size.x, half_size.y),
+      center + Position::new(-half_size.x, half_size.y),
+    ];
+    for idx in 0..4 {
+      self.line(corners[idx], corners[(idx + 1) % 4], color);
+    }
+  }
+
+  fn circle(&mut self, center: Position, radius: f32, color: [f32; 4]) {
+    let segments = 32;
+    let point = |idx: i32| {
+      let angle = idx as f32 / segments as f32 * 2.0 * PI;
+      center + Position::new(angle.cos() * radius, angle.sin() * radius)
+    };
+    for idx in 0..segments {
+      self.line(point(idx), point(idx + 1), color);
+    }
+  }
+}
+
+fn screen_line(vertices: &mut Vec<DebugVertex>, layout: &ScreenLayout, start: Point2<f32>, end: Point2<f32>, color: [f32; 4]) {
+  vertices.push(DebugVertex::new(layout.to_clip_space(start), color));
+  vertices.push(DebugVertex::new(layout.to_clip_space(end), color));
+}
+
+pub struct DebugOverlay {
+  pub is_visible: bool,
+  vertices: Vec<DebugVertex>,
+  frame_times: VecDeque<f32>,
+  fps_text: TextDrawable,
+}
+
+impl DebugOverlay {
+  pub fn new() -> DebugOverlay {
+    DebugOverlay {
+      is_visible: false,
+      vertices: Vec::new(),
+      frame_times: VecDeque::with_capacity(DEBUG_FRAME_SAMPLES),
+      fps_text: TextDrawable::new("", Anchor::BottomLeft, Point2::new(16.0, -140.0), HUD_FONT_SIZE, HUD_TEXT_COLOR),
+    }
+  }
+
+  fn record_frame_time(&mut self, delta: f64) {
+    if self.frame_times.len() == DEBUG_FRAME_SAMPLES {
+      self.frame_times.pop_front();
+    }
+    self.frame_times.push_back(delta as f32 * 1000.0);
+  }
+
+  fn update_frame_graph(&mut self, layout: &ScreenLayout) {
+    let average = self.frame_times.iter().sum::<f32>() / self.frame_times.len().max(1) as f32;
+    let fps = if average > 0.0 { 1000.0 / average } else { 0.0 };
+    self.fps_text.update(format!("FPS {:.0} {:.1} ms", fps, average));
+
+    let (min, max) = layout.resolve(&UiRect::new(Anchor::BottomLeft, Point2::new(16.0, -48.0), Point2::new(240.0, 80.0)));
+    let height = |ms: f32| max.y - (ms / GRAPH_MAX_FRAME_TIME).min(1.0) * (max.y - min.y);
+    let step = (max.x - min.x) / DEBUG_FRAME_SAMPLES as f32;
+
+    screen_line(&mut self.vertices, layout, Point2::new(min.x, max.y), Point2::new(max.x, max.y), GRID_COLOR);
+    let target_y = height(GRAPH_TARGET_FRAME_TIME);
+    screen_line(&mut self.vertices, layout, Point2::new(min.x, target_y), Point2::new(max.x, target_y), GRAPH_TARGET_COLOR);
+    let points = self.frame_times.iter()
+      .enumerate()
+      .map(|(idx, ms)| Point2::new(min.x + idx as f32 * step, height(*ms)))
+      .collect::<Vec<_>>();
+    for pair in points.windows(2) {
+      screen_line(&mut self.vertices, layout, pair[0], pair[1], GRAPH_COLOR);
+    }
+  }
+
+  pub fn texts(&self) -> impl Iterator<Item=&TextDrawable> {
+    let is_visible = self.is_visible;
+    std::iter::once(&self.fps_text).filter(move |_| is_visible)
+  }
+}
+
+impl specs::prelude::Component for DebugOverlay {
+  type Storage = specs::storage::HashMapStorage<DebugOverlay>;
+}
+
+pub struct DebugDrawSystem<R: gfx::Resources> {
+  bundle: gfx::pso::bundle::Bundle<R, debug_pipeline::Data<R>>,
+}
+
+fn debug_pso<R, F>(factory: &mut F, code: &ShaderCode) -> Result<gfx::PipelineState<R, debug_pipeline::Meta>, String>
+  where R: gfx::Resources, F: gfx::Factory<R> {
+  create_pipeline(factory, code, gfx::Primitive::LineList, gfx::state::Rasterizer::new_fill(), debug_pipeline::new())
+}
+
+impl<R: gfx::Resources> DebugDrawSystem<R> {
+  pub fn new<F>(factory: &mut F,
+                rtv: gfx::handle::RenderTargetView<R, ColorFormat>,
+                assets: &AssetManager) -> GameResult<DebugDrawSystem<R>>
+    where F: gfx::Factory<R> {
+    let pso = debug_pso(factory, &SHADER.load(assets)?).map_err(GameError::Shader)?;
+
+    let vertex_buffer = factory
+      .create_buffer(MAX_DEBUG_VERTICES, gfx::buffer::Role::Vertex, gfx::memory::Usage::Dynamic, gfx::memory::Bind::empty())
+      .expect("Debug vertex buffer creation error");
+
+    let slice = gfx::Slice {
+      start: 0,
+      end: 0,
+      base_vertex: 0,
+      instances: None,
+      buffer: gfx::IndexBuffer::Auto,
+    };
+
+    Ok(DebugDrawSystem {
+      bundle: gfx::Bundle::new(slice, pso, debug_pipeline::Data { vbuf: vertex_buffer, out_color: rtv }),
+    })
+  }
+
+  pub fn reload_shaders<F>(&mut self, factory: &mut F, assets: &AssetManager) -> Result<(), String>
+    where F: gfx::Factory<R> {
+    self.bundle.pso = debug_pso(factory, &SHADER.load(assets).map_err(|e| e.to_string())?)?;
+    Ok(())
+  }
+
+  pub fn resize(&mut self, rtv: &gfx::handle::RenderTargetView<R, ColorFormat>) {
+    self.bundle.data.out_color = rtv.clone();
+  }
+
+  pub fn draw<C>(&mut self,
+                 overlay: &DebugOverlay,
+                 encoder: &mut gfx::Encoder<R, C>)
+    where C: gfx::CommandBuffer<R> {
+    if !overlay.is_visible {
+      return;
+    }
+    let vertices = &overlay.vertices[..overlay.vertices.len().min(MAX_DEBUG_VERTICES) / 2 * 2];
+    encoder.update_buffer(&self.bundle.data.vbuf, vertices, 0)
+      .unwrap_or_else(|e| panic!("Debug vertex buffer update error {:?}", e));
+    self.bundle.slice.end = vertices.len() as u32;
+    self.bundle.encode(encoder);
+  }
+}
+
+pub enum DebugControl {
+  Toggle,
+}
+
+pub struct DebugSystem {
+  queue: channel::Receiver<DebugControl>,
+}
+
+impl DebugSystem {
+  pub fn new() -> (DebugSystem, channel::Sender<DebugControl>) {
+    let (tx, rx) = channel::unbounded();
+    (DebugSystem {
+      queue: rx,
+    }, tx)
+  }
+}
+
+impl<'a> specs::prelude::System<'a> for DebugSystem {
+  type SystemData = (WriteStorage<'a, DebugOverlay>,
+                     ReadStorage<'a, CharacterDrawable>,
+                     ReadStorage<'a, CharacterInputState>,
+                     ReadStorage<'a, CameraInputState>,
+                     ReadStorage<'a, Zombies>,
+                     ReadStorage<'a, Bullets>,
+                     Read<'a, DeltaTime>,
+                     Read<'a, Dimensions>);
+
+  fn run(&mut self, (mut overlay, character, character_input, camera_input, zombies, bullets, d, dim): Self::SystemData) {
+    use specs::join::Join;
+
+    for (o, c, ci, camera, zs, bs) in (&mut overlay, &character, &character_input, &camera_input, &zombies, &bullets).join() {
+      while let Ok(DebugControl::Toggle) = self.queue.try_recv() {
+        o.is_visible = !o.is_visible;
+      }
+      o.record_frame_time(d.0);
+      if !o.is_visible {
+        continue;
+      }
+
+      let projection = dim.world_to_projection(camera);
+      let mut lines = LineBuilder {
+        projection: &projection,
+        movement: ci.movement,
+        vertices: Vec::new(),
+      };
+
+      let player_tile = coords_to_tile(ci.movement);
+      for y in (player_tile.y - DEBUG_GRID_RADIUS)..=(player_tile.y + DEBUG_GRID_RADIUS) {
+        for x in (player_tile.x - DEBUG_GRID_RADIUS)..=(player_tile.x + DEBUG_GRID_RADIUS) {
+          let tile = Point2::new(x, y);
+          let center = tile_to_coords(Point2::new(x as f32 + 0.5, y as f32 + 0.5));
+          if !can_move_to_tile(center) {
+            lines.tile(tile, BLOCKED_COLOR, true);
+          } else {
+            lines.tile(tile, GRID_COLOR, false);
+          }
+        }
+      }
+
+      lines.rect(c.position, Point2::new(15.0, 30.0), COLLIDER_COLOR);
+      for b in &bs.bullets {
+        lines.rect(b.position, Point2::new(2.0, 2.0), COLLIDER_COLOR);
+      }
+
+      for z in zs.zombies.iter().filter(|z| z.stance != Stance::NormalDeath && z.stance != Stance::CriticalDeath) {
+        lines.rect(z.position, Point2::new(15.0, 15.0), COLLIDER_COLOR);
+        lines.circle(z.position, ZOMBIE_AGGRO_RADIUS, AGGRO_COLOR);
+        if z.stance == Stance::Running {
+          if let Some((route, _)) = calc_route(ci.movement - z.position, ci.movement, &TERRAIN_OBJECTS) {
+            let centers = route.iter()
+              .map(|t| tile_to_coords(Point2::new(t.x as f32 + 0.5, t.y as f32 + 0.5)))
+              .collect::<Vec<_>>();
+            for pair in centers.windows(2) {
+              lines.map_line(pair[0], pair[1], ROUTE_COLOR);
+            }
+          }
+        }
+      }
+
+      o.vertices = lines.vertices;
+      o.update_frame_graph(&ScreenLayout::new(&dim));
+    }
+  }
+}
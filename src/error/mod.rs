@@ -0,0 +1,34 @@
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug)]
+pub enum GameError {
+  Io(String, std::io::Error),
+  Asset(String, String),
+  Map(String),
+  Audio(String),
+  Font(String),
+  Shader(String),
+  Render(String),
+  Arguments(String),
+  Replay(String),
+}
+
+pub type GameResult<T> = Result<T, GameError>;
+
+impl Display for GameError {
+  fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    match self {
+      GameError::Io(path, e) => write!(f, "File {} error: {}", path, e),
+      GameError::Asset(path, e) => write!(f, "Asset {} error: {}", path, e),
+      GameError::Map(e) => write!(f, "Map error: {}", e),
+      GameError::Audio(e) => write!(f, "Audio error: {}", e),
+      GameError::Font(e) => write!(f, "Font error: {}", e),
+      GameError::Shader(e) => write!(f, "Shader error: {}", e),
+      GameError::Render(e) => write!(f, "Render error: {}", e),
+      GameError::Arguments(e) => write!(f, "Invalid arguments: {}", e),
+      GameError::Replay(e) => write!(f, "Replay error: {}", e),
+    }
+  }
+}
+
+impl std::error::Error for GameError {}
@@ -6,35 +6,52 @@ pub const TILE_WIDTH: f32 = TILE_SIZE * 2.0;
 
 pub const Y_OFFSET: f32 = TILES_PCS_W as f32 / 2.0 * TILE_WIDTH;
 
-pub const CHARACTER_BUF_LENGTH: usize = 224;
-
 pub const RESOLUTION_X: u32 = 1600;
 pub const RESOLUTION_Y: u32 = 900;
 
-pub const ASPECT_RATIO: f32 = (RESOLUTION_X / RESOLUTION_Y) as f32;
+pub const ASPECT_RATIO: f32 = RESOLUTION_X as f32 / RESOLUTION_Y as f32;
 
 pub const VIEW_DISTANCE: f32 = 300.0;
 
-pub const CHARACTER_SHEET_TOTAL_WIDTH: f32 = 16_128f32;
-pub const SPRITE_OFFSET: f32 = 2.0;
-
-pub const ZOMBIE_SHEET_TOTAL_WIDTH: f32 = 9_184f32;
-
 pub const BULLET_SPEED: f32 = 15.0;
-pub const CHARACTER_X_SPEED: f32 = 3.0;
-pub const CHARACTER_Y_SPEED: f32 = 3.0;
+pub const BULLET_DAMAGE: f32 = 0.5;
+// World units per second
+pub const CHARACTER_MAX_SPEED: f32 = 360.0;
+pub const CHARACTER_ACCELERATION: f32 = 2400.0;
+pub const CHARACTER_DECELERATION: f32 = 3600.0;
+pub const CHARACTER_MAX_HEALTH: f32 = 100.0;
 
 pub const GAME_TITLE: &str = "Hinterland";
 
 //Assets
-pub const ZOMBIE_JSON_PATH: &str = "assets/zombie.json";
-pub const CHARACTER_JSON_PATH: &str = "assets/character.json";
-pub const PISTOL_AUDIO_PATH: &str = "assets/audio/pistol.ogg";
-pub const MAP_FILE_PATH: &str = "assets/maps/tilemap.tmx";
-
-pub const RUN_SPRITE_OFFSET: usize = 64;
-pub const ZOMBIE_STILL_SPRITE_OFFSET: usize = 32;
-pub const NORMAL_DEATH_SPRITE_OFFSET: usize = 64;
+pub const DEFAULT_ASSET_DIR: &str = "assets";
+pub const ASSET_DIR_ENV: &str = "HINTERLAND_ASSETS";
+// Seconds
+pub const ASSET_POLL_INTERVAL: f64 = 0.5;
+pub const SHADER_DIR: &str = "shaders";
+pub const LIGHTING_SHADER: &str = "lighting.glsl";
+pub const ZOMBIE_JSON_PATH: &str = "zombie.json";
+pub const CHARACTER_JSON_PATH: &str = "character.json";
+pub const PISTOL_AUDIO_PATH: &str = "audio/pistol.ogg";
+pub const MAP_FILE_PATH: &str = "maps/tilemap.tmx";
+pub const TERRAIN_TEXTURE_PATH: &str = "maps/terrain.png";
+pub const AMMO_IMAGE_PATH: &str = "maps/ammo.png";
+pub const HOUSE_IMAGE_PATH: &str = "maps/house.png";
+pub const TREE_IMAGE_PATH: &str = "maps/tree.png";
+pub const SHAPE_IMAGE_PATH: &str = "maps/shape.png";
+pub const FONT_PATH: &str = "DejaVuSans.ttf";
+
+pub const QUICK_SAVE_PATH: &str = "quicksave.json";
+pub const SAVE_FORMAT_VERSION: u32 = 3;
+
+pub const HIGH_SCORE_PATH: &str = "highscores.json";
+pub const HIGH_SCORE_TABLE_SIZE: usize = 5;
+pub const MAX_NAME_LENGTH: usize = 12;
+pub const KILL_POINTS: u32 = 100;
+pub const CRITICAL_KILL_POINTS: u32 = 150;
+pub const MAX_COMBO_MULTIPLIER: u32 = 5;
+// Seconds
+pub const COMBO_WINDOW: f64 = 2.0;
 
 // Object positions
 pub const AMMO_POSITIONS: [[i32; 2]; 4] = [ [ -13, -12 ], [ -15, 8 ], [ 16, -8 ], [ 1, 14 ] ];
@@ -53,10 +70,97 @@ pub const SMALL_HILLS: [[i32; 2]; 3] = [[4, 2], [20, -2], [-14, -6]];
 
 pub const GAME_VERSION: &str = "v0.3.12";
 
-pub const HUD_TEXTS: [&str; 15] = [GAME_VERSION, "Ammo 0", "Ammo 1", "Ammo 2", "Ammo 3",
-  "Ammo 4", "Ammo 5", "Ammo 6",
-  "Ammo 7", "Ammo 8", "Ammo 9", "Ammo 10",
-  "Magazines 0/2", "Magazines 1/2", "Magazines 2/2"];
-
-pub const CURRENT_AMMO_TEXT: &str = "Ammo 10";
-pub const CURRENT_MAGAZINE_TEXT: &str = "Magazines 2/2";
+pub const UI_REFERENCE_HEIGHT: f32 = 900.0;
+pub const MAX_UI_QUADS: usize = 256;
+pub const MAGAZINE_CAPACITY: usize = 10;
+pub const GLYPH_CACHE_SIZE: i32 = 512;
+pub const MAX_TEXT_GLYPHS: usize = 1024;
+pub const HUD_FONT_SIZE: f32 = 28.0;
+pub const TITLE_FONT_SIZE: f32 = 64.0;
+pub const HUD_TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+pub const COMBO_TEXT_COLOR: [f32; 4] = [1.0, 0.8, 0.2, 1.0];
+pub const GAME_OVER_TEXT_COLOR: [f32; 4] = [0.9, 0.1, 0.1, 1.0];
+pub const BAR_BACKGROUND_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+pub const AMMO_BAR_COLOR: [f32; 4] = [0.9, 0.8, 0.3, 1.0];
+pub const HEALTH_BAR_COLOR: [f32; 4] = [0.8, 0.1, 0.1, 1.0];
+pub const DAMAGE_TEXT_COLOR: [f32; 4] = [1.0, 0.9, 0.9, 1.0];
+pub const KILL_TEXT_COLOR: [f32; 4] = [1.0, 0.3, 0.2, 1.0];
+pub const FLOATING_TEXT_FONT_SIZE: f32 = 22.0;
+// Seconds
+pub const FLOATING_TEXT_LIFETIME: f64 = 1.0;
+pub const KILL_FEED_LIFETIME: f64 = 4.0;
+// Reference pixels per second
+pub const FLOATING_TEXT_DRIFT: f32 = 60.0;
+pub const KILL_FEED_SIZE: usize = 5;
+pub const MAX_DEBUG_VERTICES: usize = 16384;
+pub const DEBUG_GRID_RADIUS: i32 = 6;
+pub const DEBUG_FRAME_SAMPLES: usize = 120;
+pub const ZOMBIE_AGGRO_RADIUS: f32 = 400.0;
+pub const CONSOLE_HEIGHT: f32 = 320.0;
+pub const CONSOLE_FONT_SIZE: f32 = 20.0;
+pub const CONSOLE_LINE_HEIGHT: f32 = 24.0;
+pub const CONSOLE_OUTPUT_LINES: usize = 11;
+pub const CONSOLE_HISTORY_SIZE: usize = 32;
+pub const CONSOLE_BACKGROUND_COLOR: [f32; 4] = [0.05, 0.05, 0.08, 0.85];
+pub const CONSOLE_TEXT_COLOR: [f32; 4] = [0.85, 0.9, 0.85, 1.0];
+pub const CONSOLE_ERROR_COLOR: [f32; 4] = [1.0, 0.4, 0.4, 1.0];
+pub const MAX_TIME_SCALE: f64 = 10.0;
+pub const ZOMBIE_SPAWN_DISTANCE: f32 = 300.0;
+pub const ZOMBIES_PER_WAVE: usize = 4;
+pub const WAVE_SPAWN_DISTANCE: f32 = 700.0;
+pub const MAX_PARTICLES: usize = 2048;
+pub const MUZZLE_OFFSET: f32 = 18.0;
+pub const MINIMAP_SIZE: f32 = 192.0;
+pub const MINIMAP_DOT_SIZE: f32 = 4.0;
+pub const MINIMAP_PLAYER_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+pub const MINIMAP_ZOMBIE_COLOR: [f32; 4] = [0.9, 0.1, 0.1, 1.0];
+pub const MINIMAP_PICKUP_COLOR: [f32; 4] = [0.9, 0.8, 0.3, 1.0];
+pub const MINIMAP_HOUSE_COLOR: [f32; 4] = [0.3, 0.5, 0.9, 1.0];
+pub const MAX_SPRITE_INSTANCES: usize = 1024;
+pub const SPRITE_DEPTH_NEAR: f32 = 0.1;
+pub const SPRITE_DEPTH_FAR: f32 = 0.9;
+pub const TERRAIN_SHAPE_DEPTH: f32 = 0.95;
+pub const BULLET_COLOR: [f32; 4] = [0.5, 0.2, 0.2, 0.8];
+pub const ATLAS_WIDTH: u32 = 4096;
+pub const ATLAS_MAX_HEIGHT: u32 = 4096;
+pub const ATLAS_PADDING: u32 = 2;
+pub const CHARACTER_SHEET: &str = "character";
+pub const ZOMBIE_SHEET: &str = "zombie";
+pub const TERRAIN_SHAPE_SPRITE: &str = "shape";
+pub const SPRITE_SHEETS: [(&str, &str); 2] = [(CHARACTER_SHEET, CHARACTER_JSON_PATH), (ZOMBIE_SHEET, ZOMBIE_JSON_PATH)];
+pub const SETTINGS_PATH: &str = "settings.json";
+pub const SCREENSHOT_DIR: &str = "screenshots";
+// Seconds, used instead of wall clock time when capturing frames
+pub const FIXED_TICK_DELTA: f64 = 1.0 / 120.0;
+pub const RESOLUTIONS: [(u32, u32); 4] = [(1280, 720), (1600, 900), (1920, 1080), (2560, 1440)];
+pub const MSAA_LEVELS: [u16; 4] = [0, 2, 4, 8];
+pub const VOLUME_STEP: f32 = 0.1;
+pub const MIN_MOUSE_SENSITIVITY: f32 = 0.1;
+pub const MAX_MOUSE_SENSITIVITY: f32 = 5.0;
+pub const ZOOM_MIN: f32 = 200.0;
+pub const ZOOM_MAX: f32 = 600.0;
+pub const ZOOM_WHEEL_STEP: f32 = 20.0;
+pub const SETTINGS_MENU_WIDTH: f32 = 520.0;
+pub const SETTINGS_MENU_LINE_HEIGHT: f32 = 32.0;
+pub const SETTINGS_MENU_FONT_SIZE: f32 = 24.0;
+pub const SETTINGS_SELECTED_COLOR: [f32; 4] = [1.0, 0.8, 0.2, 1.0];
+// Scene values above this brightness feed the bloom pass
+pub const BLOOM_THRESHOLD: f32 = 1.0;
+pub const BLOOM_STRENGTH: f32 = 0.8;
+pub const VIGNETTE_STRENGTH: f32 = 0.35;
+pub const FILM_GRAIN_STRENGTH: f32 = 0.06;
+pub const NIGHT_LUT_SIZE: usize = 16;
+pub const LAMP_OBJECT_TYPE: &str = "lamp";
+// Must match MAX_LIGHTS in lighting.glsl
+pub const MAX_LIGHTS: usize = 16;
+pub const AMBIENT_LIGHT: [f32; 3] = [0.15, 0.15, 0.15];
+pub const SUN_COLOR: [f32; 3] = [0.8, 0.5, 0.5];
+pub const FLASHLIGHT_COLOR: [f32; 3] = [1.0, 0.95, 0.8];
+pub const FLASHLIGHT_RADIUS: f32 = 320.0;
+pub const FLASHLIGHT_ANGLE: f32 = 25.0;
+pub const MUZZLE_LIGHT_COLOR: [f32; 3] = [2.0, 1.5, 0.7];
+pub const MUZZLE_LIGHT_RADIUS: f32 = 140.0;
+// Seconds
+pub const MUZZLE_LIGHT_DURATION: f32 = 0.08;
+pub const LAMP_COLOR: [f32; 3] = [1.0, 0.75, 0.4];
+pub const LAMP_RADIUS: f32 = 180.0;
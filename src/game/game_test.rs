@@ -0,0 +1,12 @@
+#[test]
+fn seeded_rng_is_deterministic_test() {
+  use crate::game::{GameRng, get_rand_from_range, get_random_bool};
+
+  let mut first = GameRng::new(42);
+  let mut second = GameRng::new(42);
+
+  for _ in 0..100 {
+    assert_eq!(get_rand_from_range(&mut first, 0, 359), get_rand_from_range(&mut second, 0, 359), "Same seed should give same range values");
+    assert_eq!(get_random_bool(&mut first), get_random_bool(&mut second), "Same seed should give same booleans");
+  }
+}
@@ -1,22 +1,50 @@
 use num::Integer;
+use rand::{Rng, rngs::StdRng, SeedableRng};
 use rand::distributions::uniform::SampleUniform;
-use rand::Rng;
 
 pub mod constants;
+mod game_test;
 
-pub fn get_random_bool() -> bool {
-  let mut rnd = rand::thread_rng();
-  rnd.gen()
+pub struct GameRng {
+  rng: StdRng,
 }
 
-pub fn get_rand_from_range<T>(min: T, max: T) -> T
+impl GameRng {
+  pub fn new(seed: u64) -> GameRng {
+    GameRng {
+      rng: StdRng::seed_from_u64(seed),
+    }
+  }
+}
+
+impl Default for GameRng {
+  fn default() -> GameRng {
+    GameRng::new(0)
+  }
+}
+
+pub fn random_seed() -> u64 {
+  rand::thread_rng().gen()
+}
+
+pub fn get_random_bool(rng: &mut GameRng) -> bool {
+  rng.rng.gen()
+}
+
+pub fn get_rand_from_range<T>(rng: &mut GameRng, min: T, max: T) -> T
   where T: Integer + SampleUniform {
-  let mut rnd = rand::thread_rng();
-  rnd.gen_range(min, max)
+  rng.rng.gen_range(min, max)
+}
+
+pub fn get_rand_f32_from_range(rng: &mut GameRng, min: f32, max: f32) -> f32 {
+  if max > min {
+    rng.rng.gen_range(min, max)
+  } else {
+    min
+  }
 }
 
 #[allow(dead_code)]
-pub fn get_weighted_random(weight: f32) -> bool {
-  let mut rnd = rand::thread_rng();
-  rnd.gen::<f32>() < weight
+pub fn get_weighted_random(rng: &mut GameRng, weight: f32) -> bool {
+  rng.rng.gen::<f32>() < weight
 }
@@ -2,9 +2,17 @@ use crossbeam_channel as channel;
 
 use crate::audio::Effects;
 use crate::character::controls::CharacterControl;
+use crate::console::ConsoleControl;
+use crate::debug::DebugControl;
 use crate::gfx_app::mouse_controls::MouseControl;
+use crate::gfx_app::replay::InputEvent;
 use crate::graphics::camera::CameraControl;
+use crate::hud::HudControl;
+use crate::save::SaveControl;
+use crate::score::ScoreControl;
+use crate::settings::menu::SettingsControl;
 
+#[derive(Clone, Copy)]
 pub enum Control {
   Plus,
   Negative,
@@ -16,22 +24,67 @@ pub struct TilemapControls {
   terrain_control: channel::Sender<CameraControl>,
   character_control: channel::Sender<CharacterControl>,
   mouse_control: channel::Sender<(MouseControl, Option<(f64, f64)>)>,
+  save_control: channel::Sender<SaveControl>,
+  score_control: channel::Sender<ScoreControl>,
+  hud_control: channel::Sender<HudControl>,
+  debug_control: channel::Sender<DebugControl>,
+  console_control: channel::Sender<ConsoleControl>,
+  is_console_open: bool,
+  settings_control: channel::Sender<SettingsControl>,
+  is_settings_open: bool,
+  screenshot_control: channel::Sender<()>,
+  input_recorder: Option<channel::Sender<InputEvent>>,
 }
 
 impl TilemapControls {
   pub fn new(atc: channel::Sender<Effects>,
              ttc: channel::Sender<CameraControl>,
              ctc: channel::Sender<CharacterControl>,
-             mtc: channel::Sender<(MouseControl, Option<(f64, f64)>)>) -> TilemapControls {
+             mtc: channel::Sender<(MouseControl, Option<(f64, f64)>)>,
+             stc: channel::Sender<SaveControl>,
+             sctc: channel::Sender<ScoreControl>,
+             htc: channel::Sender<HudControl>,
+             dtc: channel::Sender<DebugControl>,
+             cotc: channel::Sender<ConsoleControl>,
+             setc: channel::Sender<SettingsControl>,
+             shtc: channel::Sender<()>,
+             irc: Option<channel::Sender<InputEvent>>) -> TilemapControls {
     TilemapControls {
       audio_control: atc,
       terrain_control: ttc,
       character_control: ctc,
       mouse_control: mtc,
+      save_control: stc,
+      score_control: sctc,
+      hud_control: htc,
+      debug_control: dtc,
+      console_control: cotc,
+      is_console_open: false,
+      settings_control: setc,
+      is_settings_open: false,
+      screenshot_control: shtc,
+      input_recorder: irc,
+    }
+  }
+
+  fn record(&self, event: InputEvent) {
+    if let Some(ref recorder) = self.input_recorder {
+      recorder.send(event).expect("Input recorder update error");
+    }
+  }
+
+  pub fn apply(&mut self, event: InputEvent) {
+    match event {
+      InputEvent::Zoom(control) => self.zoom(&control),
+      InputEvent::ZoomBy(amount) => self.zoom_by(amount),
+      InputEvent::Character(control) => self.move_character(control),
+      InputEvent::MouseLeftClick(aim) => self.mouse_left_click(aim),
+      InputEvent::Save(control) => self.save_game(control),
     }
   }
 
   pub fn zoom(&mut self, control: &Control) {
+    self.record(InputEvent::Zoom(*control));
     match control {
       Control::Plus => self.terrain_control.send(CameraControl::ZoomIn),
       Control::Negative => self.terrain_control.send(CameraControl::ZoomOut),
@@ -39,31 +92,103 @@ impl TilemapControls {
     }.expect("Terrain control update error");
   }
 
+  pub fn zoom_by(&mut self, amount: f32) {
+    self.record(InputEvent::ZoomBy(amount));
+    self.terrain_control.send(CameraControl::ZoomBy(amount)).expect("Terrain control update error");
+  }
+
   pub fn ctrl_pressed(&mut self, is_ctrl: bool) {
-    if is_ctrl {
-      self.character_control.send(CharacterControl::CtrlPressed)
+    let control = if is_ctrl {
+      CharacterControl::CtrlPressed
     } else {
-      self.character_control.send(CharacterControl::CtrlReleased)
-    }.expect("Character Ctrl control update error");
+      CharacterControl::CtrlReleased
+    };
+    self.record(InputEvent::Character(control));
+    self.character_control.send(control).expect("Character Ctrl control update error");
   }
 
   pub fn move_character(&mut self, character_control: CharacterControl) {
+    self.record(InputEvent::Character(character_control));
     self.character_control.send(character_control).expect("Character move control update error");
   }
 
   pub fn reload_weapon(&mut self, is_reloading: bool) {
-    if is_reloading {
-      self.character_control.send(CharacterControl::ReloadPressed)
+    let control = if is_reloading {
+      CharacterControl::ReloadPressed
     } else {
-      self.character_control.send(CharacterControl::ReloadReleased)
-    }.expect("Character reload weapon control update error");
+      CharacterControl::ReloadReleased
+    };
+    self.record(InputEvent::Character(control));
+    self.character_control.send(control).expect("Character reload weapon control update error");
   }
 
-  pub fn mouse_left_click(&mut self, mouse_pos: Option<(f64, f64)>) {
-    self.mouse_control.send((MouseControl::LeftClick, mouse_pos)).expect("Mouse control shoot update error");
-    match mouse_pos {
+  pub fn mouse_left_click(&mut self, aim: Option<(f64, f64)>) {
+    self.record(InputEvent::MouseLeftClick(aim));
+    self.mouse_control.send((MouseControl::LeftClick, aim)).expect("Mouse control shoot update error");
+    match aim {
       Some(_) => self.audio_control.send(Effects::PistolFire),
       _ => self.audio_control.send(Effects::None),
     }.expect("Audio control update error");
   }
+
+  pub fn save_game(&mut self, save_control: SaveControl) {
+    self.record(InputEvent::Save(save_control));
+    self.save_control.send(save_control).expect("Save control update error");
+  }
+
+  pub fn enter_character(&mut self, character: char) {
+    self.score_control.send(ScoreControl::NameInput(character)).expect("Score control update error");
+  }
+
+  pub fn toggle_minimap(&mut self) {
+    self.hud_control.send(HudControl::ToggleMinimap).expect("HUD control update error");
+  }
+
+  pub fn toggle_debug_overlay(&mut self) {
+    self.debug_control.send(DebugControl::Toggle).expect("Debug control update error");
+  }
+
+  pub fn take_screenshot(&mut self) {
+    self.screenshot_control.send(()).expect("Screenshot control update error");
+  }
+
+  pub fn is_console_open(&self) -> bool {
+    self.is_console_open
+  }
+
+  pub fn toggle_console(&mut self) {
+    if !self.is_console_open && self.input_recorder.is_some() {
+      println!("Console is disabled while recording");
+      return;
+    }
+    self.is_console_open = !self.is_console_open;
+    if self.is_console_open {
+      self.move_character(CharacterControl::XMoveStop);
+      self.move_character(CharacterControl::YMoveStop);
+      self.mouse_left_click(None);
+    }
+    self.console_input(ConsoleControl::Toggle);
+  }
+
+  pub fn console_input(&mut self, control: ConsoleControl) {
+    self.console_control.send(control).expect("Console control update error");
+  }
+
+  pub fn is_settings_open(&self) -> bool {
+    self.is_settings_open
+  }
+
+  pub fn toggle_settings(&mut self) {
+    self.is_settings_open = !self.is_settings_open;
+    if self.is_settings_open {
+      self.move_character(CharacterControl::XMoveStop);
+      self.move_character(CharacterControl::YMoveStop);
+      self.mouse_left_click(None);
+    }
+    self.settings_input(SettingsControl::Toggle);
+  }
+
+  pub fn settings_input(&mut self, control: SettingsControl) {
+    self.settings_control.send(control).expect("Settings control update error");
+  }
 }
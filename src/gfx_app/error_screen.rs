@@ -0,0 +1,41 @@
+use cgmath::Point2;
+
+use crate::assets::AssetManager;
+use crate::error::GameError;
+use crate::game::constants::{CONSOLE_ERROR_COLOR, HUD_FONT_SIZE, HUD_TEXT_COLOR, TITLE_FONT_SIZE};
+use crate::gfx_app::{Window, WindowStatus};
+use crate::graphics::dimensions::Dimensions;
+use crate::hud::{TextDrawable, TextDrawSystem};
+use crate::hud::layout::{Anchor, ScreenLayout};
+
+pub fn show<W, D, F>(window: &mut W, assets: &AssetManager, error: &GameError)
+  where W: Window<D, F>,
+        D: gfx::Device,
+        F: gfx::Factory<D::Resources> {
+  let rtv = window.get_render_target_view();
+  let dsv = window.get_depth_stencil_view();
+  let mut text_system = match TextDrawSystem::new(window.get_factory(), rtv.clone(), dsv.clone(), assets) {
+    Ok(text_system) => text_system,
+    Err(e) => {
+      println!("{}", e);
+      return;
+    }
+  };
+
+  let viewport_size = window.get_viewport_size();
+  let layout = ScreenLayout::new(&Dimensions::new(viewport_size.0, viewport_size.1, window.get_hidpi_factor()));
+  let texts = [
+    TextDrawable::new("Game could not be started", Anchor::Center, Point2::new(0.0, -120.0), TITLE_FONT_SIZE, HUD_TEXT_COLOR),
+    TextDrawable::new(&error.to_string(), Anchor::Center, Point2::new(0.0, 0.0), HUD_FONT_SIZE, CONSOLE_ERROR_COLOR),
+    TextDrawable::new("Press Esc to quit", Anchor::Center, Point2::new(0.0, 80.0), HUD_FONT_SIZE, HUD_TEXT_COLOR),
+  ];
+
+  let mut encoder: gfx::Encoder<D::Resources, D::CommandBuffer> = window.create_buffers(1).remove(0).into();
+  while let WindowStatus::Open = window.poll_close() {
+    encoder.clear(&rtv, [16.0 / 256.0, 16.0 / 256.0, 20.0 / 256.0, 1.0]);
+    encoder.clear_depth(&dsv, 1.0);
+    text_system.draw(texts.iter(), &layout, &mut encoder);
+    encoder.flush(window.get_device());
+    window.swap_window();
+  }
+}
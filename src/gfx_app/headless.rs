@@ -0,0 +1,117 @@
+use gfx::handle::{DepthStencilView, RenderTargetView};
+use gfx::memory::Typed;
+use glutin::{Api, Context, GlProfile, GlRequest, PossiblyCurrent};
+use glutin::dpi::PhysicalSize;
+use glutin::os::unix::HeadlessContextExt;
+
+use crate::error::{GameError, GameResult};
+use crate::gfx_app::{COLOR_FORMAT_VALUE, ColorFormat, controls, DEPTH_FORMAT_VALUE, DepthFormat, GameOptions, Window, WindowStatus};
+use crate::gfx_app::replay::InputEvent;
+
+pub struct HeadlessContext {
+  // Owns the OSMesa buffer the device renders into
+  _context: Context<PossiblyCurrent>,
+  controls: Option<controls::TilemapControls>,
+  device: gfx_device_gl::Device,
+  factory: gfx_device_gl::Factory,
+  render_target_view: RenderTargetView<gfx_device_gl::Resources, ColorFormat>,
+  depth_stencil_view: DepthStencilView<gfx_device_gl::Resources, DepthFormat>,
+  game_options: GameOptions,
+}
+
+impl HeadlessContext {
+  pub fn new(game_options: GameOptions) -> GameResult<HeadlessContext> {
+    println!("{}", game_options);
+
+    let (width, height) = game_options.settings.resolution;
+    let context = glutin::ContextBuilder::new()
+      .with_gl(GlRequest::Specific(Api::OpenGl, (3, 2)))
+      .with_gl_profile(GlProfile::Core)
+      .with_pixel_format(24, 8)
+      .build_osmesa(PhysicalSize::new(width.into(), height.into()))
+      .map_err(|e| GameError::Render(format!("OSMesa context creation failed: {}", e)))?;
+
+    let context = unsafe {
+      context
+        .make_current()
+        .map_err(|(_, e)| GameError::Render(format!("OSMesa context activation failed: {:?}", e)))?
+    };
+
+    let (device, factory) = gfx_device_gl::create(|s|
+      context.get_proc_address(s) as *const std::os::raw::c_void);
+
+    let (rtv, dsv) =
+      gfx_device_gl::create_main_targets_raw((width as _, height as _, 1, gfx::texture::AaMode::Single),
+                                             COLOR_FORMAT_VALUE,
+                                             DEPTH_FORMAT_VALUE);
+
+    Ok(HeadlessContext {
+      _context: context,
+      controls: None,
+      device,
+      factory,
+      render_target_view: RenderTargetView::new(rtv),
+      depth_stencil_view: DepthStencilView::new(dsv),
+      game_options,
+    })
+  }
+}
+
+impl Window<gfx_device_gl::Device, gfx_device_gl::Factory> for HeadlessContext {
+  fn swap_window(&mut self) {
+    use gfx::Device;
+    self.device.cleanup();
+  }
+
+  fn create_buffers(&mut self, count: usize) -> Vec<gfx_device_gl::CommandBuffer> {
+    (0..count).map(|_| self.factory.create_command_buffer()).collect()
+  }
+
+  fn set_controls(&mut self, controls: controls::TilemapControls) {
+    self.controls = Some(controls);
+  }
+
+  fn replay_input(&mut self, event: InputEvent) {
+    match self.controls {
+      Some(ref mut c) => c.apply(event),
+      None => panic!("Terrain controls have not been initialized"),
+    }
+  }
+
+  fn get_viewport_size(&mut self) -> (f32, f32) {
+    let (width, height) = self.game_options.settings.resolution;
+    (width as f32, height as f32)
+  }
+
+  fn resize_targets(&mut self) -> bool {
+    false
+  }
+
+  fn get_device(&mut self) -> &mut gfx_device_gl::Device {
+    &mut self.device
+  }
+
+  fn get_factory(&mut self) -> &mut gfx_device_gl::Factory {
+    &mut self.factory
+  }
+
+  fn get_hidpi_factor(&mut self) -> f32 {
+    1.0
+  }
+
+  fn get_render_target_view(&mut self) -> RenderTargetView<gfx_device_gl::Resources, ColorFormat> {
+    self.render_target_view.clone()
+  }
+
+  fn get_depth_stencil_view(&mut self) -> DepthStencilView<gfx_device_gl::Resources, DepthFormat> {
+    self.depth_stencil_view.clone()
+  }
+
+  fn poll_events(&mut self) -> WindowStatus {
+    WindowStatus::Open
+  }
+
+  fn poll_close(&mut self) -> WindowStatus {
+    WindowStatus::Close
+  }
+}
@@ -1,46 +1,125 @@
+use std::path::{Path, PathBuf};
 use std::time;
 
-use specs::{Builder, prelude::DispatcherBuilder, shred::World, world::WorldExt};
+use crossbeam_channel as channel;
+use specs::{Builder, prelude::{DispatcherBuilder, RunNow}, shred::World, world::WorldExt};
 
 use crate::{bullet, terrain_shape};
+use crate::animation::{AnimationLibrary, AnimationSystem, Animator};
+use crate::assets::{AssetManager, AssetWatcher, AssetWatchSystem};
 use crate::audio::AudioSystem;
 use crate::bullet::bullets::Bullets;
 use crate::bullet::collision::CollisionSystem;
 use crate::character;
 use crate::character::controls::CharacterControlSystem;
-use crate::critter::CharacterSprite;
-use crate::gfx_app::{Window, WindowStatus};
+use crate::console;
+use crate::console::{Console, ConsoleSystem, registry::CommandRegistry};
+use crate::debug::{DebugOverlay, DebugSystem};
+use crate::error::{GameError, GameResult};
+use crate::gfx_app::{GameOptions, Window, WindowStatus};
 use crate::gfx_app::controls::TilemapControls;
+use crate::gfx_app::error_screen;
 use crate::gfx_app::mouse_controls::{MouseControlSystem, MouseInputState};
-use crate::gfx_app::renderer::DeviceRenderer;
+use crate::gfx_app::renderer::{DeviceRenderer, EncoderQueue};
+use crate::gfx_app::replay::{InputRecorder, InputReplay};
 use crate::gfx_app::system::DrawSystem;
 use crate::graphics;
-use crate::graphics::{DeltaTime, dimensions::Dimensions, GameTime};
+use crate::graphics::{AssetReload, DeltaTime, dimensions::Dimensions, GameTime, TimeScale};
 use crate::graphics::camera::CameraControlSystem;
 use crate::hud;
+use crate::hud::combat_feed::CombatEvents;
+use crate::lighting::{LightEvents, Lights, LightSystem};
+use crate::particle::{ParticleEvents, Particles, ParticleSystem};
 use crate::terrain;
+use crate::terrain::tile_map::Terrain;
 use crate::terrain_object;
 use crate::zombie;
 use crate::zombie::zombies::Zombies;
-use crate::game::constants::SMALL_HILLS;
+use crate::game::constants::{FIXED_TICK_DELTA, QUICK_SAVE_PATH, SCREENSHOT_DIR, SHADER_DIR, SMALL_HILLS, TERRAIN_TEXTURE_PATH};
+use crate::game::GameRng;
+use crate::save::{read_save_file, SaveGame, SaveSystem};
+use crate::score::{Score, ScoreSystem};
+use crate::settings::{menu::{SettingsMenu, SettingsMenuSystem}, Settings};
+use crate::shaders::Position;
 
-pub fn run<W, D, F>(window: &mut W)
+pub fn run<W, D, F>(window: &mut W, game_options: &GameOptions) -> GameResult<()>
   where W: Window<D, F>,
         D: gfx::Device + 'static,
         F: gfx::Factory<D::Resources>,
         D::CommandBuffer: Send {
 
   let mut w = WorldExt::new();
+  let dimensions = window_dimensions(window);
+  let save_game = if game_options.load_game {
+    match read_save_file(QUICK_SAVE_PATH) {
+      Ok(save_game) => Some(save_game),
+      Err(e) => {
+        println!("{}", e);
+        None
+      }
+    }
+  } else {
+    None
+  };
+  let replay = match game_options.replay {
+    Some(ref path) => Some(InputReplay::load(path).map_err(GameError::Replay)?),
+    None => None,
+  };
+  let seed = replay.as_ref().map_or(game_options.seed, |r| r.seed);
+  let mut settings = game_options.settings.clone();
+  if let Some(ref r) = replay {
+    settings.difficulty = r.difficulty;
+    settings.mouse_sensitivity = r.mouse_sensitivity;
+  }
+  let recorder = game_options.record.as_ref()
+    .map(|path| InputRecorder::new(path, seed, &settings));
+
+  let assets = AssetManager::from_options(game_options.asset_dir.as_ref().map(String::as_str));
+  let (device_renderer, encoder_queue) = DeviceRenderer::new(window.create_buffers(2));
+  let loaded = create_draw_system(window, &assets, settings.msaa, encoder_queue)
+    .and_then(|draw| AnimationLibrary::load(&assets).map(|animations| (draw, animations)));
+  let ((draw, lamps), animations) = match loaded {
+    Ok(loaded) => loaded,
+    Err(e) => {
+      error_screen::show(window, &assets, &e);
+      return Err(e);
+    }
+  };
+
+  let settings_menu = SettingsMenu::new(recorder.is_some() || replay.is_some());
+  setup_world(&mut w, dimensions, seed, save_game, animations, assets, settings, settings_menu, lamps);
+  dispatch_loop(window, &mut w, draw, device_renderer, recorder, replay, game_options)
+}
+
+fn window_dimensions<W, D, F>(window: &mut W) -> Dimensions
+  where W: Window<D, F>,
+        D: gfx::Device,
+        F: gfx::Factory<D::Resources> {
   let viewport_size = window.get_viewport_size();
-  let dimensions = Dimensions::new(viewport_size.0,
-                                   viewport_size.1,
-                                   window.get_hidpi_factor(),
-                                   window.is_windowed());
-  setup_world(&mut w, dimensions);
-  dispatch_loop(window, &mut w);
+  Dimensions::new(viewport_size.0, viewport_size.1, window.get_hidpi_factor())
 }
 
-fn setup_world(world: &mut World, dimensions: Dimensions) {
+fn create_draw_system<W, D, F>(window: &mut W, assets: &AssetManager, msaa: u16, encoder_queue: EncoderQueue<D>)
+                                 -> GameResult<(DrawSystem<D>, Vec<Position>)>
+  where W: Window<D, F>,
+        D: gfx::Device,
+        F: gfx::Factory<D::Resources> {
+  let terrain = Terrain::new(assets)?;
+  let tile_sheet = assets.load_image(TERRAIN_TEXTURE_PATH)?;
+  let rtv = window.get_render_target_view();
+  DrawSystem::new(window.get_factory(), &rtv, msaa, assets, &terrain, &tile_sheet, encoder_queue)
+    .map(|draw| (draw, terrain.lamps))
+}
+
+fn setup_world(world: &mut World,
+               dimensions: Dimensions,
+               seed: u64,
+               save_game: Option<SaveGame>,
+               animations: AnimationLibrary,
+               assets: AssetManager,
+               settings: Settings,
+               settings_menu: SettingsMenu,
+               lamps: Vec<Position>) {
   world.register::<terrain::TerrainDrawable>();
   world.register::<graphics::camera::CameraInputState>();
   world.register::<character::CharacterDrawable>();
@@ -49,15 +128,29 @@ fn setup_world(world: &mut World, dimensions: Dimensions) {
   world.register::<terrain_shape::terrain_shape_objects::TerrainShapeObjects>();
   world.register::<Zombies>();
   world.register::<Bullets>();
-  world.register::<CharacterSprite>();
+  world.register::<Animator>();
   world.register::<character::controls::CharacterInputState>();
   world.register::<MouseInputState>();
+  world.register::<Score>();
+  world.register::<DebugOverlay>();
+  world.register::<Particles>();
+  world.register::<Lights>();
 
   world.insert(dimensions);
   world.insert(character::controls::CharacterInputState::new());
   world.insert(MouseInputState::new());
   world.insert(DeltaTime(0.0));
   world.insert(GameTime(0));
+  world.insert(GameRng::new(seed));
+  world.insert(CombatEvents::default());
+  world.insert(ParticleEvents::default());
+  world.insert(LightEvents::default());
+  world.insert(TimeScale::default());
+  world.insert(AssetReload::default());
+  world.insert(animations);
+  world.insert(assets);
+  world.insert(settings);
+  world.insert(settings_menu);
 
   let mut hills = terrain_shape::terrain_shape_objects::TerrainShapeObjects::new();
 
@@ -65,58 +158,112 @@ fn setup_world(world: &mut World, dimensions: Dimensions) {
     hills.small_hill(hill[0], hill[1]);
   }
 
+  let mut character = character::CharacterDrawable::new();
+  let mut character_input = character::controls::CharacterInputState::new();
+  let mut camera_input = graphics::camera::CameraInputState::new();
+  let mut zombies = Zombies::new();
+  let mut terrain_objects = terrain_object::terrain_objects::TerrainObjects::new();
+  let mut bullets = Bullets::new();
+  let mut score = Score::new();
+
+  if let Some(save) = save_game {
+    save.apply(&mut character, &mut character_input, &mut camera_input, &mut score, &mut zombies, &mut terrain_objects, &mut bullets);
+  }
+
   world.create_entity()
     .with(terrain::TerrainDrawable::new())
-    .with(character::CharacterDrawable::new())
+    .with(character)
     .with(hud::hud_objects::HudObjects::new())
-    .with(terrain_object::terrain_objects::TerrainObjects::new())
+    .with(terrain_objects)
     .with(hills)
-    .with(Zombies::new())
-    .with(Bullets::new())
-    .with(CharacterSprite::new())
-    .with(graphics::camera::CameraInputState::new())
-    .with(character::controls::CharacterInputState::new())
+    .with(zombies)
+    .with(bullets)
+    .with(Animator::default())
+    .with(camera_input)
+    .with(character_input)
+    .with(score)
+    .with(DebugOverlay::new())
+    .with(Particles::new())
+    .with(Lights::new(lamps))
     .with(MouseInputState::new()).build();
 }
 
 fn dispatch_loop<W, D, F>(window: &mut W,
-                          w: &mut World)
+                          w: &mut World,
+                          mut draw: DrawSystem<D>,
+                          mut device_renderer: DeviceRenderer<D>,
+                          mut recorder: Option<InputRecorder>,
+                          mut replay: Option<InputReplay>,
+                          game_options: &GameOptions) -> GameResult<()>
   where W: Window<D, F>,
         D: gfx::Device + 'static,
         F: gfx::Factory<D::Resources>,
         D::CommandBuffer: Send {
-  let (mut device_renderer, encoder_queue) = DeviceRenderer::new(window.create_buffers(2));
-  let draw = {
-    let rtv = window.get_render_target_view();
-    let dsv = window.get_depth_stencil_view();
-    DrawSystem::new(window.get_factory(), &rtv, &dsv, encoder_queue)
-  };
-
-  let (audio_system, audio_control) = AudioSystem::new();
+  let (audio_system, audio_control) = AudioSystem::new(&w.read_resource::<AssetManager>());
   let (terrain_system, terrain_control) = CameraControlSystem::new();
   let (character_system, character_control) = CharacterControlSystem::new();
   let (mouse_system, mouse_control) = MouseControlSystem::new();
-  let controls = TilemapControls::new(audio_control, terrain_control, character_control, mouse_control);
-
-  let mut dispatcher = DispatcherBuilder::new()
-    .with(draw, "drawing", &[])
-    .with(terrain::PreDrawSystem, "draw-prep-terrain", &["drawing"])
-    .with(character::PreDrawSystem, "draw-prep-character", &["drawing"])
-    .with(zombie::PreDrawSystem, "draw-prep-zombie", &["drawing"])
-    .with(bullet::PreDrawSystem, "draw-prep-bullet", &["drawing"])
-    .with(hud::PreDrawSystem, "draw-prep-hud", &[])
+  let (save_system, save_control) = SaveSystem::new();
+  let (score_system, score_control) = ScoreSystem::new();
+  let (hud_system, hud_control) = hud::PreDrawSystem::new();
+  let (debug_system, debug_control) = DebugSystem::new();
+  let (console_system, console_control) = ConsoleSystem::new();
+  let (settings_system, settings_control) = SettingsMenuSystem::new(game_options.file_settings.clone());
+  let (input_recorder, recorded_inputs) = channel::unbounded();
+  let (screenshot_control, screenshot_requests) = channel::unbounded();
+  let controls = TilemapControls::new(audio_control, terrain_control, character_control, mouse_control, save_control,
+                                      score_control, hud_control, debug_control,
+                                      console_control, settings_control, screenshot_control, recorder.as_ref().map(|_| input_recorder));
+
+  let mut dispatcher_builder = DispatcherBuilder::new()
+    .with(terrain::PreDrawSystem, "draw-prep-terrain", &[])
+    .with(character::PreDrawSystem, "draw-prep-character", &[])
+    .with(AnimationSystem, "animation-system", &["draw-prep-character"])
+    .with(zombie::PreDrawSystem, "draw-prep-zombie", &[])
+    .with(bullet::PreDrawSystem, "draw-prep-bullet", &[])
+    .with(hud_system, "draw-prep-hud", &[])
     .with(terrain_system, "terrain-system", &[])
     .with(terrain_object::PreDrawSystem, "draw-prep-terrain_object", &["terrain-system"])
     .with(terrain_shape::PreDrawSystem, "draw-prep-terrain_shape_object", &["terrain-system"])
     .with(character_system, "character-system", &[])
     .with(mouse_system, "mouse-system", &[])
     .with(audio_system, "audio-system", &[])
-    .with(CollisionSystem, "collision-system", &["mouse-system"])
-    .build();
+    .with(CollisionSystem, "collision-system", &["mouse-system", "draw-prep-zombie", "draw-prep-bullet"])
+    .with(save_system, "save-system", &[])
+    .with(score_system, "score-system", &[])
+    .with(debug_system, "debug-system", &["draw-prep-zombie", "draw-prep-bullet"])
+    .with(console_system, "console-system", &[])
+    .with(settings_system, "settings-system", &[])
+    .with(ParticleSystem::new(), "particle-system", &["mouse-system", "draw-prep-zombie", "draw-prep-bullet"])
+    .with(LightSystem, "light-system", &["mouse-system", "draw-prep-character"]);
+  let hot_reload = game_options.hot_reload;
+  if hot_reload {
+    dispatcher_builder.add(AssetWatchSystem::new(&w.read_resource::<AssetManager>()), "asset-watch-system", &[]);
+  }
+  let mut dispatcher = dispatcher_builder.build();
+
+  // Drawing runs on the main thread so pipelines can be rebuilt with the window's factory
+  let assets = AssetManager::clone(&w.read_resource::<AssetManager>());
+  let mut shader_watcher = if hot_reload {
+    Some(AssetWatcher::new(&assets.path(SHADER_DIR)))
+  } else {
+    None
+  };
+
+  let mut registry = CommandRegistry::new();
+  console::register_commands(&mut registry);
+  character::register_commands(&mut registry);
+  zombie::register_commands(&mut registry);
+  w.insert(Console::new(&registry));
 
   window.set_controls(controls);
 
-  let start_time = time::Instant::now();
+  let mut result = match game_options.screenshot {
+    Some((ticks, _)) => Err(GameError::Render(format!("Game closed before screenshot tick {}", ticks))),
+    None => Ok(()),
+  };
+  let mut tick: u64 = 0;
+  let mut game_time = 0.0;
   let mut last_time = time::Instant::now();
   loop {
     let elapsed = last_time.elapsed();
@@ -124,19 +271,108 @@ fn dispatch_loop<W, D, F>(window: &mut W,
     // Throttle update speed
     if delta >= 0.0083 {
       last_time = time::Instant::now();
+
+      let tick_delta = if let Some(ref mut r) = replay {
+        match r.delta(tick) {
+          Some(recorded_delta) => {
+            for event in r.events_for(tick) {
+              window.replay_input(event);
+            }
+            recorded_delta
+          }
+          None => {
+            println!("Replay finished after {} ticks", tick);
+            break;
+          }
+        }
+      } else if game_options.screenshot.is_some() {
+        FIXED_TICK_DELTA * w.read_resource::<TimeScale>().0
+      } else {
+        delta * w.read_resource::<TimeScale>().0
+      };
+
+      if let Some(ref mut r) = recorder {
+        r.record_delta(tick_delta);
+      }
+
+      game_time += tick_delta;
+      *w.write_resource::<DeltaTime>() = DeltaTime(tick_delta);
+      *w.write_resource::<GameTime>() = GameTime(game_time as u64);
+
+      if let Some(ref mut watcher) = shader_watcher {
+        let changed = watcher.poll();
+        if !changed.is_empty() {
+          println!("Shaders {} changed, recompiling", changed.join(", "));
+          draw.reload_shaders(window.get_factory(), &assets);
+        }
+      }
+
+      if window.resize_targets() {
+        let rtv = window.get_render_target_view();
+        if let Err(e) = draw.resize(window.get_factory(), &rtv) {
+          println!("{}", e);
+        }
+        *w.write_resource::<Dimensions>() = window_dimensions(window);
+      }
+
+      let is_final_frame = game_options.screenshot.as_ref().map_or(false, |(ticks, _)| *ticks == tick);
+      let screenshot_path = if is_final_frame {
+        game_options.screenshot.as_ref().map(|(_, path)| PathBuf::from(path))
+      } else if screenshot_requests.try_iter().count() > 0 {
+        Some(screenshot_file())
+      } else {
+        None
+      };
+      if screenshot_path.is_some() {
+        draw.request_capture();
+      }
+
+      draw.run_now(w);
       dispatcher.dispatch(&w);
       w.maintain();
-
-      *w.write_resource::<DeltaTime>() = DeltaTime(delta);
-      *w.write_resource::<GameTime>() = GameTime(start_time.elapsed().as_secs());
+      console::execute_pending(w, &registry);
+      tick += 1;
 
       device_renderer.draw(window.get_device());
 
+      if let Some(path) = screenshot_path {
+        let saved = draw.save_capture(window.get_factory(), &path);
+        match saved {
+          Ok(()) => println!("Screenshot saved to {}", path.display()),
+          Err(ref e) => println!("{}", e),
+        }
+        if is_final_frame {
+          result = saved;
+          break;
+        }
+      }
+
       window.swap_window();
     }
 
     if let WindowStatus::Close = window.poll_events() {
       break;
     }
+
+    if let Some(ref mut r) = recorder {
+      while let Ok(event) = recorded_inputs.try_recv() {
+        r.record(tick, event);
+      }
+    }
   }
+
+  if let Some(r) = recorder {
+    match r.write() {
+      Ok(()) => println!("Replay recorded after {} ticks", tick),
+      Err(e) => println!("{}", e),
+    }
+  }
+  result
+}
+
+fn screenshot_file() -> PathBuf {
+  let timestamp = time::SystemTime::now()
+    .duration_since(time::UNIX_EPOCH)
+    .map_or(0, |d| d.as_millis());
+  Path::new(SCREENSHOT_DIR).join(format!("hinterland_{}.png", timestamp))
 }
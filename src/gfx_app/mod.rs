@@ -1,43 +1,78 @@
 use gfx::format::SurfaceType;
 use gfx::handle::{DepthStencilView, RenderTargetView};
 use gfx::memory::Typed;
-use glutin::{KeyboardInput, MouseButton, PossiblyCurrent, WindowedContext};
+use glutin::{KeyboardInput, MouseButton, MouseScrollDelta, PossiblyCurrent, WindowedContext};
 use glutin::dpi::LogicalSize;
 use glutin::ElementState::{Pressed, Released};
-use glutin::VirtualKeyCode::{A, D, Escape, R, S, W, X, Z};
+use glutin::VirtualKeyCode::{A, D, Down, Escape, F10, F12, F3, F5, F9, Grave, Left, M, R, Return, Right, S, Tab, Up, W, X, Z};
 use std::fmt::{Display, Formatter, Result};
 
 use crate::character::controls::CharacterControl;
-use crate::game::constants::{GAME_TITLE, RESOLUTION_X, RESOLUTION_Y};
+use crate::console::ConsoleControl;
+use crate::game::constants::GAME_TITLE;
 use crate::gfx_app::controls::{Control, TilemapControls};
+use crate::gfx_app::mouse_controls::cursor_aim;
+use crate::gfx_app::replay::InputEvent;
+use crate::save::SaveControl;
+use crate::settings::{menu::SettingsControl, Settings};
 
 pub mod init;
 pub mod renderer;
 pub mod system;
 pub mod controls;
+pub mod error_screen;
+#[cfg(target_os = "linux")]
+pub mod headless;
 pub mod mouse_controls;
+pub mod replay;
+mod replay_test;
 
 pub type ColorFormat = gfx::format::Rgba8;
 pub type DepthFormat = gfx::format::DepthStencil;
+pub type HdrColorFormat = (gfx::format::R16_G16_B16_A16, gfx::format::Float);
 
 pub const COLOR_FORMAT_VALUE: SurfaceType = SurfaceType::R8_G8_B8_A8;
 pub const DEPTH_FORMAT_VALUE: SurfaceType = SurfaceType::D24_S8;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct GameOptions {
-  windowed_mode: bool,
+  pub settings: Settings,
+  pub file_settings: Settings,
+  pub load_game: bool,
+  pub seed: u64,
+  pub record: Option<String>,
+  pub replay: Option<String>,
+  pub asset_dir: Option<String>,
+  pub hot_reload: bool,
+  pub screenshot: Option<(u64, String)>,
 }
 
 impl Display for GameOptions {
   fn fmt(&self, f: &mut Formatter) -> Result {
-    write!(f, "{}", format!("windowed_mode={}", self.windowed_mode))
+    write!(f, "{}", format!("settings={:?}, load_game={}, seed={}, record={:?}, replay={:?}, asset_dir={:?}, hot_reload={}, screenshot={:?}",
+                          self.settings, self.load_game, self.seed, self.record, self.replay, self.asset_dir, self.hot_reload, self.screenshot))
   }
 }
 
 impl GameOptions {
-  pub fn new(windowed_mode: bool) -> GameOptions {
+  pub fn new(settings: Settings,
+             file_settings: Settings,
+             load_game: bool,
+             seed: u64,
+             record: Option<String>,
+             replay: Option<String>,
+             asset_dir: Option<String>,
+             hot_reload: bool) -> GameOptions {
     GameOptions {
-      windowed_mode,
+      settings,
+      file_settings,
+      load_game,
+      seed,
+      record,
+      replay,
+      asset_dir,
+      hot_reload,
+      screenshot: None,
     }
   }
 }
@@ -51,6 +86,8 @@ pub struct WindowContext {
   render_target_view: RenderTargetView<gfx_device_gl::Resources, ColorFormat>,
   depth_stencil_view: DepthStencilView<gfx_device_gl::Resources, DepthFormat>,
   mouse_pos: (f64, f64),
+  aa: u8,
+  is_resized: bool,
   game_options: GameOptions
 }
 
@@ -63,27 +100,34 @@ impl WindowContext {
 
     println!("{}", game_options);
 
-    let builder = if game_options.windowed_mode {
-      let logical_size = LogicalSize::new(RESOLUTION_X.into(), RESOLUTION_Y.into());
-      window_title
-        .with_dimensions(logical_size)
-        .with_decorations(false)
+    let monitor = if game_options.settings.fullscreen {
+      let monitor = events_loop.get_available_monitors().next();
+      if monitor.is_none() {
+        println!("No monitor found, running in windowed mode");
+      }
+      monitor
     } else {
-      let monitor = {
-        events_loop.get_available_monitors().next().expect("No monitor found")
-      };
-      let monitor_resolution = monitor.get_dimensions();
-
-      let resolution = ((monitor_resolution.width as f32 * 16.0 / 9.0) as u32, monitor_resolution.height);
+      None
+    };
 
-      let logical_size = LogicalSize::new(resolution.0.into(), resolution.1);
-      window_title.with_fullscreen(Some(monitor))
-        .with_decorations(false)
-        .with_dimensions(logical_size)
+    let builder = match monitor {
+      Some(monitor) => {
+        let logical_size = monitor.get_dimensions().to_logical(monitor.get_hidpi_factor());
+        window_title.with_fullscreen(Some(monitor))
+          .with_decorations(false)
+          .with_dimensions(logical_size)
+      }
+      None => {
+        let (width, height) = game_options.settings.resolution;
+        let logical_size = LogicalSize::new(width.into(), height.into());
+        window_title
+          .with_dimensions(logical_size)
+          .with_resizable(true)
+      }
     };
 
     let window_context = glutin::ContextBuilder::new()
-      .with_vsync(true)
+      .with_vsync(game_options.settings.vsync)
       .with_double_buffer(Some(true))
       .with_pixel_format(24, 8)
       .with_srgb(true)
@@ -125,6 +169,8 @@ impl WindowContext {
       render_target_view: RenderTargetView::new(rtv),
       depth_stencil_view: DepthStencilView::new(dsv),
       mouse_pos: (0.0, 0.0),
+      aa,
+      is_resized: false,
       game_options,
     }
   }
@@ -140,14 +186,16 @@ pub trait Window<D: gfx::Device, F: gfx::Factory<D::Resources>> {
   fn swap_window(&mut self);
   fn create_buffers(&mut self, count: usize) -> Vec<D::CommandBuffer>;
   fn set_controls(&mut self, controls: controls::TilemapControls);
+  fn replay_input(&mut self, event: InputEvent);
   fn get_viewport_size(&mut self) -> (f32, f32);
+  fn resize_targets(&mut self) -> bool;
   fn get_device(&mut self) -> &mut D;
   fn get_factory(&mut self) -> &mut F;
   fn get_hidpi_factor(&mut self) -> f32;
   fn get_render_target_view(&mut self) -> RenderTargetView<D::Resources, ColorFormat>;
   fn get_depth_stencil_view(&mut self) -> DepthStencilView<D::Resources, DepthFormat>;
   fn poll_events(&mut self) -> WindowStatus;
-  fn is_windowed(&self) -> bool;
+  fn poll_close(&mut self) -> WindowStatus;
 }
 
 impl Window<gfx_device_gl::Device, gfx_device_gl::Factory> for WindowContext {
@@ -171,14 +219,46 @@ impl Window<gfx_device_gl::Device, gfx_device_gl::Factory> for WindowContext {
     self.controls = Some(controls);
   }
 
+  fn replay_input(&mut self, event: InputEvent) {
+    match self.controls {
+      Some(ref mut c) => c.apply(event),
+      None => panic!("Terrain controls have not been initialized"),
+    }
+  }
+
   fn get_viewport_size(&mut self) -> (f32, f32) {
-    if self.game_options.windowed_mode {
-      (RESOLUTION_X as f32, RESOLUTION_Y as f32)
-    } else {
-      let monitor = self.events_loop.get_available_monitors().next().expect("No monitor found");
-      let monitor_resolution = monitor.get_dimensions();
-      (monitor_resolution.width as f32, monitor_resolution.height as f32)
+    match self.window_context.window().get_inner_size() {
+      Some(size) => (size.width as f32, size.height as f32),
+      None => {
+        let (width, height) = self.game_options.settings.resolution;
+        (width as f32, height as f32)
+      }
+    }
+  }
+
+  fn resize_targets(&mut self) -> bool {
+    if !self.is_resized {
+      return false;
+    }
+    self.is_resized = false;
+    let window = self.window_context.window();
+    let size = match window.get_inner_size() {
+      Some(inner_size) => inner_size.to_physical(window.get_hidpi_factor()),
+      None => return false,
+    };
+    let (width, height) = (size.width as u16, size.height as u16);
+    let (current_width, current_height, _, _) = self.render_target_view.get_dimensions();
+    if width == 0 || height == 0 || (width, height) == (current_width, current_height) {
+      return false;
     }
+    self.window_context.resize(size);
+    let (rtv, dsv) =
+      gfx_device_gl::create_main_targets_raw((width, height, 1, self.aa.into()),
+                                             COLOR_FORMAT_VALUE,
+                                             DEPTH_FORMAT_VALUE);
+    self.render_target_view = RenderTargetView::new(rtv);
+    self.depth_stencil_view = DepthStencilView::new(dsv);
+    true
   }
 
   fn get_device(&mut self) -> &mut gfx_device_gl::Device {
@@ -190,11 +270,7 @@ impl Window<gfx_device_gl::Device, gfx_device_gl::Factory> for WindowContext {
   }
 
   fn get_hidpi_factor(&mut self) -> f32 {
-    if self.game_options.windowed_mode {
-      1.0
-    } else {
-      self.window_context.window().get_hidpi_factor() as f32
-    }
+    self.window_context.window().get_hidpi_factor() as f32
   }
 
   fn get_render_target_view(&mut self) -> RenderTargetView<gfx_device_gl::Resources, ColorFormat> {
@@ -206,28 +282,58 @@ impl Window<gfx_device_gl::Device, gfx_device_gl::Factory> for WindowContext {
   }
 
   fn poll_events(&mut self) -> WindowStatus {
-    use glutin::WindowEvent::{CursorMoved, CloseRequested, MouseInput};
+    use glutin::WindowEvent::{CursorMoved, CloseRequested, HiDpiFactorChanged, MouseInput, MouseWheel, ReceivedCharacter, Resized};
 
+    let window_size = self.get_viewport_size();
     let controls = match self.controls {
       Some(ref mut c) => c,
       None => panic!("Terrain controls have not been initialized"),
     };
 
     let m_pos = &mut self.mouse_pos;
+    let is_resized = &mut self.is_resized;
+    let is_replaying = self.game_options.replay.is_some();
     let mut game_status = WindowStatus::Open;
 
     self.events_loop.poll_events(|event| {
       game_status = if let glutin::Event::WindowEvent { event, .. } = event {
         match event {
+          Resized(_) | HiDpiFactorChanged(_) => {
+            *is_resized = true;
+            WindowStatus::Open
+          }
+          glutin::WindowEvent::KeyboardInput { input, .. } if is_replaying => {
+            if let Some(Escape) = input.virtual_keycode { WindowStatus::Close } else { WindowStatus::Open }
+          }
+          MouseInput { .. } | MouseWheel { .. } | ReceivedCharacter(_) if is_replaying => WindowStatus::Open,
+          glutin::WindowEvent::KeyboardInput { input, .. } if controls.is_settings_open() => { process_settings_input(input, controls) }
+          glutin::WindowEvent::KeyboardInput { input, .. } if controls.is_console_open() => { process_console_input(input, controls) }
+          MouseInput { .. } | MouseWheel { .. } if controls.is_settings_open() || controls.is_console_open() => WindowStatus::Open,
+          ReceivedCharacter(character) if controls.is_console_open() => {
+            controls.console_input(ConsoleControl::Character(character));
+            WindowStatus::Open
+          }
           glutin::WindowEvent::KeyboardInput { input, .. } => { process_keyboard_input(input, controls) }
           MouseInput { state: Pressed, button: MouseButton::Left, .. } => {
-            controls.mouse_left_click(Some(*m_pos));
+            controls.mouse_left_click(Some(cursor_aim(*m_pos, window_size)));
             WindowStatus::Open
           }
           MouseInput { state: Released, button: MouseButton::Left, .. } => {
             controls.mouse_left_click(None);
             WindowStatus::Open
           }
+          ReceivedCharacter(_) if controls.is_settings_open() => WindowStatus::Open,
+          ReceivedCharacter(character) => {
+            controls.enter_character(character);
+            WindowStatus::Open
+          }
+          MouseWheel { delta, .. } => {
+            controls.zoom_by(match delta {
+              MouseScrollDelta::LineDelta(_, y) => y,
+              MouseScrollDelta::PixelDelta(position) => position.y as f32 / 16.0,
+            });
+            WindowStatus::Open
+          }
           CursorMoved { position, .. } => {
             *m_pos = ((position.x as f32).into(), (position.y as f32).into());
             WindowStatus::Open
@@ -242,11 +348,47 @@ impl Window<gfx_device_gl::Device, gfx_device_gl::Factory> for WindowContext {
     game_status
   }
 
-  fn is_windowed(&self) -> bool {
-    self.game_options.windowed_mode
+  fn poll_close(&mut self) -> WindowStatus {
+    let mut status = WindowStatus::Open;
+    self.events_loop.poll_events(|event| {
+      if let glutin::Event::WindowEvent { event, .. } = event {
+        match event {
+          glutin::WindowEvent::KeyboardInput { input: KeyboardInput { state: Pressed, virtual_keycode: Some(Escape), .. }, .. } |
+          glutin::WindowEvent::CloseRequested => status = WindowStatus::Close,
+          _ => (),
+        }
+      }
+    });
+    status
   }
 }
 
+fn process_console_input(input: glutin::KeyboardInput, controls: &mut TilemapControls) -> WindowStatus {
+  match input {
+    KeyboardInput { state: Pressed, virtual_keycode: Some(Grave), .. } |
+    KeyboardInput { state: Pressed, virtual_keycode: Some(Escape), .. } => controls.toggle_console(),
+    KeyboardInput { state: Pressed, virtual_keycode: Some(Up), .. } => controls.console_input(ConsoleControl::HistoryPrevious),
+    KeyboardInput { state: Pressed, virtual_keycode: Some(Down), .. } => controls.console_input(ConsoleControl::HistoryNext),
+    KeyboardInput { state: Pressed, virtual_keycode: Some(Tab), .. } => controls.console_input(ConsoleControl::Complete),
+    _ => (),
+  }
+  WindowStatus::Open
+}
+
+fn process_settings_input(input: glutin::KeyboardInput, controls: &mut TilemapControls) -> WindowStatus {
+  match input {
+    KeyboardInput { state: Pressed, virtual_keycode: Some(F10), .. } |
+    KeyboardInput { state: Pressed, virtual_keycode: Some(Escape), .. } |
+    KeyboardInput { state: Pressed, virtual_keycode: Some(Return), .. } => controls.toggle_settings(),
+    KeyboardInput { state: Pressed, virtual_keycode: Some(Up), .. } => controls.settings_input(SettingsControl::Previous),
+    KeyboardInput { state: Pressed, virtual_keycode: Some(Down), .. } => controls.settings_input(SettingsControl::Next),
+    KeyboardInput { state: Pressed, virtual_keycode: Some(Left), .. } => controls.settings_input(SettingsControl::Decrease),
+    KeyboardInput { state: Pressed, virtual_keycode: Some(Right), .. } => controls.settings_input(SettingsControl::Increase),
+    _ => (),
+  }
+  WindowStatus::Open
+}
+
 fn process_keyboard_input(input: glutin::KeyboardInput, controls: &mut TilemapControls) -> WindowStatus {
   match input {
     KeyboardInput { state: Pressed, virtual_keycode: Some(Z), .. } => {
@@ -285,6 +427,27 @@ fn process_keyboard_input(input: glutin::KeyboardInput, controls: &mut TilemapCo
     KeyboardInput { state: Released, virtual_keycode: Some(R), .. } => {
       controls.reload_weapon(false);
     }
+    KeyboardInput { state: Pressed, virtual_keycode: Some(F5), .. } => {
+      controls.save_game(SaveControl::QuickSave);
+    }
+    KeyboardInput { state: Pressed, virtual_keycode: Some(F9), .. } => {
+      controls.save_game(SaveControl::QuickLoad);
+    }
+    KeyboardInput { state: Pressed, virtual_keycode: Some(M), .. } => {
+      controls.toggle_minimap();
+    }
+    KeyboardInput { state: Pressed, virtual_keycode: Some(F3), .. } => {
+      controls.toggle_debug_overlay();
+    }
+    KeyboardInput { state: Pressed, virtual_keycode: Some(Grave), .. } => {
+      controls.toggle_console();
+    }
+    KeyboardInput { state: Pressed, virtual_keycode: Some(F10), .. } => {
+      controls.toggle_settings();
+    }
+    KeyboardInput { state: Pressed, virtual_keycode: Some(F12), .. } => {
+      controls.take_screenshot();
+    }
     KeyboardInput { state: Pressed, modifiers, .. } => {
       if modifiers.ctrl {
         controls.ctrl_pressed(true);
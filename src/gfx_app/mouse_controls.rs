@@ -1,11 +1,14 @@
-use cgmath::Point2;
+use cgmath::{Point2, Vector2};
 use crossbeam_channel as channel;
-use specs::prelude::{Read, ReadStorage, WriteStorage};
+use specs::prelude::{Read, ReadStorage, Write, WriteStorage};
 
 use crate::bullet::bullets::Bullets;
 use crate::character::{CharacterDrawable, controls::CharacterInputState};
-use crate::game::constants::SMALL_HILLS;
-use crate::graphics::{camera::CameraInputState, check_terrain_elevation, dimensions::Dimensions, direction};
+use crate::game::constants::{MUZZLE_OFFSET, SMALL_HILLS};
+use crate::graphics::{camera::CameraInputState, check_terrain_elevation, dimensions::Dimensions, direction, direction_movement, orientation::Stance};
+use crate::lighting::LightEvents;
+use crate::particle::{EmitterKind, ParticleEvents};
+use crate::score::Score;
 use crate::shaders::Position;
 
 type MouseEvent = channel::Sender<(MouseControl, Option<(f64, f64)>)>;
@@ -37,6 +40,17 @@ impl specs::prelude::Component for MouseInputState {
   type Storage = specs::storage::VecStorage<MouseInputState>;
 }
 
+pub fn cursor_aim(cursor: (f64, f64), window_size: (f32, f32)) -> (f64, f64) {
+  let x = cursor.0 - f64::from(window_size.0) / 2.0;
+  let y = cursor.1 - f64::from(window_size.1) / 2.0;
+  let length = (x * x + y * y).sqrt();
+  if length > 0.0 {
+    (x / length, y / length)
+  } else {
+    (0.0, 0.0)
+  }
+}
+
 pub enum MouseControl {
   LeftClick,
 }
@@ -60,24 +74,36 @@ impl<'a> specs::prelude::System<'a> for MouseControlSystem {
                      ReadStorage<'a, CameraInputState>,
                      ReadStorage<'a, CharacterInputState>,
                      WriteStorage<'a, Bullets>,
+                     WriteStorage<'a, Score>,
+                     Write<'a, ParticleEvents>,
+                     Write<'a, LightEvents>,
                      Read<'a, Dimensions>);
 
-  fn run(&mut self, (mut mouse_input, mut character_drawable, camera, character_input, mut bullets, dim): Self::SystemData) {
+  fn run(&mut self, (mut mouse_input, mut character_drawable, camera, character_input, mut bullets, mut score, mut particle_events, mut light_events, dim): Self::SystemData) {
     use specs::join::Join;
 
     while let Ok((control_value, value)) = self.queue.try_recv() {
       match control_value {
         MouseControl::LeftClick => {
-          for (mut mi, cd, bs, ca, ci) in (&mut mouse_input, &mut character_drawable, &mut bullets, &camera, &character_input).join() {
-            if let Some(val) = value {
-              if ci.is_shooting && cd.stats.ammunition > 0 {
+          for (mi, cd, bs, s, ca, ci) in (&mut mouse_input, &mut character_drawable, &mut bullets, &mut score, &camera, &character_input).join() {
+            if let Some(aim) = value {
+              if ci.is_shooting && cd.stats.ammunition > 0 && cd.stance != Stance::NormalDeath {
                 cd.stats.ammunition -= 1;
+                s.register_shot();
                 let start_point = Point2::new(dim.window_width / 2.0 * dim.hidpi_factor, dim.window_height / 2.0 * dim.hidpi_factor);
-                let end_point = Point2::new(val.0 as f32 * dim.hidpi_factor, val.1 as f32 * dim.hidpi_factor);
+                let end_point = start_point + Vector2::new(aim.0 as f32, aim.1 as f32) * start_point.y;
                 mi.left_click_point = Some(end_point);
                 let dir = direction(start_point, end_point);
                 let elevated_pos_y = check_terrain_elevation(ci.movement, &SMALL_HILLS);
                 Bullets::add_bullet(bs, Position::new(-ca.movement.x(), ca.movement.y() + elevated_pos_y), dir);
+
+                let shot_direction = direction_movement(dir);
+                let draw_direction = Point2::new(shot_direction.x, -shot_direction.y);
+                let player_position = ci.movement - cd.position;
+                let muzzle_position = player_position - Position::new(draw_direction.x * MUZZLE_OFFSET, draw_direction.y * MUZZLE_OFFSET);
+                particle_events.emit(EmitterKind::MuzzleFlash, muzzle_position, draw_direction);
+                light_events.flash(muzzle_position);
+                particle_events.emit(EmitterKind::ShellCasing, player_position, Point2::new(-draw_direction.y, draw_direction.x));
               }
             } else {
               mi.left_click_point = None;
@@ -0,0 +1,240 @@
+use std::{collections::VecDeque, fs};
+
+use json::JsonValue;
+
+use crate::character::controls::CharacterControl;
+use crate::gfx_app::controls::Control;
+use crate::save::SaveControl;
+use crate::settings::{Difficulty, Settings};
+
+const REPLAY_FORMAT_VERSION: u32 = 2;
+
+#[derive(Clone, Copy)]
+pub enum InputEvent {
+  Zoom(Control),
+  ZoomBy(f32),
+  Character(CharacterControl),
+  MouseLeftClick(Option<(f64, f64)>),
+  Save(SaveControl),
+}
+
+fn zoom_name(control: Control) -> &'static str {
+  match control {
+    Control::Plus => "Plus",
+    Control::Negative => "Negative",
+    Control::Released => "Released",
+  }
+}
+
+fn zoom_from_name(name: &str) -> Option<Control> {
+  match name {
+    "Plus" => Some(Control::Plus),
+    "Negative" => Some(Control::Negative),
+    "Released" => Some(Control::Released),
+    _ => None,
+  }
+}
+
+fn character_name(control: CharacterControl) -> &'static str {
+  match control {
+    CharacterControl::Left => "Left",
+    CharacterControl::Right => "Right",
+    CharacterControl::Up => "Up",
+    CharacterControl::Down => "Down",
+    CharacterControl::XMoveStop => "XMoveStop",
+    CharacterControl::YMoveStop => "YMoveStop",
+    CharacterControl::CtrlPressed => "CtrlPressed",
+    CharacterControl::CtrlReleased => "CtrlReleased",
+    CharacterControl::ReloadPressed => "ReloadPressed",
+    CharacterControl::ReloadReleased => "ReloadReleased",
+  }
+}
+
+fn character_from_name(name: &str) -> Option<CharacterControl> {
+  match name {
+    "Left" => Some(CharacterControl::Left),
+    "Right" => Some(CharacterControl::Right),
+    "Up" => Some(CharacterControl::Up),
+    "Down" => Some(CharacterControl::Down),
+    "XMoveStop" => Some(CharacterControl::XMoveStop),
+    "YMoveStop" => Some(CharacterControl::YMoveStop),
+    "CtrlPressed" => Some(CharacterControl::CtrlPressed),
+    "CtrlReleased" => Some(CharacterControl::CtrlReleased),
+    "ReloadPressed" => Some(CharacterControl::ReloadPressed),
+    "ReloadReleased" => Some(CharacterControl::ReloadReleased),
+    _ => None,
+  }
+}
+
+fn save_name(control: SaveControl) -> &'static str {
+  match control {
+    SaveControl::QuickSave => "QuickSave",
+    SaveControl::QuickLoad => "QuickLoad",
+  }
+}
+
+fn save_from_name(name: &str) -> Option<SaveControl> {
+  match name {
+    "QuickSave" => Some(SaveControl::QuickSave),
+    "QuickLoad" => Some(SaveControl::QuickLoad),
+    _ => None,
+  }
+}
+
+impl InputEvent {
+  fn to_json(self, tick: u64) -> JsonValue {
+    let mut event = JsonValue::new_object();
+    event["tick"] = tick.into();
+    match self {
+      InputEvent::Zoom(control) => {
+        event["type"] = "zoom".into();
+        event["value"] = zoom_name(control).into();
+      }
+      InputEvent::ZoomBy(amount) => {
+        event["type"] = "zoom_by".into();
+        event["value"] = amount.into();
+      }
+      InputEvent::Character(control) => {
+        event["type"] = "character".into();
+        event["value"] = character_name(control).into();
+      }
+      InputEvent::MouseLeftClick(aim) => {
+        event["type"] = "mouse_left_click".into();
+        event["value"] = match aim {
+          Some((x, y)) => JsonValue::Array(vec![x.into(), y.into()]),
+          None => JsonValue::Null,
+        };
+      }
+      InputEvent::Save(control) => {
+        event["type"] = "save".into();
+        event["value"] = save_name(control).into();
+      }
+    }
+    event
+  }
+
+  fn from_json(event: &JsonValue) -> Result<(u64, InputEvent), String> {
+    let tick = event["tick"].as_u64().ok_or("Replay event tick missing")?;
+    let value = &event["value"];
+    let input_event = match event["type"].as_str() {
+      Some("zoom") => value.as_str().and_then(zoom_from_name).map(InputEvent::Zoom),
+      Some("zoom_by") => value.as_f32().map(InputEvent::ZoomBy),
+      Some("character") => value.as_str().and_then(character_from_name).map(InputEvent::Character),
+      Some("mouse_left_click") => {
+        if value.is_null() {
+          Some(InputEvent::MouseLeftClick(None))
+        } else {
+          value[0].as_f64()
+            .and_then(|x| value[1].as_f64().map(|y| (x, y)))
+            .map(|aim| InputEvent::MouseLeftClick(Some(aim)))
+        }
+      }
+      Some("save") => value.as_str().and_then(save_from_name).map(InputEvent::Save),
+      _ => None,
+    };
+    input_event
+      .map(|e| (tick, e))
+      .ok_or_else(|| format!("Invalid replay event {}", event.dump()))
+  }
+}
+
+pub struct InputRecorder {
+  path: String,
+  seed: u64,
+  difficulty: Difficulty,
+  mouse_sensitivity: f32,
+  deltas: Vec<f64>,
+  events: Vec<(u64, InputEvent)>,
+}
+
+impl InputRecorder {
+  pub fn new(path: &str, seed: u64, settings: &Settings) -> InputRecorder {
+    InputRecorder {
+      path: path.to_string(),
+      seed,
+      difficulty: settings.difficulty,
+      mouse_sensitivity: settings.mouse_sensitivity,
+      deltas: Vec::new(),
+      events: Vec::new(),
+    }
+  }
+
+  pub fn record_delta(&mut self, delta: f64) {
+    self.deltas.push(delta);
+  }
+
+  pub fn record(&mut self, tick: u64, event: InputEvent) {
+    self.events.push((tick, event));
+  }
+
+  pub fn write(&self) -> Result<(), String> {
+    let mut replay = JsonValue::new_object();
+    replay["version"] = REPLAY_FORMAT_VERSION.into();
+    replay["seed"] = self.seed.to_string().into();
+    replay["difficulty"] = self.difficulty.name().into();
+    replay["mouse_sensitivity"] = self.mouse_sensitivity.into();
+    replay["deltas"] = JsonValue::Array(self.deltas.iter().map(|d| (*d).into()).collect());
+    replay["events"] = JsonValue::Array(self.events.iter().map(|(tick, e)| e.to_json(*tick)).collect());
+    fs::write(&self.path, replay.dump())
+      .map_err(|e| format!("Replay file {} write error {}", self.path, e))
+  }
+}
+
+pub struct InputReplay {
+  pub seed: u64,
+  pub difficulty: Difficulty,
+  pub mouse_sensitivity: f32,
+  deltas: Vec<f64>,
+  events: VecDeque<(u64, InputEvent)>,
+}
+
+impl InputReplay {
+  pub fn load(path: &str) -> Result<InputReplay, String> {
+    let replay_json = fs::read_to_string(path)
+      .map_err(|e| format!("Replay file {} not found: {}", path, e))?;
+    let replay = json::parse(&replay_json)
+      .map_err(|e| format!("Replay file {} parse error {:?}", path, e))?;
+
+    match replay["version"].as_u32() {
+      Some(REPLAY_FORMAT_VERSION) => (),
+      _ => return Err(format!("Unsupported replay file {}", path)),
+    }
+
+    let seed = replay["seed"].as_str()
+      .and_then(|s| s.parse::<u64>().ok())
+      .ok_or("Replay seed missing")?;
+    let difficulty = replay["difficulty"].as_str()
+      .and_then(Difficulty::from_name)
+      .unwrap_or_default();
+    let mouse_sensitivity = replay["mouse_sensitivity"].as_f32()
+      .unwrap_or_else(|| Settings::default().mouse_sensitivity);
+    let deltas = replay["deltas"].members()
+      .map(|d| d.as_f64().ok_or_else(|| "Invalid replay delta".to_string()))
+      .collect::<Result<Vec<f64>, String>>()?;
+    let events = replay["events"].members()
+      .map(InputEvent::from_json)
+      .collect::<Result<VecDeque<(u64, InputEvent)>, String>>()?;
+
+    Ok(InputReplay {
+      seed,
+      difficulty,
+      mouse_sensitivity,
+      deltas,
+      events,
+    })
+  }
+
+  pub fn delta(&self, tick: u64) -> Option<f64> {
+    self.deltas.get(tick as usize).cloned()
+  }
+
+  pub fn events_for(&mut self, tick: u64) -> Vec<InputEvent> {
+    let mut events = Vec::new();
+    while self.events.front().map_or(false, |(t, _)| *t <= tick) {
+      if let Some((_, event)) = self.events.pop_front() {
+        events.push(event);
+      }
+    }
+    events
+  }
+}
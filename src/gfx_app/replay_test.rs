@@ -0,0 +1,84 @@
+#[test]
+fn replay_round_trip_test() {
+  use crate::character::controls::CharacterControl;
+  use crate::gfx_app::controls::Control;
+  use crate::gfx_app::replay::{InputEvent, InputRecorder, InputReplay};
+  use crate::save::SaveControl;
+  use crate::settings::{Difficulty, Settings};
+
+  let path = std::env::temp_dir().join(format!("hinterland_replay_test_{}.json", std::process::id()));
+  let filename = path.to_str().unwrap();
+  let settings = Settings {
+    difficulty: Difficulty::Hard,
+    mouse_sensitivity: 1.5,
+    ..Settings::default()
+  };
+  let mut recorder = InputRecorder::new(filename, 42, &settings);
+  recorder.record_delta(0.016);
+  recorder.record_delta(0.017);
+  recorder.record(0, InputEvent::Character(CharacterControl::Left));
+  recorder.record(0, InputEvent::Zoom(Control::Plus));
+  recorder.record(1, InputEvent::ZoomBy(-0.5));
+  recorder.record(1, InputEvent::MouseLeftClick(Some((0.6, -0.8))));
+  recorder.record(3, InputEvent::MouseLeftClick(None));
+  recorder.record(3, InputEvent::Save(SaveControl::QuickSave));
+  recorder.write().unwrap();
+  let mut replay = InputReplay::load(filename).unwrap();
+  std::fs::remove_file(&path).unwrap();
+
+  assert_eq!(42, replay.seed);
+  assert_eq!(Difficulty::Hard, replay.difficulty);
+  assert_eq!(1.5, replay.mouse_sensitivity);
+  assert_eq!(Some(0.017), replay.delta(1));
+  assert_eq!(None, replay.delta(2));
+
+  match replay.events_for(0).as_slice() {
+    [InputEvent::Character(CharacterControl::Left), InputEvent::Zoom(Control::Plus)] => (),
+    _ => panic!("Tick 0 events should be replayed in order"),
+  }
+  match replay.events_for(1).as_slice() {
+    [InputEvent::ZoomBy(amount), InputEvent::MouseLeftClick(Some((x, y)))] => {
+      assert_eq!(-0.5, *amount);
+      assert_eq!((0.6, -0.8), (*x, *y));
+    }
+    _ => panic!("Tick 1 events should be replayed in order"),
+  }
+  assert!(replay.events_for(2).is_empty());
+  match replay.events_for(3).as_slice() {
+    [InputEvent::MouseLeftClick(None), InputEvent::Save(SaveControl::QuickSave)] => (),
+    _ => panic!("Tick 3 events should be replayed in order"),
+  }
+}
+
+#[test]
+fn malformed_replay_test() {
+  use crate::gfx_app::replay::InputReplay;
+
+  let path = std::env::temp_dir().join(format!("hinterland_malformed_replay_test_{}.json", std::process::id()));
+  let filename = path.to_str().unwrap();
+  let malformed = [
+    "not json",
+    r#"{ "version": 99, "seed": "1" }"#,
+    r#"{ "version": 2 }"#,
+    r#"{ "version": 2, "seed": "1", "deltas": ["fast"] }"#,
+    r#"{ "version": 2, "seed": "1", "events": [{ "tick": 0, "type": "character", "value": "Jump" }] }"#,
+    r#"{ "version": 2, "seed": "1", "events": [{ "type": "zoom", "value": "Plus" }] }"#,
+  ];
+  for replay in malformed.iter() {
+    std::fs::write(&path, replay).unwrap();
+    assert!(InputReplay::load(filename).is_err(), "Replay {} should be rejected", replay);
+  }
+  std::fs::remove_file(&path).unwrap();
+
+  assert!(InputReplay::load(filename).is_err(), "Missing replay file should be rejected");
+}
+
+#[test]
+fn cursor_aim_test() {
+  use crate::gfx_app::mouse_controls::cursor_aim;
+
+  assert_eq!((1.0, 0.0), cursor_aim((1000.0, 360.0), (1280.0, 720.0)));
+  assert_eq!((1.0, 0.0), cursor_aim((700.0, 200.0), (800.0, 400.0)), "Aim should not depend on window size");
+  assert_eq!((0.6, -0.8), cursor_aim((700.0, 280.0), (1280.0, 720.0)));
+  assert_eq!((0.0, 0.0), cursor_aim((640.0, 360.0), (1280.0, 720.0)), "Window center should have no aim");
+}
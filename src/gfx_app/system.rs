@@ -1,96 +1,208 @@
+use std::path::Path;
 use std::time::Instant;
 
-use specs::prelude::{Read, WriteStorage};
+use gfx::format::R8_G8_B8_A8;
+use image::RgbaImage;
+use specs::prelude::{Read, ReadStorage, Write, WriteStorage};
 
 use crate::{bullet, terrain_shape};
+use crate::animation::{AnimationLibrary, Animator};
+use crate::assets::AssetManager;
 use crate::character;
-use crate::critter::CharacterSprite;
-use crate::game::constants::{CURRENT_AMMO_TEXT, GAME_VERSION, HUD_TEXTS};
-use crate::gfx_app::{ColorFormat, DepthFormat};
+use crate::console::Console;
+use crate::debug::{DebugDrawSystem, DebugOverlay};
+use crate::error::{GameError, GameResult};
+use crate::game::constants::{SHADER_DIR, TERRAIN_SHAPE_DEPTH, TERRAIN_TEXTURE_PATH};
+use crate::gfx_app::ColorFormat;
 use crate::gfx_app::renderer::EncoderQueue;
-use crate::graphics::{DeltaTime, orientation::{Orientation, Stance}};
+use crate::graphics::{AssetReload, dimensions::Dimensions, GameTime, orientation::Orientation};
+use crate::graphics::atlas::{build_sprite_atlas, load_sprite_atlas, TextureAtlas};
 use crate::graphics::Drawables;
+use crate::graphics::post_process::{danger_level, night_factor, post_locals, PostProcessSystem};
+use crate::graphics::render_target::{OffscreenTarget, PresentDrawSystem, RenderTargets, save_screenshot};
+use crate::graphics::sprite_batch::sprite_depth;
+use crate::graphics::texture::update_texture;
 use crate::hud;
+use crate::hud::layout::ScreenLayout;
+use crate::lighting::{LightBuffers, Lights};
+use crate::particle::{ParticleDrawSystem, Particles};
+use crate::settings::{menu::SettingsMenu, Settings};
 use crate::terrain;
+use crate::terrain::tile_map::Terrain;
 use crate::terrain_object;
 use crate::terrain_object::TerrainTexture;
 use crate::zombie;
 
 pub struct DrawSystem<D: gfx::Device> {
-  render_target_view: gfx::handle::RenderTargetView<D::Resources, ColorFormat>,
-  depth_stencil_view: gfx::handle::DepthStencilView<D::Resources, DepthFormat>,
+  offscreen: OffscreenTarget<D::Resources>,
+  post_system: PostProcessSystem<D::Resources>,
+  post_frame: u32,
+  present_system: PresentDrawSystem<D::Resources>,
+  capture_frame: bool,
+  light_buffers: LightBuffers<D::Resources>,
   terrain_system: terrain::TerrainDrawSystem<D::Resources>,
   character_system: character::CharacterDrawSystem<D::Resources>,
   zombie_system: zombie::ZombieDrawSystem<D::Resources>,
   bullet_system: bullet::BulletDrawSystem<D::Resources>,
   terrain_object_system: [terrain_object::TerrainObjectDrawSystem<D::Resources>; 3],
   terrain_shape_system: [terrain_shape::TerrainShapeDrawSystem<D::Resources>; 9],
-  text_system: [hud::TextDrawSystem<D::Resources>; 3],
+  text_system: hud::TextDrawSystem<D::Resources>,
+  ui_system: hud::ui::UiDrawSystem<D::Resources>,
+  particle_system: ParticleDrawSystem<D::Resources>,
+  debug_system: DebugDrawSystem<D::Resources>,
+  sprite_atlas: TextureAtlas,
+  sprite_texture: gfx::handle::Texture<D::Resources, R8_G8_B8_A8>,
   encoder_queue: EncoderQueue<D>,
   game_time: Instant,
   frames: u32,
-  cool_down: f64,
-  run_cool_down: f64,
-  fire_cool_down: f64,
 }
 
 impl<D: gfx::Device> DrawSystem<D> {
   pub fn new<F>(factory: &mut F,
                 rtv: &gfx::handle::RenderTargetView<D::Resources, ColorFormat>,
-                dsv: &gfx::handle::DepthStencilView<D::Resources, DepthFormat>,
+                msaa: u16,
+                assets: &AssetManager,
+                terrain: &Terrain,
+                tile_sheet: &RgbaImage,
                 encoder_queue: EncoderQueue<D>)
-                -> DrawSystem<D>
+                -> GameResult<DrawSystem<D>>
     where F: gfx::Factory<D::Resources> {
-    DrawSystem {
-      render_target_view: rtv.clone(),
-      depth_stencil_view: dsv.clone(),
-      terrain_system: terrain::TerrainDrawSystem::new(factory, rtv.clone(), dsv.clone()),
-      character_system: character::CharacterDrawSystem::new(factory, rtv.clone(), dsv.clone()),
-      zombie_system: zombie::ZombieDrawSystem::new(factory, rtv.clone(), dsv.clone()),
-      bullet_system: bullet::BulletDrawSystem::new(factory, rtv.clone(), dsv.clone()),
+    let sprite_atlas = load_sprite_atlas(factory, assets)?;
+    let (width, height, _, _) = rtv.get_dimensions();
+    let offscreen = OffscreenTarget::new(factory, width, height)?;
+    let post_system = PostProcessSystem::new(factory, offscreen.rtv.clone(), msaa, assets)?;
+    let scene = &post_system.scene;
+    let light_buffers = LightBuffers::new(factory);
+    Ok(DrawSystem {
+      terrain_system: terrain::TerrainDrawSystem::new(factory, scene.rtv.clone(), scene.dsv.clone(), &light_buffers, terrain, tile_sheet, assets)?,
+      character_system: character::CharacterDrawSystem::new(factory, scene.rtv.clone(), scene.dsv.clone(), &light_buffers, &sprite_atlas, assets)?,
+      zombie_system: zombie::ZombieDrawSystem::new(factory, scene.rtv.clone(), scene.dsv.clone(), &light_buffers, &sprite_atlas, assets)?,
+      bullet_system: bullet::BulletDrawSystem::new(factory, scene.rtv.clone(), scene.dsv.clone(), assets)?,
       terrain_object_system: [
-        terrain_object::TerrainObjectDrawSystem::new(factory, rtv.clone(), dsv.clone(), TerrainTexture::Ammo),
-        terrain_object::TerrainObjectDrawSystem::new(factory, rtv.clone(), dsv.clone(), TerrainTexture::House),
-        terrain_object::TerrainObjectDrawSystem::new(factory, rtv.clone(), dsv.clone(), TerrainTexture::Tree)
+        terrain_object::TerrainObjectDrawSystem::new(factory, scene.rtv.clone(), scene.dsv.clone(), &light_buffers, &sprite_atlas, TerrainTexture::Ammo, assets)?,
+        terrain_object::TerrainObjectDrawSystem::new(factory, scene.rtv.clone(), scene.dsv.clone(), &light_buffers, &sprite_atlas, TerrainTexture::House, assets)?,
+        terrain_object::TerrainObjectDrawSystem::new(factory, scene.rtv.clone(), scene.dsv.clone(), &light_buffers, &sprite_atlas, TerrainTexture::Tree, assets)?
       ],
       terrain_shape_system: [
-        terrain_shape::TerrainShapeDrawSystem::new(factory, rtv.clone(), dsv.clone(), Orientation::Right),
-        terrain_shape::TerrainShapeDrawSystem::new(factory, rtv.clone(), dsv.clone(), Orientation::DownRight),
-        terrain_shape::TerrainShapeDrawSystem::new(factory, rtv.clone(), dsv.clone(), Orientation::Down),
-        terrain_shape::TerrainShapeDrawSystem::new(factory, rtv.clone(), dsv.clone(), Orientation::DownLeft),
-        terrain_shape::TerrainShapeDrawSystem::new(factory, rtv.clone(), dsv.clone(), Orientation::Left),
-        terrain_shape::TerrainShapeDrawSystem::new(factory, rtv.clone(), dsv.clone(), Orientation::UpLeft),
-        terrain_shape::TerrainShapeDrawSystem::new(factory, rtv.clone(), dsv.clone(), Orientation::UpRight),
-        terrain_shape::TerrainShapeDrawSystem::new(factory, rtv.clone(), dsv.clone(), Orientation::Normal),
-        terrain_shape::TerrainShapeDrawSystem::new(factory, rtv.clone(), dsv.clone(), Orientation::Up),
-      ],
-      text_system: [
-        hud::TextDrawSystem::new(factory, &HUD_TEXTS, GAME_VERSION, rtv.clone(), dsv.clone()),
-        hud::TextDrawSystem::new(factory, &HUD_TEXTS, CURRENT_AMMO_TEXT, rtv.clone(), dsv.clone()),
-        hud::TextDrawSystem::new(factory, &HUD_TEXTS, CURRENT_AMMO_TEXT, rtv.clone(), dsv.clone())
+        terrain_shape::TerrainShapeDrawSystem::new(factory, scene.rtv.clone(), scene.dsv.clone(), &light_buffers, &sprite_atlas, Orientation::Right, assets)?,
+        terrain_shape::TerrainShapeDrawSystem::new(factory, scene.rtv.clone(), scene.dsv.clone(), &light_buffers, &sprite_atlas, Orientation::DownRight, assets)?,
+        terrain_shape::TerrainShapeDrawSystem::new(factory, scene.rtv.clone(), scene.dsv.clone(), &light_buffers, &sprite_atlas, Orientation::Down, assets)?,
+        terrain_shape::TerrainShapeDrawSystem::new(factory, scene.rtv.clone(), scene.dsv.clone(), &light_buffers, &sprite_atlas, Orientation::DownLeft, assets)?,
+        terrain_shape::TerrainShapeDrawSystem::new(factory, scene.rtv.clone(), scene.dsv.clone(), &light_buffers, &sprite_atlas, Orientation::Left, assets)?,
+        terrain_shape::TerrainShapeDrawSystem::new(factory, scene.rtv.clone(), scene.dsv.clone(), &light_buffers, &sprite_atlas, Orientation::UpLeft, assets)?,
+        terrain_shape::TerrainShapeDrawSystem::new(factory, scene.rtv.clone(), scene.dsv.clone(), &light_buffers, &sprite_atlas, Orientation::UpRight, assets)?,
+        terrain_shape::TerrainShapeDrawSystem::new(factory, scene.rtv.clone(), scene.dsv.clone(), &light_buffers, &sprite_atlas, Orientation::Normal, assets)?,
+        terrain_shape::TerrainShapeDrawSystem::new(factory, scene.rtv.clone(), scene.dsv.clone(), &light_buffers, &sprite_atlas, Orientation::Up, assets)?,
       ],
+      text_system: hud::TextDrawSystem::new(factory, offscreen.rtv.clone(), offscreen.dsv.clone(), assets)?,
+      ui_system: hud::ui::UiDrawSystem::new(factory, offscreen.rtv.clone(), offscreen.dsv.clone(), assets, terrain, tile_sheet)?,
+      particle_system: ParticleDrawSystem::new(factory, scene.rtv.clone(), scene.dsv.clone(), assets)?,
+      debug_system: DebugDrawSystem::new(factory, offscreen.rtv.clone(), assets)?,
+      sprite_atlas: sprite_atlas.atlas,
+      sprite_texture: sprite_atlas.handle,
+      present_system: PresentDrawSystem::new(factory, offscreen.view.clone(), rtv.clone(), assets)?,
+      post_system,
+      post_frame: 0,
+      offscreen,
+      capture_frame: false,
+      light_buffers,
       encoder_queue,
       game_time: Instant::now(),
       frames: 0,
-      cool_down: 1.0,
-      run_cool_down: 1.0,
-      fire_cool_down: 1.0,
+    })
+  }
+
+  pub fn reload_shaders<F>(&mut self, factory: &mut F, assets: &AssetManager)
+    where F: gfx::Factory<D::Resources> {
+    let mut results = vec![
+      self.terrain_system.reload_shaders(factory, assets),
+      self.character_system.reload_shaders(factory, assets),
+      self.zombie_system.reload_shaders(factory, assets),
+      self.bullet_system.reload_shaders(factory, assets),
+      self.text_system.reload_shaders(factory, assets),
+      self.ui_system.reload_shaders(factory, assets),
+      self.particle_system.reload_shaders(factory, assets),
+      self.debug_system.reload_shaders(factory, assets),
+      self.post_system.reload_shaders(factory, assets),
+      self.present_system.reload_shaders(factory, assets),
+    ];
+    results.extend(self.terrain_object_system.iter_mut().map(|system| system.reload_shaders(factory, assets)));
+    results.extend(self.terrain_shape_system.iter_mut().map(|system| system.reload_shaders(factory, assets)));
+
+    let mut errors = results.into_iter().filter_map(Result::err).collect::<Vec<String>>();
+    errors.dedup();
+    if errors.is_empty() {
+      println!("Shaders reloaded from {}", assets.path(SHADER_DIR).display());
+    }
+    for e in errors {
+      println!("Shader reload error, keeping previous pipeline\n{}", e);
+    }
+  }
+
+  pub fn resize<F>(&mut self, factory: &mut F, rtv: &gfx::handle::RenderTargetView<D::Resources, ColorFormat>) -> GameResult<()>
+    where F: gfx::Factory<D::Resources> {
+    let (width, height, _, _) = rtv.get_dimensions();
+    self.offscreen = OffscreenTarget::new(factory, width, height)?;
+    self.present_system.resize(&self.offscreen.view, rtv);
+    self.post_system.resize(factory, &self.offscreen.rtv)?;
+    let (rtv, dsv) = (&self.post_system.scene.rtv, &self.post_system.scene.dsv);
+    self.terrain_system.resize(rtv, dsv);
+    self.character_system.resize(rtv, dsv);
+    self.zombie_system.resize(rtv, dsv);
+    self.bullet_system.resize(rtv, dsv);
+    for object_system in &mut self.terrain_object_system {
+      object_system.resize(rtv, dsv);
+    }
+    for shape_system in &mut self.terrain_shape_system {
+      shape_system.resize(rtv, dsv);
     }
+    self.particle_system.resize(rtv, dsv);
+    let (rtv, dsv) = (&self.offscreen.rtv, &self.offscreen.dsv);
+    self.text_system.resize(rtv, dsv);
+    self.ui_system.resize(rtv, dsv);
+    self.debug_system.resize(rtv);
+    Ok(())
   }
 
-  fn update_cooldowns(&mut self, delta: f64) {
-    if self.cool_down == 0.0 {
-      self.cool_down += 0.05;
+  pub fn request_capture(&mut self) {
+    self.capture_frame = true;
+  }
+
+  pub fn save_capture<F>(&mut self, factory: &mut F, path: &Path) -> GameResult<()>
+    where F: gfx::Factory<D::Resources> {
+    if !self.capture_frame {
+      return Err(GameError::Render("No frame captured".to_string()));
     }
-    if self.fire_cool_down == 0.0 {
-      self.fire_cool_down += 0.2;
+    self.capture_frame = false;
+    let image = self.offscreen.read_image(factory)?;
+    save_screenshot(&image, path)
+  }
+
+  fn reload_sprites<C>(&mut self, assets: &AssetManager, encoder: &mut gfx::Encoder<D::Resources, C>) -> GameResult<()>
+    where C: gfx::CommandBuffer<D::Resources> {
+    let (atlas, texels) = build_sprite_atlas(assets, self.sprite_atlas.size.y)?;
+    update_texture(encoder, &self.sprite_texture, &texels)
+      .map_err(|e| GameError::Asset("sprite atlas".to_string(), e))?;
+    self.sprite_atlas = atlas.with_size(self.sprite_atlas.size);
+
+    self.character_system.reload_data(&self.sprite_atlas, assets);
+    self.zombie_system.reload_data(&self.sprite_atlas, assets);
+    for object_system in &mut self.terrain_object_system {
+      object_system.reload_data(&self.sprite_atlas);
     }
-    if self.run_cool_down == 0.0 {
-      self.run_cool_down += 0.02;
+    for shape_system in &mut self.terrain_shape_system {
+      shape_system.reload_data(&self.sprite_atlas);
     }
-    self.cool_down = (self.cool_down - delta).max(0.0);
-    self.run_cool_down = (self.run_cool_down - delta).max(0.0);
-    self.fire_cool_down = (self.fire_cool_down - delta).max(0.0);
+    Ok(())
+  }
+
+  fn reload_terrain<C>(&mut self, assets: &AssetManager, encoder: &mut gfx::Encoder<D::Resources, C>) -> GameResult<()>
+    where C: gfx::CommandBuffer<D::Resources> {
+    let terrain = Terrain::new(assets)?;
+    let tile_sheet = assets.load_image(TERRAIN_TEXTURE_PATH)?;
+    self.terrain_system.reload(&terrain, &tile_sheet, encoder)
+      .and_then(|_| self.ui_system.reload_minimap(&terrain, &tile_sheet, encoder))
+      .map_err(|e| GameError::Asset(TERRAIN_TEXTURE_PATH.to_string(), e))
   }
 }
 
@@ -100,68 +212,68 @@ impl<'a, D> specs::prelude::System<'a> for DrawSystem<D>
   type SystemData = (WriteStorage<'a, terrain::TerrainDrawable>,
                      WriteStorage<'a, terrain_shape::terrain_shape_objects::TerrainShapeObjects>,
                      WriteStorage<'a, character::CharacterDrawable>,
-                     WriteStorage<'a, CharacterSprite>,
+                     ReadStorage<'a, Animator>,
                      WriteStorage<'a, hud::hud_objects::HudObjects>,
                      WriteStorage<'a, zombie::zombies::Zombies>,
                      WriteStorage<'a, bullet::bullets::Bullets>,
                      WriteStorage<'a, terrain_object::terrain_objects::TerrainObjects>,
-                     Read<'a, DeltaTime>);
+                     ReadStorage<'a, Particles>,
+                     ReadStorage<'a, Lights>,
+                     ReadStorage<'a, DebugOverlay>,
+                     Read<'a, Console>,
+                     Read<'a, SettingsMenu>,
+                     Read<'a, Settings>,
+                     Write<'a, AssetReload>,
+                     Write<'a, AnimationLibrary>,
+                     Read<'a, AssetManager>,
+                     Read<'a, Dimensions>,
+                     Read<'a, GameTime>);
 
-  fn run(&mut self, (mut terrain, mut terrain_shape, mut character, mut character_sprite, mut hud_objects, mut zombies, mut bullets, mut terrain_objects, dt): Self::SystemData) {
+  fn run(&mut self, (mut terrain, mut terrain_shape, mut character, animators, mut hud_objects, mut zombies, mut bullets, mut terrain_objects, particles, lights, debug_overlay, console, settings_menu, settings, mut asset_reload, mut animations, assets, dim, gt): Self::SystemData) {
     use specs::join::Join;
     let mut encoder = self.encoder_queue.receiver
       .recv()
       .expect("Encoder error");
 
-    self.update_cooldowns(dt.0);
+    if asset_reload.sprites {
+      if let Err(e) = self.reload_sprites(&assets, &mut encoder) {
+        println!("Sprite reload error {}", e);
+      }
+      match AnimationLibrary::load(&assets) {
+        Ok(library) => *animations = library,
+        Err(e) => println!("Animation reload error {}", e),
+      }
+      asset_reload.sprites = false;
+    }
+
+    if asset_reload.terrain {
+      if let Err(e) = self.reload_terrain(&assets, &mut encoder) {
+        println!("Terrain reload error {}", e);
+      }
+      asset_reload.terrain = false;
+    }
 
     let current_time = Instant::now();
     self.frames += 1;
 
-    let time_passed = current_time.duration_since(self.game_time).as_secs();
+    // Night grading follows game time so captured frames are reproducible
+    let time_passed = gt.0;
 
-    if cfg!(feature = "framerate") && time_passed >= 1 {
+    if cfg!(feature = "framerate") && current_time.duration_since(self.game_time).as_secs() >= 1 {
       println!("{:?} ms/frames", 1000.0 / f64::from(self.frames));
       self.frames = 0;
       self.game_time = Instant::now();
     }
 
-    encoder.clear(&self.render_target_view, [16.0 / 256.0, 16.0 / 256.0, 20.0 / 256.0, 1.0]);
-    encoder.clear_depth(&self.depth_stencil_view, 1.0);
+    self.post_frame = self.post_frame.wrapping_add(1);
+    self.post_system.clear(&mut encoder, [16.0 / 256.0, 16.0 / 256.0, 20.0 / 256.0, 1.0]);
+    encoder.clear_depth(&self.offscreen.dsv, 1.0);
 
-    for (t, t_shape, c, cs, hds, zs, bs, obj) in (&mut terrain, &mut terrain_shape, &mut character, &mut character_sprite, &mut hud_objects,
-                                         &mut zombies, &mut bullets, &mut terrain_objects).join() {
-      self.terrain_system.draw(t, time_passed,  &mut encoder);
-
-      for hud in &mut hds.objects {
-        self.text_system[0].draw(hud, &mut encoder);
-        self.text_system[1].draw(hud, &mut encoder);
-      }
-
-      if self.cool_down == 0.0 {
-        if c.stance == Stance::Walking {
-          cs.update_run();
-        }
-        for z in &mut zs.zombies {
-          match z.stance {
-            Stance::NormalDeath => z.update_death_idx(5),
-            Stance::CriticalDeath => z.update_death_idx(7),
-            Stance::Walking => z.update_alive_idx(7),
-            Stance::Still => z.update_alive_idx(3),
-            _ => ()
-          };
-        }
-      } else if self.fire_cool_down == 0.0 && c.stance == Stance::Firing {
-        cs.update_fire();
-      }
-
-      if self.run_cool_down == 0.0 {
-        for z in &mut zs.zombies {
-          if let Stance::Running = z.stance {
-            z.update_alive_idx(7)
-          }
-        }
-      }
+    for (t, t_shape, c, animator, hds, zs, bs, obj, ps, ls, dbg) in (&mut terrain, &mut terrain_shape, &mut character, &animators, &mut hud_objects,
+                                                      &mut zombies, &mut bullets, &mut terrain_objects, &particles, &lights, &debug_overlay).join() {
+      self.light_buffers.upload(ls, &mut encoder);
+      self.terrain_system.draw(t, &mut encoder);
+      let danger = danger_level(c);
 
       let mut drawables: Vec<Drawables> = vec![];
       drawables.append(&mut bs.bullets.iter().map(|b| Drawables::Bullet(b)).collect());
@@ -185,28 +297,57 @@ impl<'a, D> specs::prelude::System<'a> for DrawSystem<D>
 
       for ts in &t_shape.objects {
         match ts.get_shape() {
-          Orientation::Right => self.terrain_shape_system[0].draw(ts, time_passed, &mut encoder),
-          Orientation::DownRight => self.terrain_shape_system[1].draw(ts, time_passed, &mut encoder),
-          Orientation::Down => self.terrain_shape_system[2].draw(ts, time_passed, &mut encoder),
-          Orientation::DownLeft => self.terrain_shape_system[3].draw(ts, time_passed, &mut encoder),
-          Orientation::Left => self.terrain_shape_system[4].draw(ts, time_passed, &mut encoder),
-          Orientation::UpLeft => self.terrain_shape_system[5].draw(ts, time_passed, &mut encoder),
-          Orientation::UpRight => self.terrain_shape_system[6].draw(ts, time_passed, &mut encoder),
-          Orientation::Normal => self.terrain_shape_system[7].draw(ts, time_passed, &mut encoder),
-          Orientation::Up => self.terrain_shape_system[8].draw(ts, time_passed, &mut encoder),
+          Orientation::Right => self.terrain_shape_system[0].push(ts, TERRAIN_SHAPE_DEPTH),
+          Orientation::DownRight => self.terrain_shape_system[1].push(ts, TERRAIN_SHAPE_DEPTH),
+          Orientation::Down => self.terrain_shape_system[2].push(ts, TERRAIN_SHAPE_DEPTH),
+          Orientation::DownLeft => self.terrain_shape_system[3].push(ts, TERRAIN_SHAPE_DEPTH),
+          Orientation::Left => self.terrain_shape_system[4].push(ts, TERRAIN_SHAPE_DEPTH),
+          Orientation::UpLeft => self.terrain_shape_system[5].push(ts, TERRAIN_SHAPE_DEPTH),
+          Orientation::UpRight => self.terrain_shape_system[6].push(ts, TERRAIN_SHAPE_DEPTH),
+          Orientation::Normal => self.terrain_shape_system[7].push(ts, TERRAIN_SHAPE_DEPTH),
+          Orientation::Up => self.terrain_shape_system[8].push(ts, TERRAIN_SHAPE_DEPTH),
         }
       }
 
-      for e in &mut drawables {
+      for (rank, e) in drawables.iter_mut().enumerate() {
+        let depth = sprite_depth(rank);
         match *e {
-          Drawables::Bullet(ref e) => { self.bullet_system.draw(e, &mut encoder) }
-          Drawables::Zombie(ref mut e) => { self.zombie_system.draw(e, &mut encoder) }
-          Drawables::TerrainAmmo(ref mut e) => { self.terrain_object_system[0].draw(e, time_passed, &mut encoder) }
-          Drawables::TerrainHouse(ref mut e) => { self.terrain_object_system[1].draw(e, time_passed, &mut encoder) }
-          Drawables::TerrainTree(ref mut e) => { self.terrain_object_system[2].draw(e, time_passed, &mut encoder) }
-          Drawables::Character(ref mut e) => { self.character_system.draw(e, cs, &mut encoder) }
+          Drawables::Bullet(ref e) => { self.bullet_system.push(e, depth) }
+          Drawables::Zombie(ref mut e) => { self.zombie_system.push(e, depth) }
+          Drawables::TerrainAmmo(ref mut e) => { self.terrain_object_system[0].push(e, depth) }
+          Drawables::TerrainHouse(ref mut e) => { self.terrain_object_system[1].push(e, depth) }
+          Drawables::TerrainTree(ref mut e) => { self.terrain_object_system[2].push(e, depth) }
+          Drawables::Character(ref mut e) => { self.character_system.push(e, animator, depth) }
         }
       }
+
+      for shape_system in &mut self.terrain_shape_system {
+        shape_system.draw(&mut encoder);
+      }
+      for object_system in &mut self.terrain_object_system {
+        object_system.draw(&mut encoder);
+      }
+      self.zombie_system.draw(&mut encoder);
+      self.character_system.draw(&mut encoder);
+      self.bullet_system.draw(&mut encoder);
+
+      self.particle_system.draw(ps, &mut encoder);
+
+      let locals = post_locals(&settings, night_factor(time_passed), danger, self.post_frame);
+      self.post_system.draw(&locals, &mut encoder);
+
+      let layout = ScreenLayout::new(&dim);
+      self.ui_system.draw(hds.bars().chain(console.bars()).chain(settings_menu.bars()), &hds.icons, &hds.minimap, &layout, &mut encoder);
+      self.text_system.draw(hds.texts().chain(dbg.texts()).chain(console.texts()).chain(settings_menu.texts()), &layout, &mut encoder);
+      self.debug_system.draw(dbg, &mut encoder);
+    }
+
+    self.present_system.draw(&mut encoder);
+    if self.capture_frame {
+      if let Err(e) = self.offscreen.capture(&mut encoder) {
+        println!("{}", e);
+        self.capture_frame = false;
+      }
     }
 
     self.encoder_queue.sender.send(encoder).expect("Encoder queue update error");
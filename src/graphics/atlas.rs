@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+
+use cgmath::Point2;
+use gfx::{Factory, format::R8_G8_B8_A8, handle, handle::ShaderResourceView, Resources};
+use image::RgbaImage;
+
+use crate::assets::AssetManager;
+use crate::critter::CritterData;
+use crate::data;
+use crate::error::{GameError, GameResult};
+use crate::game::constants::{AMMO_IMAGE_PATH, ATLAS_MAX_HEIGHT, ATLAS_PADDING, ATLAS_WIDTH, HOUSE_IMAGE_PATH, SHAPE_IMAGE_PATH, SPRITE_SHEETS,
+                             TERRAIN_SHAPE_SPRITE, TREE_IMAGE_PATH};
+use crate::graphics::texture::create_updatable_texture;
+use crate::terrain_object::TerrainTexture;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasRegion {
+  pub x: u32,
+  pub y: u32,
+  pub w: u32,
+  pub h: u32,
+}
+
+#[derive(Clone)]
+pub struct TextureAtlas {
+  pub size: Point2<u32>,
+  regions: HashMap<String, AtlasRegion>,
+}
+
+impl TextureAtlas {
+  pub fn region(&self, name: &str) -> Option<&AtlasRegion> {
+    self.regions.get(name)
+  }
+
+  pub fn uv_rect(&self, name: &str) -> Option<[f32; 4]> {
+    let (width, height) = (self.size.x as f32, self.size.y as f32);
+    self.region(name).map(|r| [r.x as f32 / width, r.y as f32 / height, r.w as f32 / width, r.h as f32 / height])
+  }
+
+  pub fn sheet_uv_rect(&self, sheet: &str, frame: &str) -> Option<[f32; 4]> {
+    self.uv_rect(&sheet_frame_name(sheet, frame))
+  }
+
+  pub fn with_size(self, size: Point2<u32>) -> TextureAtlas {
+    TextureAtlas { size, ..self }
+  }
+}
+
+pub fn sheet_frame_name(sheet: &str, frame: &str) -> String {
+  format!("{}/{}", sheet, frame)
+}
+
+struct SkylineSegment {
+  x: u32,
+  y: u32,
+  w: u32,
+}
+
+fn skyline_fit(skyline: &[SkylineSegment], idx: usize, w: u32, width: u32) -> Option<u32> {
+  let x = skyline[idx].x;
+  if x + w > width {
+    return None;
+  }
+  let mut y = 0;
+  let mut remaining = w as i64;
+  for segment in &skyline[idx..] {
+    if remaining <= 0 {
+      break;
+    }
+    y = y.max(segment.y);
+    remaining -= i64::from(segment.w);
+  }
+  Some(y)
+}
+
+fn skyline_place(skyline: &mut Vec<SkylineSegment>, idx: usize, region: &AtlasRegion) {
+  let right = region.x + region.w;
+  skyline.insert(idx, SkylineSegment { x: region.x, y: region.y + region.h, w: region.w });
+  while idx + 1 < skyline.len() && skyline[idx + 1].x < right {
+    let next = &mut skyline[idx + 1];
+    let next_right = next.x + next.w;
+    if next_right <= right {
+      skyline.remove(idx + 1);
+    } else {
+      next.w = next_right - right;
+      next.x = right;
+    }
+  }
+  let mut i = 0;
+  while i + 1 < skyline.len() {
+    if skyline[i].y == skyline[i + 1].y {
+      skyline[i].w += skyline[i + 1].w;
+      skyline.remove(i + 1);
+    } else {
+      i += 1;
+    }
+  }
+}
+
+pub fn pack_regions(sizes: &[(String, Point2<u32>)], width: u32, max_height: u32) -> Result<TextureAtlas, String> {
+  let mut order = (0..sizes.len()).collect::<Vec<usize>>();
+  order.sort_by(|a, b| {
+    let (a, b) = (&sizes[*a], &sizes[*b]);
+    b.1.y.cmp(&a.1.y).then(b.1.x.cmp(&a.1.x)).then(a.0.cmp(&b.0))
+  });
+
+  let mut skyline = vec![SkylineSegment { x: 0, y: 0, w: width }];
+  let mut regions = HashMap::with_capacity(sizes.len());
+  let mut used_height = 0;
+  for idx in order {
+    let (name, size) = &sizes[idx];
+    let padded = Point2::new(size.x + ATLAS_PADDING, size.y + ATLAS_PADDING);
+    let best = (0..skyline.len())
+      .filter_map(|i| skyline_fit(&skyline, i, padded.x, width).map(|y| (i, y)))
+      .min_by_key(|(i, y)| (*y + padded.y, skyline[*i].x));
+    let (i, y) = best.ok_or_else(|| format!("Atlas sprite {} ({}x{}) wider than atlas", name, size.x, size.y))?;
+    let region = AtlasRegion { x: skyline[i].x, y, w: padded.x, h: padded.y };
+    skyline_place(&mut skyline, i, &region);
+    used_height = used_height.max(y + padded.y);
+    regions.insert(name.clone(), AtlasRegion { w: size.x, h: size.y, ..region });
+  }
+
+  let height = used_height.next_power_of_two();
+  if height > max_height {
+    return Err(format!("Atlas height {} exceeds maximum {}", height, max_height));
+  }
+  Ok(TextureAtlas {
+    size: Point2::new(width, height),
+    regions,
+  })
+}
+
+#[derive(Default)]
+pub struct AtlasBuilder {
+  sources: Vec<RgbaImage>,
+  entries: Vec<(String, usize, AtlasRegion)>,
+}
+
+impl AtlasBuilder {
+  pub fn add_image(&mut self, name: &str, image: RgbaImage) {
+    let region = AtlasRegion { x: 0, y: 0, w: image.width(), h: image.height() };
+    self.entries.push((name.to_string(), self.sources.len(), region));
+    self.sources.push(image);
+  }
+
+  pub fn add_sheet(&mut self, sheet: &str, image: RgbaImage, frames: &[CritterData]) -> GameResult<()> {
+    let (width, height) = image.dimensions();
+    let mut regions = Vec::with_capacity(frames.len());
+    for frame in frames {
+      let [x, y, w, h] = frame.data;
+      let region = AtlasRegion { x: x as u32, y: y as u32, w: w as u32, h: h as u32 };
+      if region.x + region.w > width || region.y + region.h > height {
+        return Err(GameError::Asset(sheet.to_string(),
+                                    format!("Frame {} {:?} outside of {}x{} sheet", frame.name, frame.data, width, height)));
+      }
+      regions.push((sheet_frame_name(sheet, &frame.name), self.sources.len(), region));
+    }
+    self.entries.extend(regions);
+    self.sources.push(image);
+    Ok(())
+  }
+
+  pub fn build(self, max_height: u32) -> Result<(TextureAtlas, RgbaImage), String> {
+    let sizes = self.entries.iter()
+      .map(|(name, _, source)| (name.clone(), Point2::new(source.w, source.h)))
+      .collect::<Vec<_>>();
+    let atlas = pack_regions(&sizes, ATLAS_WIDTH, max_height)?;
+
+    let mut texels = RgbaImage::new(atlas.size.x, atlas.size.y);
+    for (name, source_idx, source) in &self.entries {
+      let target = atlas.regions[name];
+      let image = &self.sources[*source_idx];
+      for y in 0..source.h {
+        for x in 0..source.w {
+          texels.put_pixel(target.x + x, target.y + y, *image.get_pixel(source.x + x, source.y + y));
+        }
+      }
+    }
+    Ok((atlas, texels))
+  }
+}
+
+pub struct SpriteAtlas<R> where R: Resources {
+  pub atlas: TextureAtlas,
+  pub texture: ShaderResourceView<R, [f32; 4]>,
+  pub handle: handle::Texture<R, R8_G8_B8_A8>,
+}
+
+fn add_sheet_from_file(builder: &mut AtlasBuilder, assets: &AssetManager, sheet: &str, json_path: &str) -> GameResult<()> {
+  let sprite_sheet = data::load_sprite_sheet(assets, json_path)?;
+  let image = assets.load_image(&data::sheet_image_path(json_path, &sprite_sheet))?;
+  builder.add_sheet(sheet, image, &sprite_sheet.frames)
+}
+
+pub fn build_sprite_atlas(assets: &AssetManager, max_height: u32) -> GameResult<(TextureAtlas, RgbaImage)> {
+  let mut builder = AtlasBuilder::default();
+  for (sheet, json_path) in SPRITE_SHEETS.iter() {
+    add_sheet_from_file(&mut builder, assets, sheet, json_path)?;
+  }
+  let images = [
+    (TerrainTexture::Ammo.sprite_name(), AMMO_IMAGE_PATH),
+    (TerrainTexture::House.sprite_name(), HOUSE_IMAGE_PATH),
+    (TerrainTexture::Tree.sprite_name(), TREE_IMAGE_PATH),
+    (TERRAIN_SHAPE_SPRITE, SHAPE_IMAGE_PATH),
+  ];
+  for (name, path) in images.iter() {
+    builder.add_image(name, assets.load_image(path)?);
+  }
+
+  builder.build(max_height)
+    .map_err(|e| GameError::Asset("sprite atlas".to_string(), e))
+}
+
+pub fn load_sprite_atlas<R, F>(factory: &mut F, assets: &AssetManager) -> GameResult<SpriteAtlas<R>> where R: Resources, F: Factory<R> {
+  let (atlas, texels) = build_sprite_atlas(assets, ATLAS_MAX_HEIGHT)?;
+  let (handle, texture) = create_updatable_texture(factory, &texels);
+  Ok(SpriteAtlas {
+    atlas,
+    texture,
+    handle,
+  })
+}
@@ -1,7 +1,8 @@
 use crossbeam_channel as channel;
-use specs::prelude::WriteStorage;
+use specs::prelude::{Read, WriteStorage};
 
-use crate::game::constants::VIEW_DISTANCE;
+use crate::game::constants::{VIEW_DISTANCE, ZOOM_MAX, ZOOM_MIN, ZOOM_WHEEL_STEP};
+use crate::settings::Settings;
 use crate::shaders::Position;
 
 #[derive(Clone)]
@@ -33,6 +34,7 @@ pub enum CameraControl {
   ZoomOut,
   ZoomIn,
   ZoomStop,
+  ZoomBy(f32),
   Left,
   Right,
   Up,
@@ -57,22 +59,26 @@ impl CameraControlSystem {
 }
 
 impl<'a> specs::prelude::System<'a> for CameraControlSystem {
-  type SystemData = WriteStorage<'a, CameraInputState>;
-  fn run(&mut self, mut map_input: Self::SystemData) {
+  type SystemData = (WriteStorage<'a, CameraInputState>,
+                     Read<'a, Settings>);
+  fn run(&mut self, (mut map_input, settings): Self::SystemData) {
     use specs::join::Join;
 
+    let mut wheel_zoom = 0.0;
     while let Ok(control) = self.queue.try_recv() {
       match control {
         CameraControl::ZoomIn => self.zoom_level = Some(2.0),
         CameraControl::ZoomOut => self.zoom_level = Some(-2.0),
         CameraControl::ZoomStop => self.zoom_level = None,
+        CameraControl::ZoomBy(amount) => wheel_zoom -= amount * ZOOM_WHEEL_STEP * settings.mouse_sensitivity,
         _ => (),
       }
     }
-    if let Some(zoom) = self.zoom_level {
+    let zoom = self.zoom_level.unwrap_or(0.0) + wheel_zoom;
+    if zoom != 0.0 {
       for m in (&mut map_input).join() {
-        if m.distance > 200.0 && zoom < 0.0 || m.distance < 600.0 && zoom > 0.0 {
-          m.distance += zoom;
+        if m.distance > ZOOM_MIN && zoom < 0.0 || m.distance < ZOOM_MAX && zoom > 0.0 {
+          m.distance = (m.distance + zoom).max(ZOOM_MIN).min(ZOOM_MAX);
         }
       }
     }
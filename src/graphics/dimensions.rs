@@ -1,7 +1,7 @@
-use cgmath::{Matrix4, Point3, Vector3};
+use cgmath::{Matrix4, Point2, Point3, Vector3, Vector4};
 
 use crate::graphics::camera::CameraInputState;
-use crate::shaders::Projection;
+use crate::shaders::{Position, Projection};
 
 #[derive(Clone, Default)]
 pub struct Dimensions {
@@ -11,8 +11,7 @@ pub struct Dimensions {
 }
 
 impl Dimensions {
-  pub fn new(window_width: f32, window_height: f32, hidpi_val: f32, is_windowed: bool) -> Dimensions {
-    let hidpi_factor = if is_windowed { 1.0 } else { hidpi_val };
+  pub fn new(window_width: f32, window_height: f32, hidpi_factor: f32) -> Dimensions {
     Dimensions {
       window_width,
       window_height,
@@ -42,3 +41,9 @@ pub fn get_view_matrix(view: f32) -> Matrix4<f32> {
     Vector3::unit_y(),
   )
 }
+
+pub fn world_to_clip_space(projection: &Projection, position: Position) -> Point2<f32> {
+  let transform = Matrix4::from(projection.proj) * Matrix4::from(projection.view) * Matrix4::from(projection.model);
+  let origin = transform * Vector4::new(0.0, 0.0, 0.0, 1.0);
+  Point2::new((position.x() + origin.x) / origin.w, (position.y() + origin.y) / origin.w)
+}
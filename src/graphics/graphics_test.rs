@@ -122,3 +122,239 @@ fn tile_to_coords_test() {
 
   assert_eq!(coords_to_tile(left), Point2::new(1, 126), "Left corner");
 }
+
+#[test]
+fn tile_center_round_trip_test() {
+  use cgmath::Point2;
+  use crate::graphics::{coords_to_tile, tile_to_coords};
+
+  for tile in &[Point2::new(1, 1), Point2::new(64, 64), Point2::new(10, 100), Point2::new(126, 3)] {
+    let center = tile_to_coords(Point2::new(tile.x as f32 + 0.5, tile.y as f32 + 0.5));
+    assert_eq!(*tile, coords_to_tile(center), "Tile center should map back to the tile");
+  }
+}
+
+#[test]
+fn velocity_to_orientation_test() {
+  use cgmath::Point2;
+  use crate::graphics::{orientation::Orientation, velocity_to_orientation};
+
+  assert!(Orientation::Normal == velocity_to_orientation(Point2::new(0.0, 0.0)), "Zero velocity should be Normal");
+  assert!(Orientation::Up == velocity_to_orientation(Point2::new(0.0, -360.0)), "Negative y should be Up");
+  assert!(Orientation::Left == velocity_to_orientation(Point2::new(360.0, 0.0)), "Positive x should be Left");
+  assert!(Orientation::DownLeft == velocity_to_orientation(Point2::new(254.0, 254.0)), "Positive x and y should be DownLeft");
+  assert!(Orientation::UpRight == velocity_to_orientation(Point2::new(-254.0, -254.0)), "Negative x and y should be UpRight");
+}
+
+#[test]
+fn orientation_from_center_test() {
+  use cgmath::Point2;
+  use crate::gfx_app::mouse_controls::MouseInputState;
+  use crate::graphics::{dimensions::Dimensions, get_orientation_from_center, orientation::Orientation};
+
+  let dim = Dimensions::new(800.0, 600.0, 2.0);
+  let mut mouse_input = MouseInputState::new();
+  assert!(Orientation::Right == get_orientation_from_center(&mouse_input, &dim), "No click should face Right");
+
+  mouse_input.left_click_point = Some(Point2::new(1000.0, 300.0));
+  assert!(Orientation::UpRight == get_orientation_from_center(&mouse_input, &dim), "Click above right of the center should be UpRight");
+  mouse_input.left_click_point = Some(Point2::new(1400.0, 550.0));
+  assert!(Orientation::Right == get_orientation_from_center(&mouse_input, &dim), "Click right of the center should be Right");
+  mouse_input.left_click_point = Some(Point2::new(800.0, 1100.0));
+  assert!(Orientation::Down == get_orientation_from_center(&mouse_input, &dim), "Click below the center should be Down");
+}
+
+#[test]
+fn diagonal_velocity_is_normalized_test() {
+  use cgmath::{InnerSpace, Point2, Vector2};
+  use crate::graphics::input_to_velocity;
+
+  let diagonal = input_to_velocity(Some(1.0), Some(-1.0), 360.0);
+  assert!((Vector2::new(diagonal.x, diagonal.y).magnitude() - 360.0).abs() < 0.001, "Diagonal speed should equal max speed");
+  assert_eq!(Point2::new(0.0, 0.0), input_to_velocity(None, None, 360.0), "No input should be zero velocity");
+}
+
+#[test]
+fn accelerate_towards_test() {
+  use cgmath::{MetricSpace, Point2};
+  use crate::graphics::accelerate_towards;
+
+  let velocity = accelerate_towards(Point2::new(0.0, 0.0), Point2::new(360.0, 0.0), 1000.0, 0.1);
+  assert!(velocity.distance(Point2::new(100.0, 0.0)) < 0.001, "Velocity should grow by rate * delta");
+  assert_eq!(Point2::new(360.0, 0.0),
+             accelerate_towards(Point2::new(300.0, 0.0), Point2::new(360.0, 0.0), 1000.0, 0.1),
+             "Velocity should not overshoot target");
+}
+
+#[test]
+fn sprite_depth_test() {
+  use crate::game::constants::{MAX_SPRITE_INSTANCES, SPRITE_DEPTH_FAR, SPRITE_DEPTH_NEAR};
+  use crate::graphics::sprite_batch::sprite_depth;
+
+  assert_eq!(SPRITE_DEPTH_FAR, sprite_depth(0), "First sprite should be furthest away");
+  assert!(sprite_depth(10) < sprite_depth(9), "Later sprites should be drawn in front");
+  assert!((SPRITE_DEPTH_NEAR - sprite_depth(MAX_SPRITE_INSTANCES * 2)).abs() < 0.0001, "Depth should be clamped to near plane");
+}
+
+#[test]
+fn atlas_packing_test() {
+  use cgmath::Point2;
+  use crate::graphics::atlas::pack_regions;
+
+  let sizes = (0..40)
+    .map(|i| (format!("sprite_{}", i), Point2::new(10 + i % 7 * 13, 8 + i % 5 * 11)))
+    .collect::<Vec<_>>();
+  let atlas = pack_regions(&sizes, 256, 1024).expect("Atlas packing failed");
+  let regions = sizes.iter()
+    .map(|(name, _)| *atlas.region(name).expect("Packed sprite missing"))
+    .collect::<Vec<_>>();
+
+  for (idx, a) in regions.iter().enumerate() {
+    assert!(a.x + a.w <= atlas.size.x && a.y + a.h <= atlas.size.y, "Sprite should fit inside the atlas");
+    for b in &regions[idx + 1..] {
+      let overlaps = a.x < b.x + b.w && b.x < a.x + a.w && a.y < b.y + b.h && b.y < a.y + a.h;
+      assert!(!overlaps, "Packed sprites should not overlap");
+    }
+  }
+  assert!(atlas.size.y.is_power_of_two(), "Atlas height should be a power of two");
+}
+
+#[test]
+fn atlas_uv_rect_test() {
+  use cgmath::Point2;
+  use crate::graphics::atlas::pack_regions;
+
+  let sizes = vec![("house".to_string(), Point2::new(64, 32)), ("ammo".to_string(), Point2::new(300, 8))];
+  assert!(pack_regions(&sizes, 256, 1024).is_err(), "Sprite wider than the atlas should fail");
+
+  let atlas = pack_regions(&sizes[..1], 128, 1024).expect("Atlas packing failed");
+  assert_eq!(Some([0.0, 0.0, 0.5, 0.5]), atlas.uv_rect("house"), "UV rect should be normalized to atlas size");
+  assert_eq!(None, atlas.uv_rect("tree"), "Unknown sprite should have no UV rect");
+}
+
+#[test]
+fn atlas_sheet_frame_test() {
+  use image::RgbaImage;
+  use crate::critter::CritterData;
+  use crate::graphics::atlas::AtlasBuilder;
+
+  let mut builder = AtlasBuilder::default();
+  let frames = vec![CritterData::new("run_0", [0.0, 0.0, 16.0, 16.0]), CritterData::new("run_1", [16.0, 0.0, 16.0, 16.0])];
+  assert!(builder.add_sheet("zombie", RgbaImage::new(32, 16), &frames).is_ok());
+
+  let frames = vec![CritterData::new("run_0", [0.0, 0.0, 16.0, 16.0]), CritterData::new("run_1", [24.0, 0.0, 16.0, 16.0])];
+  let error = builder.add_sheet("character", RgbaImage::new(32, 16), &frames).err().expect("Frame outside the sheet should fail");
+  let message = error.to_string();
+  assert!(message.contains("character") && message.contains("run_1"), "Error should name the sheet and frame: {}", message);
+}
+
+#[test]
+fn shader_log_test() {
+  use crate::graphics::shader::format_shader_log;
+
+  let mesa = format_shader_log("src/shaders/ui.f.glsl", "0:12(5): error: `colour' undeclared\n");
+  assert_eq!("src/shaders/ui.f.glsl:12: 0:12(5): error: `colour' undeclared", mesa);
+
+  let nvidia = format_shader_log("src/shaders/ui.f.glsl", "0(7) : error C0000: syntax error");
+  assert!(nvidia.starts_with("src/shaders/ui.f.glsl:7: "), "NVIDIA style line number should be read");
+
+  let link = format_shader_log("src/shaders/ui.v.glsl", "ERROR: 0:3: 'main' : function already has a body\nlinking failed");
+  assert_eq!(vec!["src/shaders/ui.v.glsl:3: ERROR: 0:3: 'main' : function already has a body",
+                  "src/shaders/ui.v.glsl: linking failed"],
+             link.lines().collect::<Vec<_>>());
+}
+
+#[test]
+fn shader_snippet_test() {
+  use crate::graphics::shader::insert_after_version;
+
+  let source = insert_after_version("#version 150 core\n\nvoid main() {}\n", "vec3 lighting(vec2 pos);");
+  assert_eq!("#version 150 core\nvec3 lighting(vec2 pos);\n#line 1\n\nvoid main() {}\n", source);
+  assert!(insert_after_version("void main() {}", "float x;").starts_with("float x;\n#line 0\n"), "Snippet should go first without #version");
+}
+
+#[test]
+fn aspect_ratio_test() {
+  use crate::game::constants::ASPECT_RATIO;
+  use crate::graphics::camera::CameraInputState;
+  use crate::graphics::dimensions::Dimensions;
+
+  assert!((ASPECT_RATIO - 16.0 / 9.0).abs() < 1e-6, "Default aspect ratio should be 16:9");
+
+  let camera = CameraInputState::new();
+  let wide = Dimensions::new(1600.0, 900.0, 1.0).world_to_projection(&camera);
+  let square = Dimensions::new(900.0, 900.0, 1.0).world_to_projection(&camera);
+  assert_eq!(square.proj[1][1], wide.proj[1][1], "Vertical scale should not change with window width");
+  assert!(wide.proj[0][0] < square.proj[0][0], "Wider window should show more of the map horizontally");
+}
+
+#[test]
+fn frame_image_test() {
+  use crate::graphics::render_target::frame_image;
+
+  let pixels = [[1, 0, 0, 0], [2, 0, 0, 0], [3, 0, 0, 128], [4, 0, 0, 255]];
+  let image = frame_image(&pixels, 2, 2);
+  assert_eq!([3, 0, 0, 255], image.get_pixel(0, 0).0, "GL rows start from the bottom of the frame");
+  assert_eq!([4, 0, 0, 255], image.get_pixel(1, 0).0);
+  assert_eq!([1, 0, 0, 255], image.get_pixel(0, 1).0);
+}
+
+#[test]
+fn night_factor_test() {
+  use crate::graphics::post_process::night_factor;
+
+  assert_eq!(0.0, night_factor(12), "Sun is highest around 52deg");
+  assert_eq!(1.0, night_factor(57), "Sun is lowest around 232deg");
+  assert_eq!(night_factor(30), night_factor(30 + 89), "Day cycle repeats every 89 seconds");
+}
+
+#[test]
+fn night_lut_test() {
+  use crate::game::constants::NIGHT_LUT_SIZE;
+  use crate::graphics::post_process::{night_grade, night_lut};
+
+  let lut = night_lut();
+  assert_eq!(NIGHT_LUT_SIZE * NIGHT_LUT_SIZE * NIGHT_LUT_SIZE * 4, lut.len());
+
+  let [r, g, b] = night_grade([1.0, 1.0, 1.0]);
+  assert!(b > g && g > r, "Night grading should shift colors towards blue");
+  let white = (NIGHT_LUT_SIZE * NIGHT_LUT_SIZE * NIGHT_LUT_SIZE - 1) * 4;
+  assert_eq!([(r * 255.0).round() as u8, (g * 255.0).round() as u8, (b * 255.0).round() as u8, 255], lut[white..white + 4]);
+}
+
+#[test]
+fn post_locals_test() {
+  use crate::game::constants::{BLOOM_STRENGTH, VIGNETTE_STRENGTH};
+  use crate::graphics::post_process::post_locals;
+  use crate::settings::Settings;
+
+  let mut settings = Settings::default();
+  let locals = post_locals(&settings, 0.5, 0.8, 1);
+  assert_eq!(BLOOM_STRENGTH, locals.bloom);
+  assert_eq!(VIGNETTE_STRENGTH, locals.vignette);
+  assert_eq!(0.8, locals.danger);
+  assert_eq!(0.5, locals.night);
+  assert_eq!(0.0, locals.grain, "Film grain is off by default");
+
+  settings.bloom = false;
+  settings.vignette = false;
+  settings.color_grading = false;
+  let locals = post_locals(&settings, 0.5, 0.8, 1);
+  assert_eq!([0.0; 4], [locals.bloom, locals.vignette, locals.danger, locals.night], "Disabled passes should have no effect");
+}
+
+#[test]
+fn danger_level_test() {
+  use crate::character::CharacterDrawable;
+  use crate::game::constants::CHARACTER_MAX_HEALTH;
+  use crate::graphics::post_process::danger_level;
+
+  let mut character = CharacterDrawable::new();
+  assert_eq!(0.0, danger_level(&character), "Full health should get no red vignette");
+
+  character.stats.health = CHARACTER_MAX_HEALTH / 4.0;
+  assert_eq!(0.75, danger_level(&character));
+
+  character.stats.take_damage(CHARACTER_MAX_HEALTH);
+  assert_eq!(1.0, danger_level(&character), "Dead character should get the full vignette");
+}
@@ -1,11 +1,12 @@
 use std::f32;
 
-use cgmath::{Angle, Deg, Point2};
+use cgmath::{Angle, Deg, InnerSpace, Point2, Vector2};
 use num::{Num, NumCast};
 
+use crate::assets::AssetKind;
 use crate::bullet::BulletDrawable;
 use crate::character::CharacterDrawable;
-use crate::game::{constants::{RESOLUTION_Y, TERRAIN_OBJECTS, TILE_SIZE, TILES_PCS_H, TILES_PCS_W, Y_OFFSET}, get_rand_from_range};
+use crate::game::{constants::{TERRAIN_OBJECTS, TILE_SIZE, TILES_PCS_H, TILES_PCS_W, Y_OFFSET}, GameRng, get_rand_from_range};
 use crate::game::constants::TILE_WIDTH;
 use crate::gfx_app::{mouse_controls::MouseInputState};
 use crate::graphics::{dimensions::Dimensions, orientation::Orientation};
@@ -13,11 +14,16 @@ use crate::shaders::Position;
 use crate::terrain_object::TerrainObjectDrawable;
 use crate::zombie::ZombieDrawable;
 
+pub mod atlas;
 pub mod camera;
 pub mod dimensions;
 mod graphics_test;
 pub mod mesh;
 pub mod orientation;
+pub mod post_process;
+pub mod render_target;
+pub mod shader;
+pub mod sprite_batch;
 pub mod texture;
 
 const Y_MODIFIER: f32 = 0.9;
@@ -28,8 +34,38 @@ pub struct DeltaTime(pub f64);
 #[derive(Default)]
 pub struct GameTime(pub u64);
 
-pub fn flip_y_axel(point: Point2<f32>) -> Point2<f32> {
-  Point2::new(point.x, RESOLUTION_Y as f32 - point.y)
+pub fn sun_height(game_time: u64) -> f32 {
+  let angle = ((game_time % 89) as f32 + 1.0) * 4.0;
+  let (sin, cos) = angle.to_radians().sin_cos();
+  (200.0 * sin + 150.0 * cos) / 250.0
+}
+
+pub struct TimeScale(pub f64);
+
+impl Default for TimeScale {
+  fn default() -> TimeScale {
+    TimeScale(1.0)
+  }
+}
+
+#[derive(Default)]
+pub struct AssetReload {
+  pub sprites: bool,
+  pub terrain: bool,
+}
+
+impl AssetReload {
+  pub fn request(&mut self, kind: AssetKind) {
+    match kind {
+      AssetKind::Sprites => self.sprites = true,
+      AssetKind::Terrain => self.terrain = true,
+    }
+  }
+
+  pub fn request_all(&mut self) {
+    self.sprites = true;
+    self.terrain = true;
+  }
 }
 
 pub fn direction(start_point: Point2<f32>, end_point: Point2<f32>) -> f32 {
@@ -62,13 +98,62 @@ pub fn orientation_to_direction(angle_in_degrees: f32) -> Orientation {
   }
 }
 
+pub fn orientation_to_angle(orientation: Orientation) -> Option<f32> {
+  match orientation {
+    Orientation::Right => Some(0.0),
+    Orientation::UpRight => Some(45.0),
+    Orientation::Up => Some(90.0),
+    Orientation::UpLeft => Some(135.0),
+    Orientation::Left => Some(180.0),
+    Orientation::DownLeft => Some(225.0),
+    Orientation::Down => Some(270.0),
+    Orientation::DownRight => Some(315.0),
+    Orientation::Normal => None,
+  }
+}
+
+pub fn velocity_to_orientation(velocity: Point2<f32>) -> Orientation {
+  if velocity.x.abs() < f32::EPSILON && velocity.y.abs() < f32::EPSILON {
+    Orientation::Normal
+  } else {
+    // Character movement axes point to screen left and down, flip them to screen space
+    orientation_to_direction(direction(Point2::new(0.0, 0.0), Point2::new(-velocity.x, -velocity.y)))
+  }
+}
+
+pub fn input_to_velocity(x_input: Option<f32>, y_input: Option<f32>, max_speed: f32) -> Point2<f32> {
+  let input = Vector2::new(x_input.unwrap_or(0.0), y_input.unwrap_or(0.0));
+  if input.magnitude2() > 0.0 {
+    Point2::new(0.0, 0.0) + input.normalize() * max_speed
+  } else {
+    Point2::new(0.0, 0.0)
+  }
+}
+
+pub fn accelerate_towards(velocity: Point2<f32>, target: Point2<f32>, rate: f32, delta: f32) -> Point2<f32> {
+  let diff = target - velocity;
+  let distance = diff.magnitude();
+  let max_step = rate * delta;
+  if distance <= max_step {
+    target
+  } else {
+    velocity + diff * (max_step / distance)
+  }
+}
+
 pub fn get_orientation_from_center(mouse_input: &MouseInputState, dim: &Dimensions) -> Orientation {
-  if let Some(end_point_gl) = mouse_input.left_click_point {
-    let start_point = Point2::new(dim.window_width / 2.0 * dim.hidpi_factor, dim.window_height / 2.0 * dim.hidpi_factor);
-    let dir = direction(start_point, flip_y_axel(end_point_gl));
-    orientation_to_direction(dir)
+  aim_from_center(mouse_input, dim)
+    .map_or(Orientation::Right, |aim| orientation_to_direction(direction(Point2::new(0.0, 0.0), aim)))
+}
+
+pub fn aim_from_center(mouse_input: &MouseInputState, dim: &Dimensions) -> Option<Point2<f32>> {
+  let end_point = mouse_input.left_click_point?;
+  let aim = Vector2::new(end_point.x - dim.window_width / 2.0 * dim.hidpi_factor, dim.window_height / 2.0 * dim.hidpi_factor - end_point.y);
+  if aim.magnitude2() > 0.0 {
+    let aim = aim.normalize();
+    Some(Point2::new(aim.x, aim.y))
   } else {
-    Orientation::Right
+    None
   }
 }
 
@@ -139,15 +224,21 @@ pub fn coords_to_tile(position: Position) -> Point2<i32> {
   Point2::new(((pos.x + pos.y) / TILE_WIDTH) as i32, ((pos.y - pos.x) / TILE_WIDTH) as i32)
 }
 
+pub fn tile_to_coords(tile: Point2<f32>) -> Position {
+  let x = (tile.x - tile.y) * TILE_WIDTH / 2.0;
+  let y = (tile.x + tile.y) * TILE_WIDTH / 2.0;
+  Position::new(-x, (y - Y_OFFSET) * Y_MODIFIER)
+}
+
 fn round(number: f32, precision: usize) -> f32 {
   let ten: f32 = 10.0;
   let divider = ten.powf(precision as f32);
   (number * divider).round() / divider
 }
 
-pub fn get_nearest_random_tile_position(pos: Position) -> Position {
-  fn iter(pos: Position) -> Position {
-    let offset = Position::new(get_rand_from_range(-2, 2) as f32, get_rand_from_range(-2, 2) as f32);
+pub fn get_nearest_random_tile_position(pos: Position, rng: &mut GameRng) -> Position {
+  fn iter(pos: Position, rng: &mut GameRng) -> Position {
+    let offset = Position::new(get_rand_from_range(rng, -2, 2) as f32, get_rand_from_range(rng, -2, 2) as f32);
     let offset_point = Position::new(
       round(offset.x() * TILE_WIDTH - offset.y() / TILE_WIDTH * Y_MODIFIER, 3),
       round(offset.y() * TILE_WIDTH - offset.y() / TILE_WIDTH * Y_MODIFIER, 3),
@@ -155,7 +246,7 @@ pub fn get_nearest_random_tile_position(pos: Position) -> Position {
     pos + offset_point
   }
   loop {
-    let res = iter(pos);
+    let res = iter(pos, rng);
     if can_move_to_tile(res) {
       return res;
     }
@@ -1,4 +1,5 @@
 use std::fmt::{Display, Formatter, Result};
+use std::str::FromStr;
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum Orientation {
@@ -52,3 +53,19 @@ impl Display for Stance {
   }
 }
 
+
+impl FromStr for Stance {
+  type Err = String;
+
+  fn from_str(s: &str) -> std::result::Result<Stance, String> {
+    match s {
+      "Walking" => Ok(Stance::Walking),
+      "Running" => Ok(Stance::Running),
+      "Firing" => Ok(Stance::Firing),
+      "Still" => Ok(Stance::Still),
+      "NormalDeath" => Ok(Stance::NormalDeath),
+      "CriticalDeath" => Ok(Stance::CriticalDeath),
+      _ => Err(format!("Unknown stance {}", s)),
+    }
+  }
+}
@@ -0,0 +1,239 @@
+use cgmath::Point2;
+use gfx::{Factory, handle::{RenderTargetView, ShaderResourceView}, Resources, texture::Size};
+
+use crate::assets::AssetManager;
+use crate::character::CharacterDrawable;
+use crate::error::{GameError, GameResult};
+use crate::game::constants::{BLOOM_STRENGTH, BLOOM_THRESHOLD, CHARACTER_MAX_HEALTH, FILM_GRAIN_STRENGTH, NIGHT_LUT_SIZE, VIGNETTE_STRENGTH};
+use crate::gfx_app::{ColorFormat, HdrColorFormat};
+use crate::graphics::sun_height;
+use crate::graphics::render_target::{fullscreen_quad, HdrTarget};
+use crate::graphics::shader::{create_pipeline_simple, reload_pipeline, ShaderProgram};
+use crate::graphics::texture::load_raw_texture;
+use crate::settings::Settings;
+use crate::shaders::{BlurLocals, blur_pipeline, post_pipeline, PostLocals, resolve_pipeline, VertexData};
+
+const BLOOM_SHADER: ShaderProgram = ShaderProgram::new("bloom");
+
+const POST_SHADER: ShaderProgram = ShaderProgram::new("post");
+
+const RESOLVE_SHADER: ShaderProgram = ShaderProgram::new("resolve");
+
+struct BloomTarget<R: Resources> {
+  view: ShaderResourceView<R, [f32; 4]>,
+  rtv: RenderTargetView<R, HdrColorFormat>,
+}
+
+impl<R: Resources> BloomTarget<R> {
+  fn new<F>(factory: &mut F, width: Size, height: Size) -> GameResult<BloomTarget<R>>
+    where F: Factory<R> {
+    let (_, view, rtv) = factory
+      .create_render_target::<HdrColorFormat>(width, height)
+      .map_err(|e| GameError::Render(format!("Bloom target {}x{} creation error {:?}", width, height, e)))?;
+    Ok(BloomTarget {
+      view,
+      rtv,
+    })
+  }
+}
+
+fn bloom_size(width: Size, height: Size) -> (Size, Size) {
+  ((width / 2).max(1), (height / 2).max(1))
+}
+
+fn blur_bundle<R, F>(factory: &mut F,
+                     vertex_buffer: &gfx::handle::Buffer<R, VertexData>,
+                     slice: &gfx::Slice<R>,
+                     sampler: &gfx::handle::Sampler<R>,
+                     source: &ShaderResourceView<R, [f32; 4]>,
+                     target: &BloomTarget<R>,
+                     assets: &AssetManager) -> GameResult<gfx::pso::bundle::Bundle<R, blur_pipeline::Data<R>>>
+  where R: Resources, F: Factory<R> {
+  use gfx::traits::FactoryExt;
+
+  let pso = create_pipeline_simple(factory, &BLOOM_SHADER.load(assets)?, blur_pipeline::new()).map_err(GameError::Shader)?;
+  let pipeline_data = blur_pipeline::Data {
+    vbuf: vertex_buffer.clone(),
+    source: (source.clone(), sampler.clone()),
+    blur_cb: factory.create_constant_buffer(1),
+    out_color: target.rtv.clone(),
+  };
+  Ok(gfx::Bundle::new(slice.clone(), pso, pipeline_data))
+}
+
+fn resolve_bundle<R, F>(factory: &mut F,
+                        vertex_buffer: &gfx::handle::Buffer<R, VertexData>,
+                        slice: &gfx::Slice<R>,
+                        scene: &HdrTarget<R>,
+                        samples: u16,
+                        assets: &AssetManager) -> GameResult<Option<gfx::pso::bundle::Bundle<R, resolve_pipeline::Data<R>>>>
+  where R: Resources, F: Factory<R> {
+  let multisampled = match scene.multisampled {
+    Some(ref view) => view.clone(),
+    None => return Ok(None),
+  };
+  let pso = create_pipeline_simple(factory, &RESOLVE_SHADER.load(assets)?, resolve_pipeline::new()).map_err(GameError::Shader)?;
+  let pipeline_data = resolve_pipeline::Data {
+    vbuf: vertex_buffer.clone(),
+    scene: multisampled,
+    samples: i32::from(samples),
+    out_color: scene.resolve_rtv.clone(),
+  };
+  Ok(Some(gfx::Bundle::new(slice.clone(), pso, pipeline_data)))
+}
+
+pub fn night_factor(game_time: u64) -> f32 {
+  (0.5 - sun_height(game_time)).max(0.0).min(1.0)
+}
+
+pub fn night_grade(color: [f32; 3]) -> [f32; 3] {
+  let [r, g, b] = color;
+  let luminance = 0.299 * r + 0.587 * g + 0.114 * b;
+  let desaturate = |c: f32| c + (luminance - c) * 0.6;
+  [desaturate(r) * 0.55, desaturate(g) * 0.7, (desaturate(b) + 0.03).min(1.0)]
+}
+
+pub fn night_lut() -> Vec<u8> {
+  let max = (NIGHT_LUT_SIZE - 1) as f32;
+  let mut texels = Vec::with_capacity(NIGHT_LUT_SIZE * NIGHT_LUT_SIZE * NIGHT_LUT_SIZE * 4);
+  for g in 0..NIGHT_LUT_SIZE {
+    for b in 0..NIGHT_LUT_SIZE {
+      for r in 0..NIGHT_LUT_SIZE {
+        let graded = night_grade([r as f32 / max, g as f32 / max, b as f32 / max]);
+        texels.extend(graded.iter().map(|c| (c * 255.0).round() as u8));
+        texels.push(255);
+      }
+    }
+  }
+  texels
+}
+
+pub fn danger_level(character: &CharacterDrawable) -> f32 {
+  (1.0 - character.stats.health / CHARACTER_MAX_HEALTH).max(0.0).min(1.0)
+}
+
+pub fn post_locals(settings: &Settings, night: f32, danger: f32, frame: u32) -> PostLocals {
+  let enabled = |on: bool, value: f32| if on { value } else { 0.0 };
+  PostLocals {
+    bloom: enabled(settings.bloom, BLOOM_STRENGTH),
+    vignette: enabled(settings.vignette, VIGNETTE_STRENGTH),
+    danger: enabled(settings.vignette, danger),
+    night: enabled(settings.color_grading, night),
+    grain: enabled(settings.film_grain, FILM_GRAIN_STRENGTH),
+    time: (frame % 1000) as f32,
+  }
+}
+
+pub struct PostProcessSystem<R: Resources> {
+  pub scene: HdrTarget<R>,
+  samples: u16,
+  resolve_bundle: Option<gfx::pso::bundle::Bundle<R, resolve_pipeline::Data<R>>>,
+  bloom: [BloomTarget<R>; 2],
+  blur_bundles: [gfx::pso::bundle::Bundle<R, blur_pipeline::Data<R>>; 2],
+  post_bundle: gfx::pso::bundle::Bundle<R, post_pipeline::Data<R>>,
+}
+
+impl<R: Resources> PostProcessSystem<R> {
+  pub fn new<F>(factory: &mut F,
+                rtv: RenderTargetView<R, ColorFormat>,
+                samples: u16,
+                assets: &AssetManager) -> GameResult<PostProcessSystem<R>>
+    where F: Factory<R> {
+    use gfx::traits::FactoryExt;
+
+    let (width, height, _, _) = rtv.get_dimensions();
+    let scene = HdrTarget::new(factory, width, height, samples)?;
+    let (bloom_width, bloom_height) = bloom_size(width, height);
+    let bloom = [BloomTarget::new(factory, bloom_width, bloom_height)?, BloomTarget::new(factory, bloom_width, bloom_height)?];
+    let (vertex_buffer, slice) = fullscreen_quad(factory);
+    let sampler = factory.create_sampler_linear();
+    let resolve_bundle = resolve_bundle(factory, &vertex_buffer, &slice, &scene, samples, assets)?;
+
+    let blur_bundles = [
+      blur_bundle(factory, &vertex_buffer, &slice, &sampler, &scene.view, &bloom[0], assets)?,
+      blur_bundle(factory, &vertex_buffer, &slice, &sampler, &bloom[0].view, &bloom[1], assets)?,
+    ];
+
+    let lut_size = NIGHT_LUT_SIZE as i32;
+    let lut = load_raw_texture(factory, &night_lut(), Point2::new(lut_size * lut_size, lut_size));
+    let post_pso = create_pipeline_simple(factory, &POST_SHADER.load(assets)?, post_pipeline::new()).map_err(GameError::Shader)?;
+    let post_data = post_pipeline::Data {
+      vbuf: vertex_buffer,
+      scene: (scene.view.clone(), sampler.clone()),
+      bloom: (bloom[1].view.clone(), sampler.clone()),
+      lut: (lut, sampler),
+      post_cb: factory.create_constant_buffer(1),
+      out_color: rtv,
+    };
+
+    Ok(PostProcessSystem {
+      scene,
+      samples,
+      resolve_bundle,
+      bloom,
+      blur_bundles,
+      post_bundle: gfx::Bundle::new(slice, post_pso, post_data),
+    })
+  }
+
+  pub fn reload_shaders<F>(&mut self, factory: &mut F, assets: &AssetManager) -> Result<(), String>
+    where F: Factory<R> {
+    if let Some(ref mut bundle) = self.resolve_bundle {
+      reload_pipeline(factory, assets, RESOLVE_SHADER, resolve_pipeline::new(), &mut bundle.pso)?;
+    }
+    for bundle in &mut self.blur_bundles {
+      reload_pipeline(factory, assets, BLOOM_SHADER, blur_pipeline::new(), &mut bundle.pso)?;
+    }
+    reload_pipeline(factory, assets, POST_SHADER, post_pipeline::new(), &mut self.post_bundle.pso)
+  }
+
+  pub fn resize<F>(&mut self, factory: &mut F, rtv: &RenderTargetView<R, ColorFormat>) -> GameResult<()>
+    where F: Factory<R> {
+    let (width, height, _, _) = rtv.get_dimensions();
+    let (bloom_width, bloom_height) = bloom_size(width, height);
+    self.scene = HdrTarget::new(factory, width, height, self.samples)?;
+    self.bloom = [BloomTarget::new(factory, bloom_width, bloom_height)?, BloomTarget::new(factory, bloom_width, bloom_height)?];
+
+    if let Some(ref mut bundle) = self.resolve_bundle {
+      if let Some(ref multisampled) = self.scene.multisampled {
+        bundle.data.scene = multisampled.clone();
+      }
+      bundle.data.out_color = self.scene.resolve_rtv.clone();
+    }
+
+    self.blur_bundles[0].data.source.0 = self.scene.view.clone();
+    self.blur_bundles[0].data.out_color = self.bloom[0].rtv.clone();
+    self.blur_bundles[1].data.source.0 = self.bloom[0].view.clone();
+    self.blur_bundles[1].data.out_color = self.bloom[1].rtv.clone();
+    self.post_bundle.data.scene.0 = self.scene.view.clone();
+    self.post_bundle.data.bloom.0 = self.bloom[1].view.clone();
+    self.post_bundle.data.out_color = rtv.clone();
+    Ok(())
+  }
+
+  pub fn clear<C>(&self, encoder: &mut gfx::Encoder<R, C>, color: [f32; 4])
+    where C: gfx::CommandBuffer<R> {
+    encoder.clear(&self.scene.rtv, color);
+    encoder.clear_depth(&self.scene.dsv, 1.0);
+  }
+
+  pub fn draw<C>(&self, locals: &PostLocals, encoder: &mut gfx::Encoder<R, C>)
+    where C: gfx::CommandBuffer<R> {
+    if let Some(ref bundle) = self.resolve_bundle {
+      bundle.encode(encoder);
+    }
+    if locals.bloom > 0.0 {
+      let (width, height, _, _) = self.bloom[0].rtv.get_dimensions();
+      let passes = [([1.0 / f32::from(width), 0.0], BLOOM_THRESHOLD), ([0.0, 1.0 / f32::from(height)], 0.0)];
+      for (bundle, &(direction, threshold)) in self.blur_bundles.iter().zip(passes.iter()) {
+        encoder.update_constant_buffer(&bundle.data.blur_cb, &BlurLocals { direction, threshold });
+        bundle.encode(encoder);
+      }
+    } else {
+      encoder.clear(&self.bloom[1].rtv, [0.0; 4]);
+    }
+
+    encoder.update_constant_buffer(&self.post_bundle.data.post_cb, locals);
+    self.post_bundle.encode(encoder);
+  }
+}
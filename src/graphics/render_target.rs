@@ -0,0 +1,215 @@
+use std::fs;
+use std::path::Path;
+
+use gfx::{Factory, format::{ChannelType, D24_S8, R16_G16_B16_A16, R8_G8_B8_A8, Rgba8, Swizzle}, handle::{DepthStencilView, RenderTargetView, ShaderResourceView},
+          memory::{Bind, Typed, Usage}, Resources, texture::{AaMode, Kind, Size}};
+use image::{Rgba, RgbaImage};
+
+use crate::assets::AssetManager;
+use crate::error::{GameError, GameResult};
+use crate::gfx_app::{ColorFormat, DepthFormat, HdrColorFormat};
+use crate::graphics::shader::{create_pipeline_simple, reload_pipeline, ShaderProgram};
+use crate::shaders::{present_pipeline, VertexData};
+
+const SHADER: ShaderProgram = ShaderProgram::new("present");
+
+pub type TargetViews<'a, R, C> = (&'a mut RenderTargetView<R, C>, &'a mut DepthStencilView<R, DepthFormat>);
+
+pub trait RenderTargets<R: Resources, C: Clone> {
+  fn targets(&mut self) -> Vec<TargetViews<'_, R, C>>;
+
+  fn resize(&mut self, rtv: &RenderTargetView<R, C>, dsv: &DepthStencilView<R, DepthFormat>) {
+    for (out_color, out_depth) in self.targets() {
+      *out_color = rtv.clone();
+      *out_depth = dsv.clone();
+    }
+  }
+}
+
+pub struct HdrTarget<R: Resources> {
+  pub view: ShaderResourceView<R, [f32; 4]>,
+  pub rtv: RenderTargetView<R, HdrColorFormat>,
+  pub dsv: DepthStencilView<R, DepthFormat>,
+  pub multisampled: Option<ShaderResourceView<R, [f32; 4]>>,
+  pub resolve_rtv: RenderTargetView<R, HdrColorFormat>,
+}
+
+impl<R: Resources> HdrTarget<R> {
+  pub fn new<F>(factory: &mut F, width: Size, height: Size, samples: u16) -> GameResult<HdrTarget<R>>
+    where F: Factory<R> {
+    let error = |e| GameError::Render(format!("HDR target {}x{} creation error {}", width, height, e));
+    let (_, view, resolve_rtv) = factory
+      .create_render_target::<HdrColorFormat>(width, height)
+      .map_err(|e| error(format!("{:?}", e)))?;
+    if samples < 2 {
+      let dsv = factory
+        .create_depth_stencil_view_only::<DepthFormat>(width, height)
+        .map_err(|e| error(format!("{:?}", e)))?;
+      return Ok(HdrTarget {
+        view,
+        rtv: resolve_rtv.clone(),
+        dsv,
+        multisampled: None,
+        resolve_rtv,
+      });
+    }
+
+    // Color and depth have to share the sample count to be bound together
+    let kind = Kind::D2(width, height, AaMode::Multi(samples as u8));
+    let color = factory
+      .create_texture::<R16_G16_B16_A16>(kind, 1, Bind::RENDER_TARGET | Bind::SHADER_RESOURCE, Usage::Data, Some(ChannelType::Float))
+      .map_err(|e| error(format!("{:?}", e)))?;
+    let multisampled = factory
+      .view_texture_as_shader_resource::<HdrColorFormat>(&color, (0, 0), Swizzle::new())
+      .map_err(|e| error(format!("{:?}", e)))?;
+    let rtv = factory
+      .view_texture_as_render_target::<HdrColorFormat>(&color, 0, None)
+      .map_err(|e| error(format!("{:?}", e)))?;
+    let depth = factory
+      .create_texture::<D24_S8>(kind, 1, Bind::DEPTH_STENCIL, Usage::Data, Some(ChannelType::Unorm))
+      .map_err(|e| error(format!("{:?}", e)))?;
+    let dsv = factory
+      .view_texture_as_depth_stencil_trivial::<DepthFormat>(&depth)
+      .map_err(|e| error(format!("{:?}", e)))?;
+    Ok(HdrTarget {
+      view,
+      rtv,
+      dsv,
+      multisampled: Some(multisampled),
+      resolve_rtv,
+    })
+  }
+}
+
+pub struct OffscreenTarget<R: Resources> {
+  texture: gfx::handle::Texture<R, R8_G8_B8_A8>,
+  download: gfx::handle::Buffer<R, [u8; 4]>,
+  pub view: ShaderResourceView<R, [f32; 4]>,
+  pub rtv: RenderTargetView<R, ColorFormat>,
+  pub dsv: DepthStencilView<R, DepthFormat>,
+}
+
+impl<R: Resources> OffscreenTarget<R> {
+  pub fn new<F>(factory: &mut F, width: Size, height: Size) -> GameResult<OffscreenTarget<R>>
+    where F: Factory<R> {
+    use gfx::traits::FactoryExt;
+
+    let error = |e| GameError::Render(format!("Offscreen target {}x{} creation error {}", width, height, e));
+    let kind = Kind::D2(width, height, AaMode::Single);
+    let bind = Bind::RENDER_TARGET | Bind::SHADER_RESOURCE | Bind::TRANSFER_SRC;
+    let texture = factory
+      .create_texture::<R8_G8_B8_A8>(kind, 1, bind, Usage::Data, Some(ChannelType::Unorm))
+      .map_err(|e| error(format!("{:?}", e)))?;
+    let view = factory
+      .view_texture_as_shader_resource::<Rgba8>(&texture, (0, 0), Swizzle::new())
+      .map_err(|e| error(format!("{:?}", e)))?;
+    let rtv = factory
+      .view_texture_as_render_target::<Rgba8>(&texture, 0, None)
+      .map_err(|e| error(format!("{:?}", e)))?;
+    let dsv = factory
+      .create_depth_stencil_view_only::<DepthFormat>(width, height)
+      .map_err(|e| error(format!("{:?}", e)))?;
+    let download = factory
+      .create_download_buffer::<[u8; 4]>(usize::from(width) * usize::from(height))
+      .map_err(|e| error(format!("{:?}", e)))?;
+    Ok(OffscreenTarget {
+      texture,
+      download,
+      view,
+      rtv,
+      dsv,
+    })
+  }
+
+  pub fn size(&self) -> (Size, Size) {
+    let (width, height, _, _) = self.texture.get_info().kind.get_dimensions();
+    (width, height)
+  }
+
+  pub fn capture<C>(&self, encoder: &mut gfx::Encoder<R, C>) -> GameResult<()>
+    where C: gfx::CommandBuffer<R> {
+    let info = self.texture.get_info().to_raw_image_info(ChannelType::Unorm, 0);
+    encoder.copy_texture_to_buffer_raw(self.texture.raw(), None, info, self.download.raw(), 0)
+      .map_err(|e| GameError::Render(format!("Frame capture error {:?}", e)))
+  }
+
+  pub fn read_image<F>(&self, factory: &mut F) -> GameResult<RgbaImage>
+    where F: Factory<R> {
+    let (width, height) = self.size();
+    let pixels = factory.read_mapping(&self.download)
+      .map_err(|e| GameError::Render(format!("Frame read error {:?}", e)))?;
+    Ok(frame_image(&pixels, u32::from(width), u32::from(height)))
+  }
+}
+
+pub fn frame_image(pixels: &[[u8; 4]], width: u32, height: u32) -> RgbaImage {
+  RgbaImage::from_fn(width, height, |x, y| {
+    let [r, g, b, _] = pixels[((height - 1 - y) * width + x) as usize];
+    Rgba([r, g, b, 255])
+  })
+}
+
+pub fn save_screenshot(image: &RgbaImage, path: &Path) -> GameResult<()> {
+  let io_error = |e| GameError::Io(path.display().to_string(), e);
+  if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+    fs::create_dir_all(dir).map_err(io_error)?;
+  }
+  image.save(path).map_err(io_error)
+}
+
+pub fn fullscreen_quad<R, F>(factory: &mut F) -> (gfx::handle::Buffer<R, VertexData>, gfx::Slice<R>)
+  where R: Resources, F: Factory<R> {
+  use gfx::traits::FactoryExt;
+
+  let vertices = [
+    VertexData::new([-1.0, -1.0], [0.0, 0.0]),
+    VertexData::new([1.0, -1.0], [1.0, 0.0]),
+    VertexData::new([1.0, 1.0], [1.0, 1.0]),
+    VertexData::new([-1.0, -1.0], [0.0, 0.0]),
+    VertexData::new([1.0, 1.0], [1.0, 1.0]),
+    VertexData::new([-1.0, 1.0], [0.0, 1.0]),
+  ];
+  factory.create_vertex_buffer_with_slice(&vertices, ())
+}
+
+pub struct PresentDrawSystem<R: Resources> {
+  bundle: gfx::pso::bundle::Bundle<R, present_pipeline::Data<R>>,
+}
+
+impl<R: Resources> PresentDrawSystem<R> {
+  pub fn new<F>(factory: &mut F,
+                frame: ShaderResourceView<R, [f32; 4]>,
+                rtv: RenderTargetView<R, ColorFormat>,
+                assets: &AssetManager) -> GameResult<PresentDrawSystem<R>>
+    where F: Factory<R> {
+    use gfx::traits::FactoryExt;
+
+    let (vertex_buffer, slice) = fullscreen_quad(factory);
+    let pso = create_pipeline_simple(factory, &SHADER.load(assets)?, present_pipeline::new()).map_err(GameError::Shader)?;
+
+    let pipeline_data = present_pipeline::Data {
+      vbuf: vertex_buffer,
+      frame: (frame, factory.create_sampler_linear()),
+      out_color: rtv,
+    };
+
+    Ok(PresentDrawSystem {
+      bundle: gfx::Bundle::new(slice, pso, pipeline_data),
+    })
+  }
+
+  pub fn reload_shaders<F>(&mut self, factory: &mut F, assets: &AssetManager) -> Result<(), String>
+    where F: Factory<R> {
+    reload_pipeline(factory, assets, SHADER, present_pipeline::new(), &mut self.bundle.pso)
+  }
+
+  pub fn resize(&mut self, frame: &ShaderResourceView<R, [f32; 4]>, rtv: &RenderTargetView<R, ColorFormat>) {
+    self.bundle.data.frame.0 = frame.clone();
+    self.bundle.data.out_color = rtv.clone();
+  }
+
+  pub fn draw<C>(&self, encoder: &mut gfx::Encoder<R, C>)
+    where C: gfx::CommandBuffer<R> {
+    self.bundle.encode(encoder);
+  }
+}
@@ -0,0 +1,132 @@
+use gfx::{Factory, PipelineStateError, pso::{PipelineInit, PipelineState}, Resources, shade::ProgramError};
+use gfx_core::shade::CreateShaderError;
+
+use crate::assets::AssetManager;
+use crate::error::GameResult;
+use crate::game::constants::{LIGHTING_SHADER, SHADER_DIR};
+
+#[derive(Clone, Copy)]
+pub struct ShaderProgram {
+  pub name: &'static str,
+  pub is_lit: bool,
+}
+
+pub struct ShaderCode {
+  pub name: &'static str,
+  pub vertex_path: String,
+  pub vertex: Vec<u8>,
+  pub pixel_path: String,
+  pub pixel: Vec<u8>,
+}
+
+impl ShaderProgram {
+  pub const fn new(name: &'static str) -> ShaderProgram {
+    ShaderProgram {
+      name,
+      is_lit: false,
+    }
+  }
+
+  pub const fn lit(name: &'static str) -> ShaderProgram {
+    ShaderProgram {
+      name,
+      is_lit: true,
+    }
+  }
+
+  pub fn load(&self, assets: &AssetManager) -> GameResult<ShaderCode> {
+    let vertex_file = format!("{}/{}.v.glsl", SHADER_DIR, self.name);
+    let pixel_file = format!("{}/{}.f.glsl", SHADER_DIR, self.name);
+    let pixel = if self.is_lit {
+      let lighting = assets.read_string(&format!("{}/{}", SHADER_DIR, LIGHTING_SHADER))?;
+      insert_after_version(&assets.read_string(&pixel_file)?, &lighting).into_bytes()
+    } else {
+      assets.read_bytes(&pixel_file)?
+    };
+    Ok(ShaderCode {
+      name: self.name,
+      vertex: assets.read_bytes(&vertex_file)?,
+      vertex_path: assets.path(&vertex_file).display().to_string(),
+      pixel,
+      pixel_path: assets.path(&pixel_file).display().to_string(),
+    })
+  }
+}
+
+// #line keeps compile errors on the original file's line numbers, GLSL 1.50 numbers the next line as line + 1
+pub fn insert_after_version(source: &str, snippet: &str) -> String {
+  let version_end = source.find("#version")
+    .map(|start| source[start..].find('\n').map_or(source.len(), |end| start + end + 1))
+    .unwrap_or(0);
+  let version_line = source[..version_end].lines().count();
+  format!("{}{}\n#line {}\n{}", &source[..version_end], snippet, version_line, &source[version_end..])
+}
+
+fn log_line_number(line: &str) -> Option<&str> {
+  ["0:", "0("].iter()
+    .filter_map(|prefix| line.match_indices(prefix).find(|(idx, _)| *idx == 0 || !line[..*idx].ends_with(char::is_alphanumeric)))
+    .map(|(idx, prefix)| {
+      let rest = &line[idx + prefix.len()..];
+      &rest[..rest.find(|c: char| !c.is_ascii_digit()).unwrap_or_else(|| rest.len())]
+    })
+    .find(|number| !number.is_empty())
+}
+
+pub fn format_shader_log(path: &str, log: &str) -> String {
+  log.lines()
+    .map(str::trim)
+    .filter(|line| !line.is_empty())
+    .map(|line| match log_line_number(line) {
+      Some(number) => format!("{}:{}: {}", path, number, line),
+      None => format!("{}: {}", path, line),
+    })
+    .collect::<Vec<String>>()
+    .join("\n")
+}
+
+fn shader_error(path: &str, error: CreateShaderError) -> String {
+  match error {
+    CreateShaderError::CompilationFailed(log) => format_shader_log(path, &log),
+    e => format!("{}: {:?}", path, e),
+  }
+}
+
+pub fn create_pipeline<R, F, I>(factory: &mut F,
+                                code: &ShaderCode,
+                                primitive: gfx::Primitive,
+                                rasterizer: gfx::state::Rasterizer,
+                                init: I) -> Result<PipelineState<R, I::Meta>, String>
+  where R: Resources, F: Factory<R>, I: PipelineInit {
+  use gfx::traits::FactoryExt;
+
+  let vertex = factory.create_shader_vertex(&code.vertex)
+    .map_err(|e| shader_error(&code.vertex_path, e))?;
+  let pixel = factory.create_shader_pixel(&code.pixel)
+    .map_err(|e| shader_error(&code.pixel_path, e))?;
+  // Multisampling only has an effect on multisampled targets
+  let rasterizer = gfx::state::Rasterizer { samples: Some(gfx::state::MultiSample), ..rasterizer };
+  factory.create_pipeline_state(&gfx::ShaderSet::Simple(vertex, pixel), primitive, rasterizer, init)
+    .map_err(|e| match e {
+      PipelineStateError::Program(ProgramError::Link(log)) =>
+        format_shader_log(&format!("{} + {}", code.vertex_path, code.pixel_path), &log.to_string()),
+      e => format!("{} pipeline error {:?}", code.name, e),
+    })
+}
+
+pub fn create_pipeline_simple<R, F, I>(factory: &mut F,
+                                       code: &ShaderCode,
+                                       init: I) -> Result<PipelineState<R, I::Meta>, String>
+  where R: Resources, F: Factory<R>, I: PipelineInit {
+  create_pipeline(factory, code, gfx::Primitive::TriangleList, gfx::state::Rasterizer::new_fill().with_cull_back(), init)
+}
+
+pub fn reload_pipeline<R, F, I>(factory: &mut F,
+                                assets: &AssetManager,
+                                shader: ShaderProgram,
+                                init: I,
+                                pso: &mut PipelineState<R, I::Meta>) -> Result<(), String>
+  where R: Resources, F: Factory<R>, I: PipelineInit {
+  let code = shader.load(assets).map_err(|e| e.to_string())?;
+  *pso = create_pipeline_simple(factory, &code, init)?;
+  Ok(())
+}
@@ -0,0 +1,46 @@
+use crate::game::constants::{MAX_SPRITE_INSTANCES, SPRITE_DEPTH_FAR, SPRITE_DEPTH_NEAR};
+use crate::shaders::{Projection, SpriteInstance};
+
+pub fn sprite_depth(rank: usize) -> f32 {
+  let max_rank = (MAX_SPRITE_INSTANCES - 1) as f32;
+  SPRITE_DEPTH_FAR - (rank as f32).min(max_rank) / max_rank * (SPRITE_DEPTH_FAR - SPRITE_DEPTH_NEAR)
+}
+
+pub fn create_instance_buffer<R, F>(factory: &mut F) -> gfx::handle::Buffer<R, SpriteInstance>
+  where R: gfx::Resources, F: gfx::Factory<R> {
+  factory
+    .create_buffer(MAX_SPRITE_INSTANCES, gfx::buffer::Role::Vertex, gfx::memory::Usage::Dynamic, gfx::memory::Bind::empty())
+    .expect("Sprite instance buffer creation error")
+}
+
+#[derive(Default)]
+pub struct SpriteBatch {
+  projection: Option<Projection>,
+  instances: Vec<SpriteInstance>,
+}
+
+impl SpriteBatch {
+  pub fn push(&mut self, projection: &Projection, instance: SpriteInstance) {
+    self.projection = Some(*projection);
+    if self.instances.len() < MAX_SPRITE_INSTANCES {
+      self.instances.push(instance);
+    }
+  }
+
+  pub fn upload<R, C>(&mut self,
+                      projection_cb: &gfx::handle::Buffer<R, Projection>,
+                      instance_buffer: &gfx::handle::Buffer<R, SpriteInstance>,
+                      encoder: &mut gfx::Encoder<R, C>) -> Option<u32>
+    where R: gfx::Resources, C: gfx::CommandBuffer<R> {
+    let projection = self.projection.take()?;
+    if self.instances.is_empty() {
+      return None;
+    }
+    encoder.update_constant_buffer(projection_cb, &projection);
+    encoder.update_buffer(instance_buffer, &self.instances, 0)
+      .unwrap_or_else(|e| panic!("Sprite instance buffer update error {:?}", e));
+    let count = self.instances.len() as u32;
+    self.instances.clear();
+    Some(count)
+  }
+}
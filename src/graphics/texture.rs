@@ -1,13 +1,6 @@
-use std::collections::HashMap;
-use std::hash::BuildHasher;
-use std::io::Cursor;
-
 use cgmath::Point2;
-use gfx::{Factory, format::Rgba8, handle::ShaderResourceView, Resources, texture::{AaMode, Kind, Mipmap, Size}};
-use rusttype::Font;
-
-use crate::gfx_app::ColorFormat;
-use crate::hud::font::draw_text;
+use gfx::{Factory, format::{ChannelType, R8_G8_B8_A8, Rgba8, SurfaceType, Swizzle}, handle::ShaderResourceView, memory::{Bind, Typed, Usage}, Resources, texture::{AaMode, Kind, Mipmap, Size}};
+use image::RgbaImage;
 
 #[derive(Clone)]
 pub struct Texture<R> where R: Resources {
@@ -24,11 +17,11 @@ impl<R> Texture<R> where R: Resources {
   }
 }
 
-pub fn load_texture<R, F>(factory: &mut F, data: &[u8]) -> ShaderResourceView<R, [f32; 4]> where R: Resources, F: Factory<R> {
-  let img = image::load(Cursor::new(data), image::PNG).unwrap().to_rgba();
+pub fn load_texture<R, F>(factory: &mut F, img: &RgbaImage) -> ShaderResourceView<R, [f32; 4]> where R: Resources, F: Factory<R> {
   let (width, height) = img.dimensions();
   let kind = Kind::D2(width as Size, height as Size, AaMode::Single);
-  match factory.create_texture_immutable_u8::<Rgba8>(kind, Mipmap::Provided, &[&img]) {
+  let data: &[u8] = img;
+  match factory.create_texture_immutable_u8::<Rgba8>(kind, Mipmap::Provided, &[data]) {
     Ok(val) => val.1,
     Err(e) => panic!("Couldn't load texture {:?}", e)
   }
@@ -37,25 +30,71 @@ pub fn load_texture<R, F>(factory: &mut F, data: &[u8]) -> ShaderResourceView<R,
 pub fn load_raw_texture<R, F>(factory: &mut F, data: &[u8], size: Point2<i32>) -> ShaderResourceView<R, [f32; 4]>
   where R: Resources, F: Factory<R> {
   let kind = Kind::D2(size.x as Size, size.y as Size, AaMode::Single);
-  let mipmap = Mipmap::Provided;
-  match factory
-    .create_texture_immutable_u8::<ColorFormat>(kind, mipmap, &[data]) {
+  match factory.create_texture_immutable_u8::<Rgba8>(kind, Mipmap::Provided, &[data]) {
     Ok(val) => val.1,
     Err(e) => panic!("Couldn't load raw texture {:?}", e)
   }
 }
 
-pub fn text_texture<'a, R, F, S: BuildHasher>(factory: &mut F,
-                                              font: &Font,
-                                              texts: &[&str],
-                                              texture_cache: &'a mut HashMap<String, Texture<R>, S>)
-                                              -> &'a mut HashMap<String, Texture<R>, S>
+pub fn solid_texture<R, F>(factory: &mut F, color: [u8; 4]) -> ShaderResourceView<R, [f32; 4]>
   where R: Resources, F: Factory<R> {
-  let text_texture_height = 100.0;
-  texts.iter().for_each(|text| {
-    let (texture_size, texture_data) = draw_text(&font, text_texture_height, text);
-    let texture = load_raw_texture(factory, &texture_data.as_slice(), texture_size);
-    texture_cache.insert((*text).to_string(), Texture::new(texture, None));
-  });
-  texture_cache
+  load_raw_texture(factory, &color, Point2::new(1, 1))
+}
+
+pub fn create_dynamic_texture<R, F>(factory: &mut F, size: Point2<i32>)
+                                    -> (gfx::handle::Texture<R, R8_G8_B8_A8>, ShaderResourceView<R, [f32; 4]>)
+  where R: Resources, F: Factory<R> {
+  let kind = Kind::D2(size.x as Size, size.y as Size, AaMode::Single);
+  let texture = factory
+    .create_texture::<R8_G8_B8_A8>(kind, 1, Bind::SHADER_RESOURCE, Usage::Dynamic, Some(ChannelType::Unorm))
+    .unwrap_or_else(|e| panic!("Couldn't create dynamic texture {:?}", e));
+  let view = factory
+    .view_texture_as_shader_resource::<Rgba8>(&texture, (0, 0), Swizzle::new())
+    .unwrap_or_else(|e| panic!("Couldn't create dynamic texture view {:?}", e));
+  (texture, view)
+}
+
+pub fn create_updatable_texture<R, F>(factory: &mut F, img: &RgbaImage)
+                                      -> (gfx::handle::Texture<R, R8_G8_B8_A8>, ShaderResourceView<R, [f32; 4]>)
+  where R: Resources, F: Factory<R> {
+  let (width, height) = img.dimensions();
+  let info = gfx::texture::Info {
+    kind: Kind::D2(width as Size, height as Size, AaMode::Single),
+    levels: 1,
+    format: SurfaceType::R8_G8_B8_A8,
+    bind: Bind::SHADER_RESOURCE,
+    usage: Usage::Dynamic,
+  };
+  let data: &[u8] = img;
+  let raw = factory
+    .create_texture_raw(info, Some(ChannelType::Unorm), Some((&[data], Mipmap::Provided)))
+    .unwrap_or_else(|e| panic!("Couldn't create updatable texture {:?}", e));
+  let texture: gfx::handle::Texture<R, R8_G8_B8_A8> = Typed::new(raw);
+  let view = factory
+    .view_texture_as_shader_resource::<Rgba8>(&texture, (0, 0), Swizzle::new())
+    .unwrap_or_else(|e| panic!("Couldn't create updatable texture view {:?}", e));
+  (texture, view)
+}
+
+pub fn update_texture<R, C>(encoder: &mut gfx::Encoder<R, C>,
+                            texture: &gfx::handle::Texture<R, R8_G8_B8_A8>,
+                            img: &RgbaImage) -> Result<(), String>
+  where R: Resources, C: gfx::CommandBuffer<R> {
+  let (width, height, _, _) = texture.get_info().kind.get_dimensions();
+  if img.width() > u32::from(width) || img.height() > u32::from(height) {
+    return Err(format!("Image {}x{} does not fit texture {}x{}", img.width(), img.height(), width, height));
+  }
+  let image_info = gfx::texture::NewImageInfo {
+    xoffset: 0,
+    yoffset: 0,
+    zoffset: 0,
+    width: img.width() as u16,
+    height: img.height() as u16,
+    depth: 1,
+    format: (),
+    mipmap: 0,
+  };
+  let texels = img.pixels().map(|p| p.0).collect::<Vec<[u8; 4]>>();
+  encoder.update_texture::<R8_G8_B8_A8, Rgba8>(texture, None, image_info, &texels)
+    .map_err(|e| format!("Texture update error {:?}", e))
 }
@@ -0,0 +1,110 @@
+use std::collections::VecDeque;
+
+use cgmath::Point2;
+
+use crate::game::constants::{DAMAGE_TEXT_COLOR, FLOATING_TEXT_DRIFT, FLOATING_TEXT_FONT_SIZE, FLOATING_TEXT_LIFETIME, HUD_FONT_SIZE,
+                             HUD_TEXT_COLOR, KILL_FEED_LIFETIME, KILL_FEED_SIZE, KILL_TEXT_COLOR};
+use crate::graphics::dimensions::world_to_clip_space;
+use crate::hud::layout::{Anchor, ScreenLayout};
+use crate::hud::TextDrawable;
+use crate::shaders::{Position, Projection};
+
+pub enum CombatEvent {
+  Damage { map_position: Position, amount: u32 },
+  Kill { map_position: Position, is_critical: bool, points: u32 },
+}
+
+#[derive(Default)]
+pub struct CombatEvents {
+  pub events: Vec<CombatEvent>,
+}
+
+fn faded(color: [f32; 4], age: f64, lifetime: f64) -> [f32; 4] {
+  [color[0], color[1], color[2], color[3] * (1.0 - (age / lifetime) as f32).max(0.0)]
+}
+
+struct FloatingText {
+  text: TextDrawable,
+  color: [f32; 4],
+  map_position: Position,
+  age: f64,
+}
+
+struct KillFeedEntry {
+  text: TextDrawable,
+  age: f64,
+}
+
+pub struct CombatFeed {
+  floating_texts: Vec<FloatingText>,
+  kill_feed: VecDeque<KillFeedEntry>,
+}
+
+impl CombatFeed {
+  pub fn new() -> CombatFeed {
+    CombatFeed {
+      floating_texts: Vec::new(),
+      kill_feed: VecDeque::new(),
+    }
+  }
+
+  fn add_floating_text(&mut self, text: &str, color: [f32; 4], map_position: Position) {
+    self.floating_texts.push(FloatingText {
+      text: TextDrawable::new(text, Anchor::Center, Point2::new(0.0, 0.0), FLOATING_TEXT_FONT_SIZE, color),
+      color,
+      map_position,
+      age: 0.0,
+    });
+  }
+
+  fn add_kill(&mut self, is_critical: bool, points: u32) {
+    let text = if is_critical {
+      format!("Critical kill +{}", points)
+    } else {
+      format!("Zombie killed +{}", points)
+    };
+    self.kill_feed.push_front(KillFeedEntry {
+      text: TextDrawable::new(&text, Anchor::TopLeft, Point2::new(0.0, 0.0), HUD_FONT_SIZE, HUD_TEXT_COLOR),
+      age: 0.0,
+    });
+    self.kill_feed.truncate(KILL_FEED_SIZE);
+  }
+
+  pub fn handle_events(&mut self, events: &mut CombatEvents) {
+    for event in events.events.drain(..) {
+      match event {
+        CombatEvent::Damage { map_position, amount } =>
+          self.add_floating_text(&format!("-{}", amount), DAMAGE_TEXT_COLOR, map_position),
+        CombatEvent::Kill { map_position, is_critical, points } => {
+          self.add_floating_text(&format!("+{}", points), KILL_TEXT_COLOR, map_position);
+          self.add_kill(is_critical, points);
+        }
+      }
+    }
+  }
+
+  pub fn update(&mut self, delta: f64, movement: Position, projection: &Projection, layout: &ScreenLayout) {
+    self.floating_texts.retain(|t| t.age < FLOATING_TEXT_LIFETIME);
+    for t in &mut self.floating_texts {
+      t.age += delta;
+      let clip = world_to_clip_space(projection, movement - t.map_position);
+      let offset = layout.clip_to_center_offset(clip);
+      t.text.set_offset(Point2::new(offset.x, offset.y - t.age as f32 * FLOATING_TEXT_DRIFT));
+      t.text.set_color(faded(t.color, t.age, FLOATING_TEXT_LIFETIME));
+    }
+
+    while self.kill_feed.back().map_or(false, |e| e.age >= KILL_FEED_LIFETIME) {
+      self.kill_feed.pop_back();
+    }
+    for (idx, e) in self.kill_feed.iter_mut().enumerate() {
+      e.age += delta;
+      e.text.set_offset(Point2::new(12.0, 48.0 + idx as f32 * (HUD_FONT_SIZE + 4.0)));
+      e.text.set_color(faded(HUD_TEXT_COLOR, e.age, KILL_FEED_LIFETIME));
+    }
+  }
+
+  pub fn texts(&self) -> impl Iterator<Item=&TextDrawable> {
+    self.floating_texts.iter().map(|t| &t.text)
+      .chain(self.kill_feed.iter().map(|e| &e.text))
+  }
+}
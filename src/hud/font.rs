@@ -1,44 +1,35 @@
 use cgmath::Point2;
-use rusttype::{Font, point, Scale};
+use rusttype::{Font, FontCollection, point, PositionedGlyph, Scale};
 
-pub fn draw_text(font: &Font, font_size: f32, text: &str) -> (Point2<i32>, Vec<u8>) {
-  let scale = Scale {
-    x: font_size / 2.0,
-    y: font_size,
-  };
-  let v_metrics = font.v_metrics(scale);
-  let offset = point(0.0, v_metrics.ascent);
-  let glyphs: Vec<_> = font.layout(text, scale, offset).collect();
-  let pixel_height = font_size.ceil() as usize;
-  let width = glyphs
-    .iter()
-    .rev()
-    .map(|g| g.position().x as f32 + g.unpositioned().h_metrics().advance_width)
-    .next()
-    .unwrap_or(0.0)
-    .ceil() as usize;
+use crate::assets::AssetManager;
+use crate::error::{GameError, GameResult};
+use crate::game::constants::FONT_PATH;
+use crate::hud::TextAlignment;
+
+pub fn load_font(assets: &AssetManager) -> GameResult<Font<'static>> {
+  let font_bytes = assets.read_bytes(FONT_PATH)?;
+  FontCollection::from_bytes(font_bytes)
+    .and_then(|collection| collection.into_font())
+    .map_err(|e| GameError::Font(e.to_string()))
+}
 
-  let mut pixel_data = vec![0_u8; 4 * width * pixel_height];
-  let mapping_scale = 255.0;
-  for g in glyphs {
-    if let Some(bb) = g.pixel_bounding_box() {
-      g.draw(|x, y, v| {
-        let v = (v * mapping_scale + 0.5) as u8;
-        let x = x as i32 + bb.min.x;
-        let y = y as i32 + bb.min.y;
-        if v > 0 && x >= 0 && x < width as i32 && y >= 0 && y < pixel_height as i32 {
-          let i = (x as usize + y as usize * width) * 4;
-          pixel_data[i] = 255;
-          pixel_data[i + 1] = 255;
-          pixel_data[i + 2] = 255;
-          pixel_data[i + 3] = v;
-        }
-      });
-    }
-  }
-  let size = Point2 {
-    x: width as i32,
-    y: pixel_height as i32,
+pub fn text_width(font: &Font<'static>, text: &str, font_size: f32) -> f32 {
+  font.layout(text, Scale::uniform(font_size), point(0.0, 0.0))
+    .last()
+    .map_or(0.0, |g| g.position().x + g.unpositioned().h_metrics().advance_width)
+}
+
+pub fn layout_text(font: &Font<'static>,
+                   text: &str,
+                   font_size: f32,
+                   anchor: Point2<f32>,
+                   alignment: TextAlignment) -> Vec<PositionedGlyph<'static>> {
+  let scale = Scale::uniform(font_size);
+  let v_metrics = font.v_metrics(scale);
+  let x = match alignment {
+    TextAlignment::Left => anchor.x,
+    TextAlignment::Center => anchor.x - text_width(font, text, font_size) / 2.0,
+    TextAlignment::Right => anchor.x - text_width(font, text, font_size),
   };
-  (size, pixel_data)
+  font.layout(text, scale, point(x, anchor.y + v_metrics.ascent)).collect()
 }
@@ -0,0 +1,19 @@
+#[test]
+fn text_alignment_test() {
+  use cgmath::Point2;
+
+  use crate::assets::AssetManager;
+  use crate::hud::font::{layout_text, load_font, text_width};
+  use crate::hud::TextAlignment;
+
+  let font = load_font(&AssetManager::default()).expect("Font should load");
+  let width = text_width(&font, "Ammo 10", 32.0);
+  assert!(width > 0.0, "Text should have width");
+
+  let left = layout_text(&font, "Ammo 10", 32.0, Point2::new(200.0, 0.0), TextAlignment::Left);
+  let right = layout_text(&font, "Ammo 10", 32.0, Point2::new(200.0, 0.0), TextAlignment::Right);
+  let center = layout_text(&font, "Ammo 10", 32.0, Point2::new(200.0, 0.0), TextAlignment::Center);
+  assert!((left[0].position().x - 200.0).abs() < 0.01);
+  assert!((right[0].position().x - (200.0 - width)).abs() < 0.01);
+  assert!((center[0].position().x - (200.0 - width / 2.0)).abs() < 0.01);
+}
@@ -1,20 +1,63 @@
+use cgmath::Point2;
 
-use crate::game::constants::{CURRENT_AMMO_TEXT, CURRENT_MAGAZINE_TEXT, GAME_VERSION};
+use crate::game::constants::{AMMO_BAR_COLOR, GAME_OVER_TEXT_COLOR, GAME_VERSION, HEALTH_BAR_COLOR, HUD_FONT_SIZE, HUD_TEXT_COLOR, TITLE_FONT_SIZE};
+use crate::hud::combat_feed::CombatFeed;
+use crate::hud::layout::{Anchor, UiRect};
+use crate::hud::minimap::Minimap;
 use crate::hud::TextDrawable;
-use crate::shaders::Position;
+use crate::hud::ui::{UiBar, UiIcon};
 
 pub struct HudObjects {
-  pub objects: Vec<TextDrawable>,
+  pub version_text: TextDrawable,
+  pub ammo_text: TextDrawable,
+  pub magazine_text: TextDrawable,
+  pub score_text: TextDrawable,
+  pub messages: Vec<TextDrawable>,
+  pub ammo_bar: UiBar,
+  pub health_bar: UiBar,
+  pub icons: Vec<UiIcon>,
+  pub minimap: Minimap,
+  pub combat_feed: CombatFeed,
 }
 
 impl HudObjects {
   pub fn new() -> HudObjects {
     HudObjects {
-      objects: vec![
-        TextDrawable::new(GAME_VERSION, Position::origin()),
-        TextDrawable::new(CURRENT_AMMO_TEXT, Position::new(1.9, -1.9)),
-        TextDrawable::new(CURRENT_MAGAZINE_TEXT, Position::new(1.9, -1.94)),
-      ]
+      version_text: TextDrawable::new(GAME_VERSION, Anchor::TopLeft, Point2::new(12.0, 12.0), HUD_FONT_SIZE, HUD_TEXT_COLOR),
+      ammo_text: TextDrawable::new("", Anchor::BottomRight, Point2::new(-56.0, -76.0), HUD_FONT_SIZE, HUD_TEXT_COLOR),
+      magazine_text: TextDrawable::new("", Anchor::BottomRight, Point2::new(-56.0, -16.0), HUD_FONT_SIZE, HUD_TEXT_COLOR),
+      score_text: TextDrawable::new("", Anchor::TopRight, Point2::new(-16.0, 12.0), HUD_FONT_SIZE, HUD_TEXT_COLOR),
+      messages: Vec::new(),
+      ammo_bar: UiBar::new(UiRect::new(Anchor::BottomRight, Point2::new(-56.0, -56.0), Point2::new(180.0, 10.0)), AMMO_BAR_COLOR),
+      health_bar: UiBar::new(UiRect::new(Anchor::BottomLeft, Point2::new(16.0, -16.0), Point2::new(220.0, 14.0)), HEALTH_BAR_COLOR),
+      icons: vec![
+        UiIcon::new(UiRect::new(Anchor::BottomRight, Point2::new(-16.0, -50.0), Point2::new(28.0, 40.0))),
+      ],
+      minimap: Minimap::new(),
+      combat_feed: CombatFeed::new(),
+    }
+  }
+
+  pub fn texts(&self) -> impl Iterator<Item=&TextDrawable> {
+    vec![&self.version_text, &self.ammo_text, &self.magazine_text, &self.score_text].into_iter()
+      .chain(self.combat_feed.texts())
+      .chain(self.messages.iter())
+  }
+
+  pub fn bars(&self) -> impl Iterator<Item=&UiBar> {
+    vec![&self.ammo_bar, &self.health_bar].into_iter()
+  }
+
+  pub fn show_messages(&mut self, texts: &[String]) {
+    self.messages.truncate(texts.len());
+    for (idx, text) in texts.iter().enumerate() {
+      if idx < self.messages.len() {
+        self.messages[idx].update(text.clone());
+      } else if idx == 0 {
+        self.messages.push(TextDrawable::new(text, Anchor::Center, Point2::new(0.0, -180.0), TITLE_FONT_SIZE, GAME_OVER_TEXT_COLOR));
+      } else {
+        self.messages.push(TextDrawable::new(text, Anchor::Center, Point2::new(0.0, -120.0 + idx as f32 * 40.0), HUD_FONT_SIZE, HUD_TEXT_COLOR));
+      }
     }
   }
 }
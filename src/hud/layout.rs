@@ -0,0 +1,112 @@
+use cgmath::Point2;
+
+use crate::game::constants::UI_REFERENCE_HEIGHT;
+use crate::graphics::dimensions::Dimensions;
+use crate::hud::TextAlignment;
+use crate::shaders::ColorVertex;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Anchor {
+  TopLeft,
+  TopRight,
+  Center,
+  BottomLeft,
+  BottomRight,
+}
+
+impl Anchor {
+  pub fn pivot(self) -> Point2<f32> {
+    match self {
+      Anchor::TopLeft => Point2::new(0.0, 0.0),
+      Anchor::TopRight => Point2::new(1.0, 0.0),
+      Anchor::Center => Point2::new(0.5, 0.5),
+      Anchor::BottomLeft => Point2::new(0.0, 1.0),
+      Anchor::BottomRight => Point2::new(1.0, 1.0),
+    }
+  }
+
+  pub fn alignment(self) -> TextAlignment {
+    match self {
+      Anchor::TopLeft | Anchor::BottomLeft => TextAlignment::Left,
+      Anchor::Center => TextAlignment::Center,
+      Anchor::TopRight | Anchor::BottomRight => TextAlignment::Right,
+    }
+  }
+}
+
+#[derive(Clone, Copy)]
+pub struct UiRect {
+  pub anchor: Anchor,
+  pub offset: Point2<f32>,
+  pub size: Point2<f32>,
+}
+
+impl UiRect {
+  pub fn new(anchor: Anchor, offset: Point2<f32>, size: Point2<f32>) -> UiRect {
+    UiRect {
+      anchor,
+      offset,
+      size,
+    }
+  }
+}
+
+pub struct ScreenLayout {
+  pub screen_size: Point2<f32>,
+  pub scale: f32,
+}
+
+impl ScreenLayout {
+  pub fn new(dimensions: &Dimensions) -> ScreenLayout {
+    let screen_size = Point2::new(dimensions.window_width * dimensions.hidpi_factor,
+                                  dimensions.window_height * dimensions.hidpi_factor);
+    ScreenLayout {
+      screen_size,
+      scale: screen_size.y / UI_REFERENCE_HEIGHT,
+    }
+  }
+
+  pub fn position(&self, anchor: Anchor, offset: Point2<f32>) -> Point2<f32> {
+    let pivot = anchor.pivot();
+    Point2::new(pivot.x * self.screen_size.x + offset.x * self.scale,
+                pivot.y * self.screen_size.y + offset.y * self.scale)
+  }
+
+  pub fn resolve(&self, rect: &UiRect) -> (Point2<f32>, Point2<f32>) {
+    let pivot = rect.anchor.pivot();
+    let position = self.position(rect.anchor, rect.offset);
+    let size = Point2::new(rect.size.x * self.scale, rect.size.y * self.scale);
+    let min = Point2::new(position.x - pivot.x * size.x, position.y - pivot.y * size.y);
+    (min, Point2::new(min.x + size.x, min.y + size.y))
+  }
+
+  pub fn clip_to_center_offset(&self, clip: Point2<f32>) -> Point2<f32> {
+    Point2::new(clip.x * self.screen_size.x / 2.0 / self.scale,
+                -clip.y * self.screen_size.y / 2.0 / self.scale)
+  }
+
+  pub fn to_clip_space(&self, point: Point2<f32>) -> [f32; 2] {
+    [point.x / self.screen_size.x * 2.0 - 1.0,
+      1.0 - point.y / self.screen_size.y * 2.0]
+  }
+
+  pub fn quad(&self,
+              min: Point2<f32>,
+              max: Point2<f32>,
+              uv_min: [f32; 2],
+              uv_max: [f32; 2],
+              color: [f32; 4]) -> [ColorVertex; 6] {
+    let top_left = self.to_clip_space(min);
+    let bottom_right = self.to_clip_space(max);
+    let top_right = [bottom_right[0], top_left[1]];
+    let bottom_left = [top_left[0], bottom_right[1]];
+    [
+      ColorVertex::new(top_left, uv_min, color),
+      ColorVertex::new(top_right, [uv_max[0], uv_min[1]], color),
+      ColorVertex::new(bottom_left, [uv_min[0], uv_max[1]], color),
+      ColorVertex::new(top_right, [uv_max[0], uv_min[1]], color),
+      ColorVertex::new(bottom_right, uv_max, color),
+      ColorVertex::new(bottom_left, [uv_min[0], uv_max[1]], color),
+    ]
+  }
+}
@@ -0,0 +1,43 @@
+#[test]
+fn anchored_rect_scales_with_resolution_test() {
+  use cgmath::Point2;
+
+  use crate::graphics::dimensions::Dimensions;
+  use crate::hud::layout::{Anchor, ScreenLayout, UiRect};
+
+  let rect = UiRect::new(Anchor::BottomRight, Point2::new(-10.0, -10.0), Point2::new(100.0, 20.0));
+
+  let layout = ScreenLayout::new(&Dimensions::new(1600.0, 900.0, 1.0));
+  let (min, max) = layout.resolve(&rect);
+  assert_eq!((Point2::new(1490.0, 870.0), Point2::new(1590.0, 890.0)), (min, max));
+
+  let layout = ScreenLayout::new(&Dimensions::new(1600.0, 900.0, 2.0));
+  let (min, max) = layout.resolve(&rect);
+  assert_eq!((Point2::new(2980.0, 1740.0), Point2::new(3180.0, 1780.0)), (min, max));
+}
+
+#[test]
+fn centered_rect_test() {
+  use cgmath::Point2;
+
+  use crate::graphics::dimensions::Dimensions;
+  use crate::hud::layout::{Anchor, ScreenLayout, UiRect};
+
+  let layout = ScreenLayout::new(&Dimensions::new(1600.0, 900.0, 1.0));
+  let (min, max) = layout.resolve(&UiRect::new(Anchor::Center, Point2::new(0.0, 0.0), Point2::new(100.0, 100.0)));
+  assert_eq!((Point2::new(750.0, 400.0), Point2::new(850.0, 500.0)), (min, max));
+  assert_eq!([0.0, 0.0], layout.to_clip_space(Point2::new(800.0, 450.0)));
+}
+
+#[test]
+fn clip_to_center_offset_test() {
+  use cgmath::Point2;
+
+  use crate::graphics::dimensions::Dimensions;
+  use crate::hud::layout::{Anchor, ScreenLayout};
+
+  let layout = ScreenLayout::new(&Dimensions::new(1600.0, 900.0, 2.0));
+  let offset = layout.clip_to_center_offset(Point2::new(0.5, 0.5));
+  assert_eq!(Point2::new(400.0, -225.0), offset);
+  assert_eq!(Point2::new(2400.0, 450.0), layout.position(Anchor::Center, offset));
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use cgmath::Point2;
+use image::RgbaImage;
+
+use crate::game::constants::{MINIMAP_DOT_SIZE, MINIMAP_SIZE, TERRAIN_OBJECTS, TILES_PCS_H, TILES_PCS_W};
+use crate::hud::layout::{Anchor, ScreenLayout, UiRect};
+use crate::shaders::ColorVertex;
+use crate::terrain::tile_map::Terrain;
+
+const TILESHEET_TILES: u32 = 32;
+
+fn average_tile_color(sheet: &image::RgbaImage, tile: u32) -> [u8; 4] {
+  let tile_width = sheet.width() / TILESHEET_TILES;
+  let tile_height = sheet.height() / TILESHEET_TILES;
+  let x_start = (tile % TILESHEET_TILES) * tile_width;
+  let y_start = (tile / TILESHEET_TILES) * tile_height;
+  let mut sum = [0_u32; 3];
+  let mut count = 0;
+  for y in y_start..(y_start + tile_height).min(sheet.height()) {
+    for x in x_start..(x_start + tile_width).min(sheet.width()) {
+      let pixel = sheet.get_pixel(x, y);
+      if pixel[3] > 0 {
+        sum[0] += u32::from(pixel[0]);
+        sum[1] += u32::from(pixel[1]);
+        sum[2] += u32::from(pixel[2]);
+        count += 1;
+      }
+    }
+  }
+  if count == 0 {
+    [0, 0, 0, 255]
+  } else {
+    [(sum[0] / count) as u8, (sum[1] / count) as u8, (sum[2] / count) as u8, 255]
+  }
+}
+
+pub fn minimap_image(terrain: &Terrain, sheet: &RgbaImage) -> RgbaImage {
+  let mut palette: HashMap<u32, [u8; 4]> = HashMap::new();
+  let mut texels = Vec::with_capacity(TILES_PCS_W * TILES_PCS_H * 4);
+  for y_pos in 0..TILES_PCS_H {
+    for x_pos in 0..TILES_PCS_W {
+      let is_blocked = TERRAIN_OBJECTS.iter().any(|e| e[0] == x_pos as i32 && e[1] == y_pos as i32);
+      let color = if is_blocked {
+        [40, 40, 40, 255]
+      } else {
+        let tile = terrain.tile_at(x_pos, y_pos);
+        *palette.entry(tile).or_insert_with(|| average_tile_color(sheet, tile))
+      };
+      texels.extend_from_slice(&color);
+    }
+  }
+  RgbaImage::from_raw(TILES_PCS_W as u32, TILES_PCS_H as u32, texels)
+    .expect("Minimap image size mismatch")
+}
+
+pub struct Minimap {
+  pub is_visible: bool,
+  rect: UiRect,
+  dots: Vec<(Point2<i32>, [f32; 4])>,
+}
+
+impl Minimap {
+  pub fn new() -> Minimap {
+    Minimap {
+      is_visible: true,
+      rect: UiRect::new(Anchor::TopRight, Point2::new(-16.0, 48.0), Point2::new(MINIMAP_SIZE, MINIMAP_SIZE)),
+      dots: Vec::new(),
+    }
+  }
+
+  pub fn clear(&mut self) {
+    self.dots.clear();
+  }
+
+  pub fn add_dot(&mut self, tile: Point2<i32>, color: [f32; 4]) {
+    self.dots.push((tile, color));
+  }
+
+  pub fn map_quad(&self, layout: &ScreenLayout) -> [ColorVertex; 6] {
+    let (min, max) = layout.resolve(&self.rect);
+    layout.quad(min, max, [0.0, 0.0], [1.0, 1.0], [1.0, 1.0, 1.0, 0.85])
+  }
+
+  pub fn dot_quads(&self, layout: &ScreenLayout) -> Vec<ColorVertex> {
+    let (min, max) = layout.resolve(&self.rect);
+    let tile_size = Point2::new((max.x - min.x) / TILES_PCS_W as f32, (max.y - min.y) / TILES_PCS_H as f32);
+    let dot_size = MINIMAP_DOT_SIZE * layout.scale / 2.0;
+    self.dots.iter()
+      .filter(|(tile, _)| tile.x >= 0 && tile.y >= 0 && tile.x < TILES_PCS_W as i32 && tile.y < TILES_PCS_H as i32)
+      .flat_map(|(tile, color)| {
+        let center = Point2::new(min.x + (tile.x as f32 + 0.5) * tile_size.x,
+                                 min.y + (tile.y as f32 + 0.5) * tile_size.y);
+        layout.quad(Point2::new(center.x - dot_size, center.y - dot_size),
+                    Point2::new(center.x + dot_size, center.y + dot_size),
+                    [0.0, 0.0],
+                    [1.0, 1.0],
+                    *color).to_vec()
+      })
+      .collect()
+  }
+}
@@ -1,39 +1,75 @@
-use std::collections::HashMap;
-
 use cgmath::Point2;
-use rusttype::FontCollection;
-use specs::{ReadStorage, WriteStorage};
+use crossbeam_channel as channel;
+use rusttype::{Font, gpu_cache::Cache, PositionedGlyph};
+use specs::{Read, ReadStorage, Write, WriteStorage};
 
-use crate::character::CharacterDrawable;
-use crate::gfx_app::ColorFormat;
-use crate::gfx_app::DepthFormat;
-use crate::graphics::{mesh::RectangularTexturedMesh};
-use crate::graphics::texture::{text_texture, Texture};
-use crate::shaders::{Position, text_pipeline};
-use crate::graphics::mesh::Geometry;
+use crate::assets::AssetManager;
+use crate::character::{CharacterDrawable, controls::CharacterInputState};
+use crate::error::{GameError, GameResult};
+use crate::game::constants::{CHARACTER_MAX_HEALTH, COMBO_TEXT_COLOR, GLYPH_CACHE_SIZE, HUD_TEXT_COLOR, MAGAZINE_CAPACITY, MAX_TEXT_GLYPHS,
+                             MINIMAP_HOUSE_COLOR, MINIMAP_PICKUP_COLOR, MINIMAP_PLAYER_COLOR, MINIMAP_ZOMBIE_COLOR};
+use crate::gfx_app::{ColorFormat, DepthFormat};
+use crate::graphics::{camera::CameraInputState, coords_to_tile, DeltaTime, dimensions::Dimensions, orientation::Stance};
+use crate::hud::combat_feed::CombatEvents;
+use crate::graphics::render_target::{RenderTargets, TargetViews};
+use crate::graphics::shader::{create_pipeline_simple, reload_pipeline, ShaderProgram};
+use crate::graphics::texture::create_dynamic_texture;
+use crate::hud::font::{layout_text, load_font};
+use crate::hud::layout::{Anchor, ScreenLayout};
+use crate::hud::minimap::Minimap;
+use crate::score::Score;
+use crate::shaders::{ColorVertex, text_pipeline};
+use crate::terrain_object::{TerrainTexture, terrain_objects::TerrainObjects};
+use crate::zombie::zombies::Zombies;
 
+pub mod combat_feed;
 pub mod font;
+mod font_test;
 pub mod hud_objects;
+pub mod layout;
+mod layout_test;
+pub mod minimap;
+pub mod ui;
+
+const SHADER: ShaderProgram = ShaderProgram::new("text");
 
-const SHADER_VERT: &[u8] = include_bytes!("../shaders/text.v.glsl");
-const SHADER_FRAG: &[u8] = include_bytes!("../shaders/text.f.glsl");
+#[derive(Clone, Copy, PartialEq)]
+pub enum TextAlignment {
+  Left,
+  Center,
+  Right,
+}
 
 pub struct TextDrawable {
   text: String,
-  position: Position,
+  anchor: Anchor,
+  offset: Point2<f32>,
+  font_size: f32,
+  color: [f32; 4],
 }
 
-impl<'a> TextDrawable {
-  pub fn new(text: &str, position: Position) -> TextDrawable {
+impl TextDrawable {
+  pub fn new(text: &str, anchor: Anchor, offset: Point2<f32>, font_size: f32, color: [f32; 4]) -> TextDrawable {
     TextDrawable {
       text: text.to_string(),
-      position,
+      anchor,
+      offset,
+      font_size,
+      color,
     }
   }
 
   pub fn update(&mut self, new_text: String) {
     self.text = new_text;
   }
+
+  pub fn set_color(&mut self, color: [f32; 4]) {
+    self.color = color;
+  }
+
+  pub fn set_offset(&mut self, offset: Point2<f32>) {
+    self.offset = offset;
+  }
 }
 
 impl specs::prelude::Component for TextDrawable {
@@ -42,77 +78,219 @@ impl specs::prelude::Component for TextDrawable {
 
 pub struct TextDrawSystem<R: gfx::Resources> {
   bundle: gfx::pso::bundle::Bundle<R, text_pipeline::Data<R>>,
-  texture_cache: HashMap<String, Texture<R>>,
-  pub current_text: String,
+  font: Font<'static>,
+  glyph_cache: Cache<'static>,
+  glyph_atlas: gfx::handle::Texture<R, gfx::format::R8_G8_B8_A8>,
 }
 
 impl<R: gfx::Resources> TextDrawSystem<R> {
   pub fn new<F>(factory: &mut F,
-                texts: &[&str],
-                current_text: &str,
                 rtv: gfx::handle::RenderTargetView<R, ColorFormat>,
-                dsv: gfx::handle::DepthStencilView<R, DepthFormat>) -> TextDrawSystem<R>
+                dsv: gfx::handle::DepthStencilView<R, DepthFormat>,
+                assets: &AssetManager) -> GameResult<TextDrawSystem<R>>
     where F: gfx::Factory<R> {
     use gfx::traits::FactoryExt;
 
-    let font_bytes = &include_bytes!("../../assets/DejaVuSans.ttf")[..];
-    let font = FontCollection::from_bytes(font_bytes as &[u8])
-      .unwrap_or_else(|e| panic!("Font loading error: {}", e))
-      .into_font().unwrap_or_else(|e| panic!("into_font error: {}", e));
-
-    let mut texture_cache: HashMap<String, Texture<R>> = HashMap::new();
-
-    text_texture(factory, &font, texts, &mut texture_cache);
+    let (glyph_atlas, glyph_atlas_view) =
+      create_dynamic_texture(factory, Point2::new(GLYPH_CACHE_SIZE, GLYPH_CACHE_SIZE));
+    let glyph_cache = Cache::builder()
+      .dimensions(GLYPH_CACHE_SIZE as u32, GLYPH_CACHE_SIZE as u32)
+      .build();
 
-    let pso = factory.create_pipeline_simple(SHADER_VERT, SHADER_FRAG, text_pipeline::new())
-      .expect("HUD shader loading error");
+    let vertex_buffer = factory
+      .create_buffer(MAX_TEXT_GLYPHS * 6, gfx::buffer::Role::Vertex, gfx::memory::Usage::Dynamic, gfx::memory::Bind::empty())
+      .expect("HUD vertex buffer creation error");
 
-    let texture = texture_cache[current_text].clone();
-
-    let rect_mesh = RectangularTexturedMesh::new(factory, texture, Geometry::Rectangle, Point2::new(1.0, 1.0), None, None, None);
+    let pso = create_pipeline_simple(factory, &SHADER.load(assets)?, text_pipeline::new())
+      .map_err(GameError::Shader)?;
 
     let pipeline_data = text_pipeline::Data {
-      vbuf: rect_mesh.mesh.vertex_buffer,
-      position_cb: factory.create_constant_buffer(1),
-      text_sheet: (rect_mesh.mesh.texture.raw, factory.create_sampler_linear()),
+      vbuf: vertex_buffer,
+      text_sheet: (glyph_atlas_view, factory.create_sampler_linear()),
       out_color: rtv,
       out_depth: dsv,
     };
 
-    TextDrawSystem {
-      bundle: gfx::Bundle::new(rect_mesh.mesh.slice, pso, pipeline_data),
-      texture_cache,
-      current_text: current_text.to_string(),
-    }
+    let slice = gfx::Slice {
+      start: 0,
+      end: 0,
+      base_vertex: 0,
+      instances: None,
+      buffer: gfx::IndexBuffer::Auto,
+    };
+
+    Ok(TextDrawSystem {
+      bundle: gfx::Bundle::new(slice, pso, pipeline_data),
+      font: load_font(assets)?,
+      glyph_cache,
+      glyph_atlas,
+    })
+  }
+
+  pub fn reload_shaders<F>(&mut self, factory: &mut F, assets: &AssetManager) -> Result<(), String>
+    where F: gfx::Factory<R> {
+    reload_pipeline(factory, assets, SHADER, text_pipeline::new(), &mut self.bundle.pso)
   }
 
-  pub fn draw<C>(&mut self,
-                 drawable: &TextDrawable,
-                 encoder: &mut gfx::Encoder<R, C>)
-    where C: gfx::CommandBuffer<R> {
-    encoder.update_constant_buffer(&self.bundle.data.position_cb, &drawable.position);
-    if self.current_text.trim() != drawable.text.trim() {
-      self.current_text = drawable.text.to_owned();
-      self.bundle.data.text_sheet.0 = self.texture_cache[&drawable.text].raw.clone();
+  fn layout(&self, drawable: &TextDrawable, layout: &ScreenLayout) -> Vec<PositionedGlyph<'static>> {
+    let font_size = drawable.font_size * layout.scale;
+    let position = layout.position(drawable.anchor, drawable.offset);
+    let top_left = Point2::new(position.x, position.y - drawable.anchor.pivot().y * font_size);
+    layout_text(&self.font, &drawable.text, font_size, top_left, drawable.anchor.alignment())
+  }
+
+  pub fn draw<'b, C, I>(&mut self,
+                        drawables: I,
+                        layout: &ScreenLayout,
+                        encoder: &mut gfx::Encoder<R, C>)
+    where C: gfx::CommandBuffer<R>,
+          I: IntoIterator<Item=&'b TextDrawable> {
+    let glyphs = drawables.into_iter()
+      .flat_map(|d| self.layout(d, layout).into_iter().map(move |g| (g, d.color)))
+      .take(MAX_TEXT_GLYPHS)
+      .collect::<Vec<_>>();
+
+    for (glyph, _) in &glyphs {
+      self.glyph_cache.queue_glyph(0, glyph.clone());
     }
+
+    let glyph_atlas = &self.glyph_atlas;
+    let cache_result = self.glyph_cache.cache_queued(|rect, data| {
+      let texels = data.iter().map(|v| [255, 255, 255, *v]).collect::<Vec<[u8; 4]>>();
+      let image_info = gfx::texture::NewImageInfo {
+        xoffset: rect.min.x as u16,
+        yoffset: rect.min.y as u16,
+        zoffset: 0,
+        width: rect.width() as u16,
+        height: rect.height() as u16,
+        depth: 1,
+        format: (),
+        mipmap: 0,
+      };
+      encoder.update_texture::<gfx::format::R8_G8_B8_A8, ColorFormat>(glyph_atlas, None, image_info, &texels)
+        .unwrap_or_else(|e| panic!("Glyph atlas update error {:?}", e));
+    });
+    if let Err(e) = cache_result {
+      println!("Glyph cache error {:?}", e);
+      return;
+    }
+
+    let mut vertices: Vec<ColorVertex> = Vec::with_capacity(glyphs.len() * 6);
+    for (glyph, color) in &glyphs {
+      if let Ok(Some((uv, screen))) = self.glyph_cache.rect_for(0, glyph) {
+        vertices.extend_from_slice(&layout.quad(Point2::new(screen.min.x as f32, screen.min.y as f32),
+                                                Point2::new(screen.max.x as f32, screen.max.y as f32),
+                                                [uv.min.x, uv.min.y],
+                                                [uv.max.x, uv.max.y],
+                                                *color));
+      }
+    }
+
+    encoder.update_buffer(&self.bundle.data.vbuf, &vertices, 0)
+      .unwrap_or_else(|e| panic!("HUD vertex buffer update error {:?}", e));
+    self.bundle.slice.end = vertices.len() as u32;
     self.bundle.encode(encoder);
   }
 }
 
-pub struct PreDrawSystem;
+impl<R: gfx::Resources> RenderTargets<R, ColorFormat> for TextDrawSystem<R> {
+  fn targets(&mut self) -> Vec<TargetViews<'_, R, ColorFormat>> {
+    vec![(&mut self.bundle.data.out_color, &mut self.bundle.data.out_depth)]
+  }
+}
+
+fn game_over_texts(score: &Score) -> Vec<String> {
+  let mut texts = vec![
+    "Game over".to_string(),
+    format!("Score {}", score.points),
+    format!("Kills {} Accuracy {}%", score.kills, score.accuracy()),
+  ];
+  match score.high_scores {
+    Some(ref high_scores) => texts.extend(high_scores.iter()
+      .enumerate()
+      .map(|(idx, e)| format!("{}. {} {}", idx + 1, e.name, e.points))),
+    None => texts.push(format!("Name: {}_", score.player_name)),
+  }
+  texts
+}
+
+fn update_minimap(minimap: &mut Minimap, ci: &CharacterInputState, zombies: &Zombies, terrain_objects: &TerrainObjects) {
+  minimap.clear();
+  for obj in &terrain_objects.objects {
+    match obj.object_type {
+      TerrainTexture::Ammo => minimap.add_dot(coords_to_tile(ci.movement - obj.position), MINIMAP_PICKUP_COLOR),
+      TerrainTexture::House => minimap.add_dot(coords_to_tile(ci.movement - obj.position), MINIMAP_HOUSE_COLOR),
+      TerrainTexture::Tree => (),
+    }
+  }
+  for z in zombies.zombies.iter().filter(|z| z.stance != Stance::NormalDeath && z.stance != Stance::CriticalDeath) {
+    minimap.add_dot(coords_to_tile(ci.movement - z.position), MINIMAP_ZOMBIE_COLOR);
+  }
+  minimap.add_dot(coords_to_tile(ci.movement), MINIMAP_PLAYER_COLOR);
+}
+
+pub enum HudControl {
+  ToggleMinimap,
+}
+
+pub struct PreDrawSystem {
+  queue: channel::Receiver<HudControl>,
+}
+
+impl PreDrawSystem {
+  pub fn new() -> (PreDrawSystem, channel::Sender<HudControl>) {
+    let (tx, rx) = channel::unbounded();
+    (PreDrawSystem {
+      queue: rx,
+    }, tx)
+  }
+}
 
 impl<'a> specs::prelude::System<'a> for PreDrawSystem {
   type SystemData = (ReadStorage<'a, CharacterDrawable>,
-                     WriteStorage<'a, hud_objects::HudObjects>);
+                     ReadStorage<'a, CharacterInputState>,
+                     ReadStorage<'a, Zombies>,
+                     ReadStorage<'a, TerrainObjects>,
+                     ReadStorage<'a, CameraInputState>,
+                     ReadStorage<'a, Score>,
+                     WriteStorage<'a, hud_objects::HudObjects>,
+                     Write<'a, CombatEvents>,
+                     Read<'a, DeltaTime>,
+                     Read<'a, Dimensions>);
 
-  fn run(&mut self, (character_drawable, mut hud_objects): Self::SystemData) {
+  fn run(&mut self, (character_drawable, character_input, zombies, terrain_objects, camera_input, score, mut hud_objects, mut combat_events, d, dim): Self::SystemData) {
     use specs::join::Join;
 
-    for (cd, huds) in (&character_drawable, &mut hud_objects).join() {
+    for (cd, ci, zs, to, camera, s, huds) in
+      (&character_drawable, &character_input, &zombies, &terrain_objects, &camera_input, &score, &mut hud_objects).join() {
+      huds.combat_feed.handle_events(&mut combat_events);
+      huds.combat_feed.update(d.0, ci.movement, &dim.world_to_projection(camera), &ScreenLayout::new(&dim));
+
+      while let Ok(HudControl::ToggleMinimap) = self.queue.try_recv() {
+        huds.minimap.is_visible = !huds.minimap.is_visible;
+      }
+      if huds.minimap.is_visible {
+        update_minimap(&mut huds.minimap, ci, zs, to);
+      }
+
       let new_ammo_text = format!("Ammo {}", cd.stats.ammunition);
       let new_mag_text = format!("Magazines {}/2", cd.stats.magazines);
-      huds.objects[1].update(new_ammo_text);
-      huds.objects[2].update(new_mag_text);
+      let new_score_text = if s.combo > 1 {
+        format!("Score {} x{}", s.points, s.combo)
+      } else {
+        format!("Score {}", s.points)
+      };
+      huds.ammo_text.update(new_ammo_text);
+      huds.magazine_text.update(new_mag_text);
+      huds.score_text.update(new_score_text);
+      huds.score_text.set_color(if s.combo > 1 { COMBO_TEXT_COLOR } else { HUD_TEXT_COLOR });
+      huds.ammo_bar.set_value(cd.stats.ammunition as f32 / MAGAZINE_CAPACITY as f32);
+      huds.health_bar.set_value(cd.stats.health / CHARACTER_MAX_HEALTH);
+      if cd.stance == Stance::NormalDeath {
+        huds.show_messages(&game_over_texts(s));
+      } else {
+        huds.messages.clear();
+      }
     }
   }
 }
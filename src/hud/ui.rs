@@ -0,0 +1,174 @@
+use cgmath::Point2;
+use gfx::format::R8_G8_B8_A8;
+use image::RgbaImage;
+
+use crate::assets::AssetManager;
+use crate::error::{GameError, GameResult};
+use crate::game::constants::{AMMO_IMAGE_PATH, BAR_BACKGROUND_COLOR, MAX_UI_QUADS};
+use crate::gfx_app::{ColorFormat, DepthFormat};
+use crate::graphics::render_target::{RenderTargets, TargetViews};
+use crate::graphics::shader::{create_pipeline_simple, reload_pipeline, ShaderProgram};
+use crate::graphics::texture::{create_updatable_texture, load_texture, solid_texture, update_texture};
+use crate::hud::layout::{ScreenLayout, UiRect};
+use crate::hud::minimap::{Minimap, minimap_image};
+use crate::shaders::{ColorVertex, ui_pipeline};
+use crate::terrain::tile_map::Terrain;
+
+const SHADER: ShaderProgram = ShaderProgram::new("ui");
+
+pub struct UiBar {
+  rect: UiRect,
+  color: [f32; 4],
+  value: f32,
+}
+
+impl UiBar {
+  pub fn new(rect: UiRect, color: [f32; 4]) -> UiBar {
+    UiBar {
+      rect,
+      color,
+      value: 1.0,
+    }
+  }
+
+  pub fn set_value(&mut self, value: f32) {
+    self.value = value.max(0.0).min(1.0);
+  }
+}
+
+pub struct UiIcon {
+  rect: UiRect,
+}
+
+impl UiIcon {
+  pub fn new(rect: UiRect) -> UiIcon {
+    UiIcon {
+      rect,
+    }
+  }
+}
+
+fn ui_bundle<R, F>(factory: &mut F,
+                   pso: gfx::PipelineState<R, ui_pipeline::Meta>,
+                   texture: gfx::handle::ShaderResourceView<R, [f32; 4]>,
+                   rtv: gfx::handle::RenderTargetView<R, ColorFormat>,
+                   dsv: gfx::handle::DepthStencilView<R, DepthFormat>) -> gfx::pso::bundle::Bundle<R, ui_pipeline::Data<R>>
+  where R: gfx::Resources, F: gfx::Factory<R> {
+  use gfx::traits::FactoryExt;
+
+  let vertex_buffer = factory
+    .create_buffer(MAX_UI_QUADS * 6, gfx::buffer::Role::Vertex, gfx::memory::Usage::Dynamic, gfx::memory::Bind::empty())
+    .expect("UI vertex buffer creation error");
+
+  let pipeline_data = ui_pipeline::Data {
+    vbuf: vertex_buffer,
+    ui_sheet: (texture, factory.create_sampler_linear()),
+    out_color: rtv,
+    out_depth: dsv,
+  };
+
+  let slice = gfx::Slice {
+    start: 0,
+    end: 0,
+    base_vertex: 0,
+    instances: None,
+    buffer: gfx::IndexBuffer::Auto,
+  };
+
+  gfx::Bundle::new(slice, pso, pipeline_data)
+}
+
+fn encode_quads<R, C>(bundle: &mut gfx::pso::bundle::Bundle<R, ui_pipeline::Data<R>>,
+                      vertices: &[ColorVertex],
+                      encoder: &mut gfx::Encoder<R, C>)
+  where R: gfx::Resources, C: gfx::CommandBuffer<R> {
+  let vertices = &vertices[..vertices.len().min(MAX_UI_QUADS * 6)];
+  encoder.update_buffer(&bundle.data.vbuf, vertices, 0)
+    .unwrap_or_else(|e| panic!("UI vertex buffer update error {:?}", e));
+  bundle.slice.end = vertices.len() as u32;
+  bundle.encode(encoder);
+}
+
+pub struct UiDrawSystem<R: gfx::Resources> {
+  solid_bundle: gfx::pso::bundle::Bundle<R, ui_pipeline::Data<R>>,
+  icon_bundle: gfx::pso::bundle::Bundle<R, ui_pipeline::Data<R>>,
+  minimap_bundle: gfx::pso::bundle::Bundle<R, ui_pipeline::Data<R>>,
+  minimap_texture: gfx::handle::Texture<R, R8_G8_B8_A8>,
+}
+
+impl<R: gfx::Resources> UiDrawSystem<R> {
+  pub fn new<F>(factory: &mut F,
+                rtv: gfx::handle::RenderTargetView<R, ColorFormat>,
+                dsv: gfx::handle::DepthStencilView<R, DepthFormat>,
+                assets: &AssetManager,
+                terrain: &Terrain,
+                tile_sheet: &RgbaImage) -> GameResult<UiDrawSystem<R>>
+    where F: gfx::Factory<R> {
+    let solid = solid_texture(factory, [255, 255, 255, 255]);
+    let icons = load_texture(factory, &assets.load_image(AMMO_IMAGE_PATH)?);
+    let (minimap_texture, minimap) = create_updatable_texture(factory, &minimap_image(terrain, tile_sheet));
+    let pso = create_pipeline_simple(factory, &SHADER.load(assets)?, ui_pipeline::new())
+      .map_err(GameError::Shader)?;
+
+    Ok(UiDrawSystem {
+      solid_bundle: ui_bundle(factory, pso.clone(), solid, rtv.clone(), dsv.clone()),
+      icon_bundle: ui_bundle(factory, pso.clone(), icons, rtv.clone(), dsv.clone()),
+      minimap_bundle: ui_bundle(factory, pso, minimap, rtv, dsv),
+      minimap_texture,
+    })
+  }
+
+  pub fn reload_shaders<F>(&mut self, factory: &mut F, assets: &AssetManager) -> Result<(), String>
+    where F: gfx::Factory<R> {
+    reload_pipeline(factory, assets, SHADER, ui_pipeline::new(), &mut self.solid_bundle.pso)?;
+    self.icon_bundle.pso = self.solid_bundle.pso.clone();
+    self.minimap_bundle.pso = self.solid_bundle.pso.clone();
+    Ok(())
+  }
+
+  pub fn reload_minimap<C>(&mut self, terrain: &Terrain, tile_sheet: &RgbaImage, encoder: &mut gfx::Encoder<R, C>) -> Result<(), String>
+    where C: gfx::CommandBuffer<R> {
+    update_texture(encoder, &self.minimap_texture, &minimap_image(terrain, tile_sheet))
+  }
+
+  pub fn draw<'b, C, I>(&mut self,
+                        bars: I,
+                        icons: &[UiIcon],
+                        minimap: &Minimap,
+                        layout: &ScreenLayout,
+                        encoder: &mut gfx::Encoder<R, C>)
+    where C: gfx::CommandBuffer<R>,
+          I: IntoIterator<Item=&'b UiBar> {
+    let mut solid_vertices = Vec::new();
+    for bar in bars {
+      let (min, max) = layout.resolve(&bar.rect);
+      let fill_max = Point2::new(min.x + (max.x - min.x) * bar.value, max.y);
+      solid_vertices.extend_from_slice(&layout.quad(min, max, [0.0, 0.0], [1.0, 1.0], BAR_BACKGROUND_COLOR));
+      solid_vertices.extend_from_slice(&layout.quad(min, fill_max, [0.0, 0.0], [1.0, 1.0], bar.color));
+    }
+    if minimap.is_visible {
+      solid_vertices.extend(minimap.dot_quads(layout));
+    }
+
+    let icon_vertices = icons.iter()
+      .flat_map(|icon| {
+        let (min, max) = layout.resolve(&icon.rect);
+        layout.quad(min, max, [0.0, 0.0], [1.0, 1.0], [1.0, 1.0, 1.0, 1.0]).to_vec()
+      })
+      .collect::<Vec<ColorVertex>>();
+
+    if minimap.is_visible {
+      encode_quads(&mut self.minimap_bundle, &minimap.map_quad(layout), encoder);
+    }
+    encode_quads(&mut self.solid_bundle, &solid_vertices, encoder);
+    encode_quads(&mut self.icon_bundle, &icon_vertices, encoder);
+  }
+}
+
+impl<R: gfx::Resources> RenderTargets<R, ColorFormat> for UiDrawSystem<R> {
+  fn targets(&mut self) -> Vec<TargetViews<'_, R, ColorFormat>> {
+    vec![(&mut self.solid_bundle.data.out_color, &mut self.solid_bundle.data.out_depth),
+         (&mut self.icon_bundle.data.out_color, &mut self.icon_bundle.data.out_depth),
+         (&mut self.minimap_bundle.data.out_color, &mut self.minimap_bundle.data.out_depth)]
+  }
+}
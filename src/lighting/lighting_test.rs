@@ -0,0 +1,94 @@
+#[test]
+fn ambient_light_test() {
+  use crate::game::constants::AMBIENT_LIGHT;
+  use crate::graphics::sun_height;
+  use crate::lighting::{ambient_light, daylight};
+
+  let night = (0..89).find(|&t| sun_height(t) <= 0.0).expect("Day cycle should have a night");
+  let noon = (0..89).max_by(|&a, &b| sun_height(a).partial_cmp(&sun_height(b)).unwrap()).unwrap();
+  assert_eq!(0.0, daylight(night));
+  assert_eq!(AMBIENT_LIGHT[0], ambient_light(night)[0]);
+  assert!(ambient_light(noon)[0] > ambient_light(night)[0], "Sun should brighten the ambient light");
+}
+
+#[test]
+fn orientation_to_angle_test() {
+  use crate::graphics::{orientation::Orientation, orientation_to_angle, orientation_to_direction};
+
+  assert_eq!(Some(0.0), orientation_to_angle(Orientation::Right));
+  assert_eq!(Some(135.0), orientation_to_angle(Orientation::UpLeft));
+  assert_eq!(None, orientation_to_angle(Orientation::Normal));
+  for &angle in &[0.0, 45.0, 90.0, 135.0, 180.0, 225.0, 270.0, 315.0] {
+    assert!(orientation_to_angle(orientation_to_direction(angle)) == Some(angle));
+  }
+}
+
+#[test]
+fn flashlight_follows_aim_test() {
+  use cgmath::Point2;
+  use crate::lighting::{LightShape, Lights};
+  use crate::shaders::Position;
+
+  let mut lights = Lights::new(vec![]);
+  lights.aim(Point2::new(0.0, 1.0));
+  let collected = lights.collect(Some(Position::origin()), Position::origin(), 0.0);
+  assert_eq!(1, collected.len());
+  match collected[0].shape {
+    LightShape::Cone(direction, _) => assert_eq!(Point2::new(0.0, 1.0), direction, "Flashlight should follow the aim"),
+    LightShape::Point => panic!("Flashlight should be a cone"),
+  }
+
+  assert!(lights.collect(None, Position::origin(), 0.0).is_empty(), "Dead player should have no flashlight");
+}
+
+#[test]
+fn mouse_aim_test() {
+  use cgmath::Point2;
+  use crate::gfx_app::mouse_controls::MouseInputState;
+  use crate::graphics::{aim_from_center, dimensions::Dimensions};
+
+  let dim = Dimensions::new(800.0, 600.0, 2.0);
+  let mut mouse_input = MouseInputState::new();
+  assert_eq!(None, aim_from_center(&mouse_input, &dim));
+
+  mouse_input.left_click_point = Some(Point2::new(1100.0, 200.0));
+  let aim = aim_from_center(&mouse_input, &dim).unwrap();
+  assert!((aim.x - 0.6).abs() < 0.001 && (aim.y - 0.8).abs() < 0.001, "Aim should point from the window center to the mouse, y up: {:?}", aim);
+}
+
+#[test]
+fn muzzle_light_expiry_test() {
+  use crate::game::constants::MUZZLE_LIGHT_DURATION;
+  use crate::lighting::Lights;
+  use crate::shaders::Position;
+
+  let mut lights = Lights::new(vec![]);
+  lights.flash(Position::new(10.0, 10.0));
+  lights.update(MUZZLE_LIGHT_DURATION / 2.0);
+  let collected = lights.collect(None, Position::origin(), 0.0);
+  assert_eq!(1, collected.len());
+  assert_eq!(Position::new(-10.0, -10.0), collected[0].position);
+
+  lights.update(MUZZLE_LIGHT_DURATION);
+  assert!(lights.collect(None, Position::origin(), 0.0).is_empty(), "Muzzle light should expire");
+}
+
+#[test]
+fn light_list_budget_test() {
+  use crate::game::constants::{ASPECT_RATIO, MAX_LIGHTS, VIEW_DISTANCE};
+  use crate::graphics::dimensions::{get_projection, get_view_matrix};
+  use crate::lighting::Lights;
+  use crate::shaders::Position;
+
+  let projection = get_projection(get_view_matrix(VIEW_DISTANCE), ASPECT_RATIO);
+  let mut lamps = vec![Position::new(100_000.0, 0.0)];
+  lamps.extend((0..MAX_LIGHTS * 2).map(|i| Position::new(i as f32, 0.0)));
+  let mut lights = Lights::new(lamps);
+  let collected = lights.collect(None, Position::origin(), 0.0);
+  lights.build(&collected, &projection, [0.1; 4], ASPECT_RATIO);
+
+  assert_eq!(MAX_LIGHTS, lights.data.len());
+  assert_eq!(MAX_LIGHTS as i32, lights.locals.count);
+  assert!(lights.data.iter().all(|light| light.position[3] == -1.0), "Lamps should be point lights");
+  assert!(lights.data.iter().all(|light| light.position[0].abs() < 1.0), "Off-screen lamp should be culled");
+}
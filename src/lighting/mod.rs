@@ -0,0 +1,223 @@
+use cgmath::Point2;
+use specs::prelude::{Read, ReadStorage, Write, WriteStorage};
+
+use crate::character::{CharacterDrawable, controls::CharacterInputState};
+use crate::game::constants::{AMBIENT_LIGHT, ASPECT_RATIO, FLASHLIGHT_ANGLE, FLASHLIGHT_COLOR, FLASHLIGHT_RADIUS, LAMP_COLOR, LAMP_RADIUS, MAX_LIGHTS,
+                             MUZZLE_LIGHT_COLOR, MUZZLE_LIGHT_DURATION, MUZZLE_LIGHT_RADIUS, SUN_COLOR};
+use crate::gfx_app::mouse_controls::MouseInputState;
+use crate::graphics::{aim_from_center, camera::CameraInputState, DeltaTime, dimensions::{Dimensions, world_to_clip_space}, direction_movement,
+                      GameTime, orientation::Stance, orientation_to_angle, sun_height};
+use crate::shaders::{LightData, LightLocals, Position, Projection};
+
+mod lighting_test;
+
+pub fn daylight(game_time: u64) -> f32 {
+  sun_height(game_time).max(0.0)
+}
+
+pub fn ambient_light(game_time: u64) -> [f32; 4] {
+  let daylight = daylight(game_time);
+  [AMBIENT_LIGHT[0] + SUN_COLOR[0] * daylight, AMBIENT_LIGHT[1] + SUN_COLOR[1] * daylight, AMBIENT_LIGHT[2] + SUN_COLOR[2] * daylight, 1.0]
+}
+
+fn scale(color: [f32; 3], intensity: f32) -> [f32; 3] {
+  [color[0] * intensity, color[1] * intensity, color[2] * intensity]
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightShape {
+  Point,
+  Cone(Point2<f32>, f32),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Light {
+  pub position: Position,
+  pub radius: f32,
+  pub color: [f32; 3],
+  pub shape: LightShape,
+}
+
+impl Light {
+  pub fn point(position: Position, radius: f32, color: [f32; 3]) -> Light {
+    Light {
+      position,
+      radius,
+      color,
+      shape: LightShape::Point,
+    }
+  }
+
+  pub fn cone(position: Position, radius: f32, color: [f32; 3], direction: Point2<f32>, angle: f32) -> Light {
+    Light {
+      position,
+      radius,
+      color,
+      shape: LightShape::Cone(direction, angle),
+    }
+  }
+
+  pub fn data(&self, projection: &Projection) -> LightData {
+    let center = world_to_clip_space(projection, self.position);
+    let edge = world_to_clip_space(projection, self.position + Position::new(0.0, self.radius));
+    let (cutoff, direction) = match self.shape {
+      LightShape::Point => (-1.0, Point2::new(0.0, 0.0)),
+      LightShape::Cone(direction, angle) => (angle.to_radians().cos(), direction),
+    };
+    LightData {
+      position: [center.x, center.y, (edge.y - center.y).abs(), cutoff],
+      color: [self.color[0], self.color[1], self.color[2], 1.0],
+      direction: [direction.x, direction.y, 0.0, 0.0],
+    }
+  }
+}
+
+fn is_on_screen(light: &LightData) -> bool {
+  let [x, y, radius, _] = light.position;
+  x.abs() - radius <= 1.0 && y.abs() - radius <= 1.0
+}
+
+#[derive(Default)]
+pub struct LightEvents {
+  pub flashes: Vec<Position>,
+}
+
+impl LightEvents {
+  pub fn flash(&mut self, map_position: Position) {
+    self.flashes.push(map_position);
+  }
+}
+
+struct Flash {
+  map_position: Position,
+  age: f32,
+}
+
+pub struct Lights {
+  lamps: Vec<Position>,
+  flashes: Vec<Flash>,
+  aim: Point2<f32>,
+  pub data: Vec<LightData>,
+  pub locals: LightLocals,
+}
+
+impl Lights {
+  pub fn new(lamps: Vec<Position>) -> Lights {
+    Lights {
+      lamps,
+      flashes: Vec::new(),
+      aim: Point2::new(1.0, 0.0),
+      data: Vec::new(),
+      locals: LightLocals {
+        ambient: ambient_light(0),
+        count: 0,
+        aspect: ASPECT_RATIO,
+      },
+    }
+  }
+
+  pub fn flash(&mut self, map_position: Position) {
+    self.flashes.push(Flash { map_position, age: 0.0 });
+  }
+
+  pub fn aim(&mut self, direction: Point2<f32>) {
+    self.aim = direction;
+  }
+
+  pub fn update(&mut self, delta: f32) {
+    for flash in &mut self.flashes {
+      flash.age += delta;
+    }
+    self.flashes.retain(|flash| flash.age < MUZZLE_LIGHT_DURATION);
+  }
+
+  pub fn collect(&self, player: Option<Position>, movement: Position, daylight: f32) -> Vec<Light> {
+    let darkness = 1.0 - daylight;
+    let mut lights = Vec::new();
+    if let Some(player) = player {
+      lights.push(Light::cone(player, FLASHLIGHT_RADIUS, scale(FLASHLIGHT_COLOR, darkness), self.aim, FLASHLIGHT_ANGLE));
+    }
+    lights.extend(self.flashes.iter().map(|flash| {
+      let fade = 1.0 - flash.age / MUZZLE_LIGHT_DURATION;
+      Light::point(movement - flash.map_position, MUZZLE_LIGHT_RADIUS, scale(MUZZLE_LIGHT_COLOR, fade))
+    }));
+    lights.extend(self.lamps.iter().map(|&lamp| Light::point(movement - lamp, LAMP_RADIUS, scale(LAMP_COLOR, darkness))));
+    lights
+  }
+
+  pub fn build(&mut self, lights: &[Light], projection: &Projection, ambient: [f32; 4], aspect: f32) {
+    self.data = lights.iter()
+      .map(|light| light.data(projection))
+      .filter(is_on_screen)
+      .take(MAX_LIGHTS)
+      .collect();
+    self.locals = LightLocals {
+      ambient,
+      count: self.data.len() as i32,
+      aspect,
+    };
+  }
+}
+
+impl specs::prelude::Component for Lights {
+  type Storage = specs::storage::VecStorage<Lights>;
+}
+
+pub struct LightBuffers<R: gfx::Resources> {
+  pub lights: gfx::handle::Buffer<R, LightData>,
+  pub locals: gfx::handle::Buffer<R, LightLocals>,
+}
+
+impl<R: gfx::Resources> LightBuffers<R> {
+  pub fn new<F>(factory: &mut F) -> LightBuffers<R>
+    where F: gfx::Factory<R> {
+    use gfx::traits::FactoryExt;
+
+    LightBuffers {
+      lights: factory.create_constant_buffer(MAX_LIGHTS),
+      locals: factory.create_constant_buffer(1),
+    }
+  }
+
+  pub fn upload<C>(&self, lights: &Lights, encoder: &mut gfx::Encoder<R, C>)
+    where C: gfx::CommandBuffer<R> {
+    if !lights.data.is_empty() {
+      encoder.update_buffer(&self.lights, &lights.data, 0)
+        .unwrap_or_else(|e| panic!("Light buffer update error {:?}", e));
+    }
+    encoder.update_constant_buffer(&self.locals, &lights.locals);
+  }
+}
+
+pub struct LightSystem;
+
+impl<'a> specs::prelude::System<'a> for LightSystem {
+  type SystemData = (WriteStorage<'a, Lights>,
+                     ReadStorage<'a, CharacterDrawable>,
+                     ReadStorage<'a, CameraInputState>,
+                     ReadStorage<'a, CharacterInputState>,
+                     ReadStorage<'a, MouseInputState>,
+                     Write<'a, LightEvents>,
+                     Read<'a, DeltaTime>,
+                     Read<'a, GameTime>,
+                     Read<'a, Dimensions>);
+
+  fn run(&mut self, (mut lights, character, camera_input, character_input, mouse_input, mut light_events, d, gt, dim): Self::SystemData) {
+    use specs::join::Join;
+
+    for (l, c, camera, ci, mi) in (&mut lights, &character, &camera_input, &character_input, &mouse_input).join() {
+      for map_position in light_events.flashes.drain(..) {
+        l.flash(map_position);
+      }
+      l.update(d.0 as f32);
+      let aim = aim_from_center(mi, &dim).or_else(|| orientation_to_angle(c.orientation()).map(direction_movement));
+      if let Some(aim) = aim {
+        l.aim(aim);
+      }
+
+      let player = if c.stance == Stance::NormalDeath { None } else { Some(c.position) };
+      let collected = l.collect(player, ci.movement, daylight(gt.0));
+      l.build(&collected, &dim.world_to_projection(camera), ambient_light(gt.0), dim.window_width / dim.window_height);
+    }
+  }
+}
@@ -4,43 +4,96 @@ extern crate gfx;
 
 use getopts::Options;
 
-use crate::game::constants::{GAME_TITLE, GAME_VERSION};
+use crate::error::{GameError, GameResult};
+use crate::game::constants::{GAME_TITLE, GAME_VERSION, MSAA_LEVELS, SETTINGS_PATH};
+use crate::game::random_seed;
 use crate::gfx_app::GameOptions;
+use crate::settings::{Difficulty, load_settings, parse_resolution};
 
+mod animation;
+mod assets;
 mod audio;
 mod bullet;
+mod console;
 mod gfx_app;
 mod game;
 mod data;
+mod debug;
+mod error;
 mod critter;
 pub mod graphics;
 mod hud;
+mod lighting;
+mod particle;
 mod terrain_object;
 mod terrain_shape;
 mod terrain;
 mod character;
+mod save;
+mod score;
+mod settings;
 mod shaders;
 mod zombie;
 
 fn print_usage() {
-  println!("USAGE:\nhinterland [FLAGS]\n\nFLAGS:\n-h, --help\t\t\tPrints help information\n-v, --version\t\t\tPrints version information\n-w, --windowed_mode\t\tRun game in windowed mode");
+  println!("USAGE:\nhinterland [FLAGS]\n\nFLAGS:\n-h, --help\t\t\tPrints help information\n-v, --version\t\t\tPrints version information\n-w, --windowed_mode\t\tRun game in windowed mode\n-l, --load_game\t\t\tLoad quick save on start\n--hot-reload\t\t\tReload changed assets while running\n--headless\t\t\tRender with OSMesa without a window, needs --replay or --screenshot-after\n--no-vsync\t\t\tDisable vertical sync\n\nOPTIONS:\n-s, --seed <SEED>\t\tSeed for game randomness\n--record <FILE>\t\t\tRecord input to replay file\n--replay <FILE>\t\t\tReplay input from replay file\n--assets <DIR>\t\t\tAsset directory (default $HINTERLAND_ASSETS or assets)\n--resolution <WxH>\t\tWindowed mode resolution\n--msaa <SAMPLES>\t\tMultisampling level (0, 2, 4 or 8)\n--difficulty <LEVEL>\t\tDifficulty (easy, normal or hard)\n--screenshot-after <TICKS> <PATH>\tSave a PNG of the given tick and exit\n\nSettings are read from settings.json and can be changed in game with F10");
 }
 
 fn print_version() {
   println!("{} - {}", GAME_TITLE, GAME_VERSION)
 }
 
+fn exit_with_error(error: GameError, show_usage: bool) -> ! {
+  println!("{}", error);
+  if show_usage {
+    print_usage();
+  }
+  std::process::exit(1)
+}
+
+fn take_screenshot_path(args: &mut Vec<String>) -> Option<String> {
+  let index = args.iter().position(|a| a == "--screenshot-after").map(|i| i + 2)
+    .or_else(|| args.iter().position(|a| a.starts_with("--screenshot-after=")).map(|i| i + 1))?;
+  if index < args.len() && !args[index].starts_with('-') {
+    Some(args.remove(index))
+  } else {
+    None
+  }
+}
+
+#[cfg(target_os = "linux")]
+fn run_headless(game_opt: &GameOptions) -> GameResult<()> {
+  let mut window = gfx_app::headless::HeadlessContext::new(game_opt.clone())?;
+  gfx_app::init::run(&mut window, game_opt)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn run_headless(_game_opt: &GameOptions) -> GameResult<()> {
+  Err(GameError::Arguments("--headless is only supported on Linux".to_string()))
+}
+
 pub fn main() {
-  let args = std::env::args().collect::<Vec<String>>();
+  let mut args = std::env::args().collect::<Vec<String>>();
+  let screenshot_path = take_screenshot_path(&mut args);
   let mut opts = Options::new();
   opts.optflag("w", "windowed_mode", "Run game in windowed mode");
+  opts.optflag("l", "load_game", "Load quick save on start");
+  opts.optopt("s", "seed", "Seed for game randomness", "SEED");
+  opts.optopt("", "record", "Record input to replay file", "FILE");
+  opts.optopt("", "replay", "Replay input from replay file", "FILE");
+  opts.optopt("", "assets", "Asset directory", "DIR");
+  opts.optflag("", "hot-reload", "Reload changed assets while running");
+  opts.optopt("", "resolution", "Windowed mode resolution", "WxH");
+  opts.optflag("", "no-vsync", "Disable vertical sync");
+  opts.optopt("", "msaa", "Multisampling level", "SAMPLES");
+  opts.optopt("", "difficulty", "Difficulty", "LEVEL");
+  opts.optopt("", "screenshot-after", "Save a PNG of the given tick and exit", "TICKS");
+  opts.optflag("", "headless", "Render with OSMesa without a window");
   opts.optflag("h", "help", "Prints help information");
   opts.optflag("v", "version", "Prints version information");
 
-  let matches = match opts.parse(&args[1..]) {
-    Ok(matching_args) => { matching_args }
-    Err(err) => { panic!(err.to_string()) }
-  };
+  let matches = opts.parse(&args[1..])
+    .unwrap_or_else(|e| exit_with_error(GameError::Arguments(e.to_string()), true));
 
   if matches.opt_present("help") {
     print_usage();
@@ -52,7 +105,72 @@ pub fn main() {
     return;
   }
 
-  let game_opt = GameOptions::new(matches.opt_present("windowed_mode"));
-  let mut window = gfx_app::WindowContext::new(game_opt);
-  gfx_app::init::run(&mut window);
+  let seed = match matches.opt_str("seed") {
+    Some(seed) => seed.parse::<u64>()
+      .unwrap_or_else(|e| exit_with_error(GameError::Arguments(format!("Invalid seed {}: {}", seed, e)), true)),
+    None => {
+      let seed = random_seed();
+      if !matches.opt_present("replay") {
+        println!("Game seed {}", seed);
+      }
+      seed
+    }
+  };
+
+  let file_settings = load_settings(SETTINGS_PATH);
+  let mut settings = file_settings.clone();
+  if matches.opt_present("windowed_mode") {
+    settings.fullscreen = false;
+  }
+  if matches.opt_present("no-vsync") {
+    settings.vsync = false;
+  }
+  if let Some(resolution) = matches.opt_str("resolution") {
+    settings.resolution = parse_resolution(&resolution)
+      .unwrap_or_else(|| exit_with_error(GameError::Arguments(format!("Invalid resolution {}", resolution)), true));
+  }
+  if let Some(msaa) = matches.opt_str("msaa") {
+    settings.msaa = msaa.parse::<u16>().ok()
+      .filter(|msaa| MSAA_LEVELS.contains(msaa))
+      .unwrap_or_else(|| exit_with_error(GameError::Arguments(format!("Invalid MSAA level {}", msaa)), true));
+  }
+  if let Some(difficulty) = matches.opt_str("difficulty") {
+    settings.difficulty = Difficulty::from_name(&difficulty)
+      .unwrap_or_else(|| exit_with_error(GameError::Arguments(format!("Invalid difficulty {}", difficulty)), true));
+  }
+
+  let screenshot = match matches.opt_str("screenshot-after") {
+    Some(ticks) => {
+      let ticks = ticks.parse::<u64>()
+        .unwrap_or_else(|e| exit_with_error(GameError::Arguments(format!("Invalid screenshot tick {}: {}", ticks, e)), true));
+      match screenshot_path {
+        Some(path) => Some((ticks, path)),
+        None => exit_with_error(GameError::Arguments("--screenshot-after needs <TICKS> <PATH>".to_string()), true),
+      }
+    }
+    None => None,
+  };
+
+  let mut game_opt = GameOptions::new(settings,
+                                      file_settings,
+                                      matches.opt_present("load_game"),
+                                      seed,
+                                      matches.opt_str("record"),
+                                      matches.opt_str("replay"),
+                                      matches.opt_str("assets"),
+                                      matches.opt_present("hot-reload"));
+  game_opt.screenshot = screenshot;
+
+  let result = if matches.opt_present("headless") {
+    if game_opt.screenshot.is_none() && game_opt.replay.is_none() {
+      exit_with_error(GameError::Arguments("--headless needs --replay or --screenshot-after".to_string()), true);
+    }
+    run_headless(&game_opt)
+  } else {
+    let mut window = gfx_app::WindowContext::new(game_opt.clone());
+    gfx_app::init::run(&mut window, &game_opt)
+  };
+  if let Err(e) = result {
+    exit_with_error(e, false);
+  }
 }
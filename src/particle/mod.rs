@@ -0,0 +1,336 @@
+use cgmath::Point2;
+use specs::prelude::{Read, ReadStorage, Write, WriteStorage};
+
+use crate::assets::AssetManager;
+use crate::character::controls::CharacterInputState;
+use crate::error::{GameError, GameResult};
+use crate::game::{GameRng, get_rand_f32_from_range};
+use crate::game::constants::{ASPECT_RATIO, MAX_PARTICLES, VIEW_DISTANCE};
+use crate::gfx_app::{DepthFormat, HdrColorFormat};
+use crate::graphics::{camera::CameraInputState, DeltaTime, dimensions::{Dimensions, get_projection, get_view_matrix}, direction,
+                      direction_movement, mesh::PlainMesh, render_target::{RenderTargets, TargetViews},
+                      shader::{create_pipeline_simple, reload_pipeline, ShaderProgram}, texture::load_raw_texture};
+use crate::shaders::{particle_pipeline, ParticleInstance, Position, Projection};
+
+mod particle_test;
+
+const SHADER: ShaderProgram = ShaderProgram::new("particle");
+
+const SHEET_FRAME_SIZE: i32 = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EmitterKind {
+  MuzzleFlash,
+  Blood,
+  Dust,
+  ShellCasing,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ParticleFrame {
+  Soft = 0,
+  Square = 1,
+}
+
+struct Emitter {
+  count: usize,
+  speed: (f32, f32),
+  spread: f32,
+  lifetime: (f32, f32),
+  size: (f32, f32),
+  start_color: [f32; 4],
+  end_color: [f32; 4],
+  drag: f32,
+  frame: ParticleFrame,
+}
+
+fn emitter(kind: EmitterKind) -> Emitter {
+  match kind {
+    EmitterKind::MuzzleFlash => Emitter {
+      count: 6,
+      speed: (60.0, 160.0),
+      spread: 25.0,
+      lifetime: (0.05, 0.12),
+      size: (6.0, 2.0),
+      // Over-bright so the flash feeds the bloom pass
+      start_color: [3.0, 2.7, 1.5, 1.0],
+      end_color: [1.5, 0.6, 0.15, 0.0],
+      drag: 4.0,
+      frame: ParticleFrame::Soft,
+    },
+    EmitterKind::Blood => Emitter {
+      count: 12,
+      speed: (40.0, 180.0),
+      spread: 50.0,
+      lifetime: (0.3, 0.7),
+      size: (3.0, 1.5),
+      start_color: [0.6, 0.0, 0.0, 1.0],
+      end_color: [0.3, 0.0, 0.0, 0.0],
+      drag: 5.0,
+      frame: ParticleFrame::Soft,
+    },
+    EmitterKind::Dust => Emitter {
+      count: 10,
+      speed: (20.0, 90.0),
+      spread: 70.0,
+      lifetime: (0.4, 0.9),
+      size: (3.0, 7.0),
+      start_color: [0.6, 0.55, 0.45, 0.8],
+      end_color: [0.5, 0.45, 0.4, 0.0],
+      drag: 3.0,
+      frame: ParticleFrame::Soft,
+    },
+    EmitterKind::ShellCasing => Emitter {
+      count: 1,
+      speed: (80.0, 120.0),
+      spread: 30.0,
+      lifetime: (0.5, 0.7),
+      size: (1.5, 1.5),
+      start_color: [0.85, 0.65, 0.2, 1.0],
+      end_color: [0.6, 0.45, 0.15, 0.0],
+      drag: 6.0,
+      frame: ParticleFrame::Square,
+    },
+  }
+}
+
+pub struct ParticleEvent {
+  pub kind: EmitterKind,
+  pub map_position: Position,
+  pub direction: Point2<f32>,
+}
+
+#[derive(Default)]
+pub struct ParticleEvents {
+  pub events: Vec<ParticleEvent>,
+}
+
+impl ParticleEvents {
+  pub fn emit(&mut self, kind: EmitterKind, map_position: Position, direction: Point2<f32>) {
+    self.events.push(ParticleEvent { kind, map_position, direction });
+  }
+}
+
+fn lerp(start: f32, end: f32, t: f32) -> f32 {
+  start + (end - start) * t
+}
+
+struct Particle {
+  map_position: Position,
+  velocity: Point2<f32>,
+  age: f32,
+  lifetime: f32,
+  size: (f32, f32),
+  start_color: [f32; 4],
+  end_color: [f32; 4],
+  drag: f32,
+  frame: ParticleFrame,
+}
+
+impl Particle {
+  fn update(&mut self, delta: f32) {
+    self.age += delta;
+    self.map_position = self.map_position + Position::new(self.velocity.x * delta, self.velocity.y * delta);
+    let damping = (1.0 - self.drag * delta).max(0.0);
+    self.velocity = Point2::new(self.velocity.x * damping, self.velocity.y * damping);
+  }
+
+  fn is_alive(&self) -> bool {
+    self.age < self.lifetime
+  }
+
+  fn color(&self) -> [f32; 4] {
+    let t = (self.age / self.lifetime).min(1.0);
+    let mut color = [0.0; 4];
+    for (idx, c) in color.iter_mut().enumerate() {
+      *c = lerp(self.start_color[idx], self.end_color[idx], t);
+    }
+    color
+  }
+
+  fn instance(&self, movement: Position) -> ParticleInstance {
+    let t = (self.age / self.lifetime).min(1.0);
+    let offset = movement - self.map_position;
+    ParticleInstance {
+      offset: [offset.x(), offset.y()],
+      size: lerp(self.size.0, self.size.1, t),
+      frame: self.frame as i32 as f32,
+      color: self.color(),
+    }
+  }
+}
+
+pub struct Particles {
+  particles: Vec<Particle>,
+  instances: Vec<ParticleInstance>,
+  projection: Projection,
+}
+
+impl Particles {
+  pub fn new() -> Particles {
+    let view = get_view_matrix(VIEW_DISTANCE);
+    Particles {
+      particles: Vec::new(),
+      instances: Vec::new(),
+      projection: get_projection(view, ASPECT_RATIO),
+    }
+  }
+
+  pub fn emit(&mut self, event: &ParticleEvent, rng: &mut GameRng) {
+    let e = emitter(event.kind);
+    let base_angle = direction(Point2::new(0.0, 0.0), event.direction);
+    for _ in 0..e.count {
+      if self.particles.len() >= MAX_PARTICLES {
+        return;
+      }
+      let angle = base_angle + get_rand_f32_from_range(rng, -e.spread, e.spread);
+      let speed = get_rand_f32_from_range(rng, e.speed.0, e.speed.1);
+      let draw_velocity = direction_movement(angle);
+      self.particles.push(Particle {
+        map_position: event.map_position,
+        // Map coordinates grow in the opposite direction to draw coordinates
+        velocity: Point2::new(-draw_velocity.x * speed, -draw_velocity.y * speed),
+        age: 0.0,
+        lifetime: get_rand_f32_from_range(rng, e.lifetime.0, e.lifetime.1),
+        size: e.size,
+        start_color: e.start_color,
+        end_color: e.end_color,
+        drag: e.drag,
+        frame: e.frame,
+      });
+    }
+  }
+
+  pub fn update(&mut self, delta: f32, movement: Position) {
+    for p in &mut self.particles {
+      p.update(delta);
+    }
+    self.particles.retain(Particle::is_alive);
+    self.instances = self.particles.iter()
+      .map(|p| p.instance(movement))
+      .collect();
+  }
+}
+
+impl Default for Particles {
+  fn default() -> Self {
+    Particles::new()
+  }
+}
+
+impl specs::prelude::Component for Particles {
+  type Storage = specs::storage::VecStorage<Particles>;
+}
+
+fn particle_texels() -> Vec<u8> {
+  let size = SHEET_FRAME_SIZE;
+  let center = (size as f32 - 1.0) / 2.0;
+  let mut texels = Vec::with_capacity((size * size * 2 * 4) as usize);
+  for y in 0..size {
+    for x in 0..size * 2 {
+      let alpha = if x < size {
+        let distance = ((x as f32 - center).powi(2) + (y as f32 - center).powi(2)).sqrt() / center;
+        (1.0 - distance).max(0.0).powf(0.7)
+      } else {
+        1.0
+      };
+      texels.extend_from_slice(&[255, 255, 255, (alpha * 255.0) as u8]);
+    }
+  }
+  texels
+}
+
+pub struct ParticleDrawSystem<R: gfx::Resources> {
+  bundle: gfx::pso::bundle::Bundle<R, particle_pipeline::Data<R>>,
+}
+
+impl<R: gfx::Resources> ParticleDrawSystem<R> {
+  pub fn new<F>(factory: &mut F,
+                rtv: gfx::handle::RenderTargetView<R, HdrColorFormat>,
+                dsv: gfx::handle::DepthStencilView<R, DepthFormat>,
+                assets: &AssetManager) -> GameResult<ParticleDrawSystem<R>>
+    where F: gfx::Factory<R> {
+    use gfx::traits::FactoryExt;
+
+    let mesh = PlainMesh::new_with_data(factory, Point2::new(1.0, 1.0), None, None, None);
+    let sheet = load_raw_texture(factory, &particle_texels(), Point2::new(SHEET_FRAME_SIZE * 2, SHEET_FRAME_SIZE));
+
+    let pso = create_pipeline_simple(factory, &SHADER.load(assets)?, particle_pipeline::new())
+      .map_err(GameError::Shader)?;
+
+    let instances = factory
+      .create_buffer(MAX_PARTICLES, gfx::buffer::Role::Vertex, gfx::memory::Usage::Dynamic, gfx::memory::Bind::empty())
+      .expect("Particle instance buffer creation error");
+
+    let pipeline_data = particle_pipeline::Data {
+      vbuf: mesh.vertex_buffer,
+      instances,
+      projection_cb: factory.create_constant_buffer(1),
+      particle_sheet: (sheet, factory.create_sampler_linear()),
+      out_color: rtv,
+      out_depth: dsv,
+    };
+
+    Ok(ParticleDrawSystem {
+      bundle: gfx::Bundle::new(mesh.slice, pso, pipeline_data),
+    })
+  }
+
+  pub fn reload_shaders<F>(&mut self, factory: &mut F, assets: &AssetManager) -> Result<(), String>
+    where F: gfx::Factory<R> {
+    reload_pipeline(factory, assets, SHADER, particle_pipeline::new(), &mut self.bundle.pso)
+  }
+
+  pub fn draw<C>(&mut self,
+                 particles: &Particles,
+                 encoder: &mut gfx::Encoder<R, C>)
+    where C: gfx::CommandBuffer<R> {
+    if particles.instances.is_empty() {
+      return;
+    }
+    encoder.update_buffer(&self.bundle.data.instances, &particles.instances, 0)
+      .unwrap_or_else(|e| panic!("Particle instance buffer update error {:?}", e));
+    encoder.update_constant_buffer(&self.bundle.data.projection_cb, &particles.projection);
+    self.bundle.slice.instances = Some((particles.instances.len() as u32, 0));
+    self.bundle.encode(encoder);
+  }
+}
+
+impl<R: gfx::Resources> RenderTargets<R, HdrColorFormat> for ParticleDrawSystem<R> {
+  fn targets(&mut self) -> Vec<TargetViews<'_, R, HdrColorFormat>> {
+    vec![(&mut self.bundle.data.out_color, &mut self.bundle.data.out_depth)]
+  }
+}
+
+pub struct ParticleSystem {
+  rng: GameRng,
+}
+
+impl ParticleSystem {
+  pub fn new() -> ParticleSystem {
+    ParticleSystem {
+      rng: GameRng::default(),
+    }
+  }
+}
+
+impl<'a> specs::prelude::System<'a> for ParticleSystem {
+  type SystemData = (WriteStorage<'a, Particles>,
+                     ReadStorage<'a, CameraInputState>,
+                     ReadStorage<'a, CharacterInputState>,
+                     Write<'a, ParticleEvents>,
+                     Read<'a, DeltaTime>,
+                     Read<'a, Dimensions>);
+
+  fn run(&mut self, (mut particles, camera_input, character_input, mut particle_events, d, dim): Self::SystemData) {
+    use specs::join::Join;
+
+    for (p, camera, ci) in (&mut particles, &camera_input, &character_input).join() {
+      p.projection = dim.world_to_projection(camera);
+      for event in particle_events.events.drain(..) {
+        p.emit(&event, &mut self.rng);
+      }
+      p.update(d.0 as f32, ci.movement);
+    }
+  }
+}
@@ -0,0 +1,53 @@
+#[test]
+fn particle_lifetime_test() {
+  use cgmath::Point2;
+  use crate::game::GameRng;
+  use crate::particle::{EmitterKind, ParticleEvent, Particles};
+  use crate::shaders::Position;
+
+  let mut particles = Particles::new();
+  let mut rng = GameRng::new(1);
+  let event = ParticleEvent { kind: EmitterKind::Blood, map_position: Position::origin(), direction: Point2::new(1.0, 0.0) };
+  particles.emit(&event, &mut rng);
+  particles.update(0.01, Position::origin());
+  assert_eq!(12, particles.instances.len(), "Blood emitter should spawn a burst");
+
+  particles.update(1.0, Position::origin());
+  assert!(particles.instances.is_empty(), "Particles should expire after their lifetime");
+}
+
+#[test]
+fn particle_color_over_life_test() {
+  use cgmath::Point2;
+  use crate::game::GameRng;
+  use crate::particle::{EmitterKind, ParticleEvent, Particles};
+  use crate::shaders::Position;
+
+  let mut particles = Particles::new();
+  let event = ParticleEvent { kind: EmitterKind::ShellCasing, map_position: Position::origin(), direction: Point2::new(0.0, 1.0) };
+  particles.emit(&event, &mut GameRng::new(1));
+  particles.update(0.0, Position::origin());
+  let start_alpha = particles.instances[0].color[3];
+  particles.update(0.25, Position::origin());
+  let faded_alpha = particles.instances[0].color[3];
+  assert_eq!(1.0, start_alpha);
+  assert!(faded_alpha < start_alpha, "Particle should fade over its lifetime");
+}
+
+#[test]
+fn particle_budget_test() {
+  use cgmath::Point2;
+  use crate::game::constants::MAX_PARTICLES;
+  use crate::game::GameRng;
+  use crate::particle::{EmitterKind, ParticleEvent, Particles};
+  use crate::shaders::Position;
+
+  let mut particles = Particles::new();
+  let mut rng = GameRng::new(1);
+  let event = ParticleEvent { kind: EmitterKind::Dust, map_position: Position::origin(), direction: Point2::new(1.0, 0.0) };
+  for _ in 0..MAX_PARTICLES {
+    particles.emit(&event, &mut rng);
+  }
+  particles.update(0.0, Position::origin());
+  assert_eq!(MAX_PARTICLES, particles.instances.len());
+}
@@ -0,0 +1,234 @@
+use std::{fs, str::FromStr};
+
+use cgmath::Point2;
+use crossbeam_channel as channel;
+use json::JsonValue;
+use specs::prelude::WriteStorage;
+
+use crate::bullet::bullets::Bullets;
+use crate::character::{CharacterDrawable, controls::CharacterInputState};
+use crate::game::constants::{QUICK_SAVE_PATH, SAVE_FORMAT_VERSION};
+use crate::graphics::{camera::CameraInputState, orientation::Stance};
+use crate::score::Score;
+use crate::shaders::Position;
+use crate::terrain_object::terrain_objects::TerrainObjects;
+use crate::zombie::{ZombieDrawable, zombies::Zombies};
+
+mod save_test;
+
+pub struct SavedZombie {
+  pub position: Position,
+  pub elevation: f32,
+  pub health: f32,
+  pub stance: Stance,
+}
+
+impl SavedZombie {
+  fn to_json(&self) -> JsonValue {
+    let mut zombie = JsonValue::new_object();
+    zombie["position"] = position_to_json(self.position);
+    zombie["elevation"] = self.elevation.into();
+    zombie["health"] = self.health.into();
+    zombie["stance"] = self.stance.to_string().into();
+    zombie
+  }
+
+  fn from_json(zombie: &JsonValue) -> Result<SavedZombie, String> {
+    Ok(SavedZombie {
+      position: position_from_json(&zombie["position"])?,
+      elevation: read_f32(&zombie["elevation"], "zombie elevation")?,
+      health: read_f32(&zombie["health"], "zombie health")?,
+      stance: Stance::from_str(zombie["stance"].as_str().unwrap_or(""))?,
+    })
+  }
+}
+
+pub struct SaveGame {
+  pub ammunition: usize,
+  pub magazines: usize,
+  pub health: f32,
+  pub movement: Position,
+  pub points: u32,
+  pub kills: u32,
+  pub shots_fired: u32,
+  pub hits: u32,
+  pub zombies: Vec<SavedZombie>,
+  pub pickups: Vec<Position>,
+}
+
+impl SaveGame {
+  pub fn new(character: &CharacterDrawable,
+             ci: &CharacterInputState,
+             score: &Score,
+             zombies: &Zombies,
+             terrain_objects: &TerrainObjects) -> SaveGame {
+    SaveGame {
+      ammunition: character.stats.ammunition,
+      magazines: character.stats.magazines,
+      health: character.stats.health,
+      movement: ci.movement,
+      points: score.points,
+      kills: score.kills,
+      shots_fired: score.shots_fired,
+      hits: score.hits,
+      zombies: zombies.zombies.iter().map(ZombieDrawable::to_save).collect(),
+      pickups: terrain_objects.pickups(),
+    }
+  }
+
+  pub fn apply(&self,
+               character: &mut CharacterDrawable,
+               ci: &mut CharacterInputState,
+               camera: &mut CameraInputState,
+               score: &mut Score,
+               zombies: &mut Zombies,
+               terrain_objects: &mut TerrainObjects,
+               bullets: &mut Bullets) {
+    character.stats.ammunition = self.ammunition;
+    character.stats.magazines = self.magazines;
+    character.stats.health = self.health;
+    character.stance = Stance::Walking;
+    ci.movement = self.movement;
+    ci.velocity = Point2::new(0.0, 0.0);
+    camera.movement = Position::new(self.movement.x(), -self.movement.y());
+    *score = Score::restore(self.points, self.kills, self.shots_fired, self.hits);
+    zombies.zombies = self.zombies.iter()
+      .map(|z| ZombieDrawable::from_save(z, self.movement))
+      .collect();
+    terrain_objects.restore(&self.pickups, self.movement);
+    bullets.bullets.clear();
+  }
+
+  pub fn to_json(&self) -> JsonValue {
+    let mut character = JsonValue::new_object();
+    character["ammunition"] = self.ammunition.into();
+    character["magazines"] = self.magazines.into();
+    character["health"] = self.health.into();
+    character["position"] = position_to_json(self.movement);
+
+    let mut score = JsonValue::new_object();
+    score["points"] = self.points.into();
+    score["kills"] = self.kills.into();
+    score["shots_fired"] = self.shots_fired.into();
+    score["hits"] = self.hits.into();
+
+    let mut save = JsonValue::new_object();
+    save["version"] = SAVE_FORMAT_VERSION.into();
+    save["character"] = character;
+    save["score"] = score;
+    save["zombies"] = JsonValue::Array(self.zombies.iter().map(SavedZombie::to_json).collect());
+    save["pickups"] = JsonValue::Array(self.pickups.iter().map(|p| position_to_json(*p)).collect());
+    save
+  }
+
+  pub fn from_json(save: &JsonValue) -> Result<SaveGame, String> {
+    match save["version"].as_u32() {
+      Some(SAVE_FORMAT_VERSION) => (),
+      Some(version) => return Err(format!("Unsupported save format version {}", version)),
+      None => return Err("Save format version missing".to_string()),
+    }
+
+    let character = &save["character"];
+    let score = &save["score"];
+    let zombies = save["zombies"].members()
+      .map(SavedZombie::from_json)
+      .collect::<Result<Vec<SavedZombie>, String>>()?;
+    let pickups = save["pickups"].members()
+      .map(position_from_json)
+      .collect::<Result<Vec<Position>, String>>()?;
+
+    Ok(SaveGame {
+      ammunition: character["ammunition"].as_usize().ok_or("Character ammunition missing")?,
+      magazines: character["magazines"].as_usize().ok_or("Character magazines missing")?,
+      health: read_f32(&character["health"], "character health")?,
+      movement: position_from_json(&character["position"])?,
+      points: score["points"].as_u32().ok_or("Score points missing")?,
+      kills: score["kills"].as_u32().ok_or("Score kills missing")?,
+      shots_fired: score["shots_fired"].as_u32().ok_or("Score shots missing")?,
+      hits: score["hits"].as_u32().ok_or("Score hits missing")?,
+      zombies,
+      pickups,
+    })
+  }
+}
+
+fn position_to_json(position: Position) -> JsonValue {
+  JsonValue::Array(vec![position.x().into(), position.y().into()])
+}
+
+fn position_from_json(position: &JsonValue) -> Result<Position, String> {
+  Ok(Position::new(read_f32(&position[0], "position x")?, read_f32(&position[1], "position y")?))
+}
+
+fn read_f32(value: &JsonValue, name: &str) -> Result<f32, String> {
+  value.as_f32().ok_or_else(|| format!("Save value {} missing", name))
+}
+
+pub fn write_save_file(filename: &str, save_game: &SaveGame) -> Result<(), String> {
+  fs::write(filename, save_game.to_json().pretty(2))
+    .map_err(|e| format!("Save file {} write error {}", filename, e))
+}
+
+pub fn read_save_file(filename: &str) -> Result<SaveGame, String> {
+  let save_json = fs::read_to_string(filename)
+    .map_err(|e| format!("Save file {} not found: {}", filename, e))?;
+  let save = json::parse(&save_json)
+    .map_err(|e| format!("Save file {} parse error {:?}", filename, e))?;
+  SaveGame::from_json(&save)
+}
+
+#[derive(Clone, Copy)]
+pub enum SaveControl {
+  QuickSave,
+  QuickLoad,
+}
+
+pub struct SaveSystem {
+  queue: channel::Receiver<SaveControl>,
+}
+
+impl SaveSystem {
+  pub fn new() -> (SaveSystem, channel::Sender<SaveControl>) {
+    let (tx, rx) = channel::unbounded();
+    (SaveSystem {
+      queue: rx,
+    }, tx)
+  }
+}
+
+impl<'a> specs::prelude::System<'a> for SaveSystem {
+  type SystemData = (WriteStorage<'a, CharacterDrawable>,
+                     WriteStorage<'a, CharacterInputState>,
+                     WriteStorage<'a, CameraInputState>,
+                     WriteStorage<'a, Score>,
+                     WriteStorage<'a, Zombies>,
+                     WriteStorage<'a, TerrainObjects>,
+                     WriteStorage<'a, Bullets>);
+
+  fn run(&mut self, (mut character, mut character_input, mut camera_input, mut score, mut zombies, mut terrain_objects, mut bullets): Self::SystemData) {
+    use specs::join::Join;
+
+    while let Ok(control) = self.queue.try_recv() {
+      for (c, ci, camera, s, zs, to, bs) in
+        (&mut character, &mut character_input, &mut camera_input, &mut score, &mut zombies, &mut terrain_objects, &mut bullets).join() {
+        match control {
+          SaveControl::QuickSave => {
+            match write_save_file(QUICK_SAVE_PATH, &SaveGame::new(c, ci, s, zs, to)) {
+              Ok(()) => println!("Game saved to {}", QUICK_SAVE_PATH),
+              Err(e) => println!("{}", e),
+            }
+          }
+          SaveControl::QuickLoad => {
+            match read_save_file(QUICK_SAVE_PATH) {
+              Ok(save_game) => {
+                save_game.apply(c, ci, camera, s, zs, to, bs);
+                println!("Game loaded from {}", QUICK_SAVE_PATH);
+              }
+              Err(e) => println!("{}", e),
+            }
+          }
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,49 @@
+#[test]
+fn save_file_round_trip_test() {
+  use crate::graphics::orientation::Stance;
+  use crate::save::{read_save_file, SavedZombie, SaveGame, write_save_file};
+  use crate::shaders::Position;
+
+  let save_game = SaveGame {
+    ammunition: 7,
+    magazines: 2,
+    health: 35.5,
+    movement: Position::new(120.5, -64.0),
+    points: 450,
+    kills: 3,
+    shots_fired: 12,
+    hits: 5,
+    zombies: vec![SavedZombie { position: Position::new(10.0, 20.0), elevation: 1.5, health: 60.0, stance: Stance::Running }],
+    pickups: vec![Position::new(-30.0, 40.0)],
+  };
+  let path = std::env::temp_dir().join(format!("hinterland_save_test_{}.json", std::process::id()));
+  let filename = path.to_str().unwrap();
+  write_save_file(filename, &save_game).unwrap();
+  let loaded = read_save_file(filename).unwrap();
+  std::fs::remove_file(&path).unwrap();
+
+  assert_eq!(7, loaded.ammunition);
+  assert_eq!(2, loaded.magazines);
+  assert_eq!(35.5, loaded.health);
+  assert_eq!(Position::new(120.5, -64.0), loaded.movement);
+  assert_eq!((450, 3, 12, 5), (loaded.points, loaded.kills, loaded.shots_fired, loaded.hits));
+  assert_eq!(1, loaded.zombies.len());
+  assert_eq!(Position::new(10.0, 20.0), loaded.zombies[0].position);
+  assert_eq!((1.5, 60.0), (loaded.zombies[0].elevation, loaded.zombies[0].health));
+  assert!(loaded.zombies[0].stance == Stance::Running);
+  assert_eq!(vec![Position::new(-30.0, 40.0)], loaded.pickups);
+}
+
+#[test]
+fn save_version_test() {
+  use crate::game::constants::SAVE_FORMAT_VERSION;
+  use crate::save::SaveGame;
+
+  let unknown = json::parse(&format!(r#"{{ "version": {} }}"#, SAVE_FORMAT_VERSION + 1)).unwrap();
+  let error = SaveGame::from_json(&unknown).err().expect("Unknown version should be rejected");
+  assert_eq!(format!("Unsupported save format version {}", SAVE_FORMAT_VERSION + 1), error);
+
+  let missing = json::parse(r#"{ "character": { "ammunition": 10 } }"#).unwrap();
+  let error = SaveGame::from_json(&missing).err().expect("Missing version should be rejected");
+  assert_eq!("Save format version missing", error);
+}
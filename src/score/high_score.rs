@@ -0,0 +1,55 @@
+use std::fs;
+
+use json::JsonValue;
+
+use crate::game::constants::HIGH_SCORE_TABLE_SIZE;
+
+#[derive(Clone)]
+pub struct HighScore {
+  pub name: String,
+  pub points: u32,
+}
+
+impl HighScore {
+  pub fn new(name: &str, points: u32) -> HighScore {
+    HighScore {
+      name: name.to_string(),
+      points,
+    }
+  }
+}
+
+pub fn insert_high_score(high_scores: &mut Vec<HighScore>, entry: HighScore) {
+  high_scores.push(entry);
+  high_scores.sort_by(|a, b| b.points.cmp(&a.points));
+  high_scores.truncate(HIGH_SCORE_TABLE_SIZE);
+}
+
+pub fn load_high_scores(filename: &str) -> Vec<HighScore> {
+  let high_scores = match fs::read_to_string(filename) {
+    Ok(f) => f,
+    Err(_) => return Vec::new(),
+  };
+  match json::parse(&high_scores) {
+    Ok(res) => res.members()
+      .filter_map(|e| e["name"].as_str().and_then(|name| e["points"].as_u32().map(|points| HighScore::new(name, points))))
+      .collect(),
+    Err(e) => {
+      println!("High score file {} parse error {:?}", filename, e);
+      Vec::new()
+    }
+  }
+}
+
+pub fn save_high_scores(filename: &str, high_scores: &[HighScore]) -> Result<(), String> {
+  let table = high_scores.iter()
+    .map(|e| {
+      let mut entry = JsonValue::new_object();
+      entry["name"] = e.name.as_str().into();
+      entry["points"] = e.points.into();
+      entry
+    })
+    .collect();
+  fs::write(filename, JsonValue::Array(table).pretty(2))
+    .map_err(|e| format!("High score file {} write error {}", filename, e))
+}
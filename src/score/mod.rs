@@ -0,0 +1,144 @@
+use crossbeam_channel as channel;
+use specs::prelude::{Read, ReadStorage, WriteStorage};
+
+use crate::character::CharacterDrawable;
+use crate::game::constants::{COMBO_WINDOW, CRITICAL_KILL_POINTS, HIGH_SCORE_PATH, KILL_POINTS, MAX_COMBO_MULTIPLIER, MAX_NAME_LENGTH};
+use crate::graphics::{DeltaTime, orientation::Stance};
+use crate::score::high_score::{HighScore, insert_high_score, load_high_scores, save_high_scores};
+
+pub mod high_score;
+mod score_test;
+
+pub fn kill_points(death: &Stance) -> u32 {
+  match death {
+    Stance::CriticalDeath => CRITICAL_KILL_POINTS,
+    _ => KILL_POINTS,
+  }
+}
+
+#[derive(Clone, Default)]
+pub struct Score {
+  pub points: u32,
+  pub kills: u32,
+  pub combo: u32,
+  combo_timer: f64,
+  pub shots_fired: u32,
+  pub hits: u32,
+  pub player_name: String,
+  pub high_scores: Option<Vec<HighScore>>,
+}
+
+impl Score {
+  pub fn new() -> Score {
+    Score::default()
+  }
+
+  pub fn restore(points: u32, kills: u32, shots_fired: u32, hits: u32) -> Score {
+    Score {
+      points,
+      kills,
+      shots_fired,
+      hits,
+      ..Score::default()
+    }
+  }
+
+  pub fn register_shot(&mut self) {
+    self.shots_fired += 1;
+  }
+
+  pub fn register_hit(&mut self) {
+    self.hits += 1;
+  }
+
+  pub fn register_kill(&mut self, death: &Stance) -> u32 {
+    self.combo = if self.combo_timer > 0.0 {
+      (self.combo + 1).min(MAX_COMBO_MULTIPLIER)
+    } else {
+      1
+    };
+    self.combo_timer = COMBO_WINDOW;
+    self.kills += 1;
+    let points = kill_points(death) * self.combo;
+    self.points += points;
+    points
+  }
+
+  pub fn update(&mut self, delta: f64) {
+    self.combo_timer = (self.combo_timer - delta).max(0.0);
+    if self.combo_timer == 0.0 {
+      self.combo = 0;
+    }
+  }
+
+  pub fn accuracy(&self) -> u32 {
+    if self.shots_fired == 0 {
+      0
+    } else {
+      self.hits * 100 / self.shots_fired
+    }
+  }
+
+  fn enter_character(&mut self, character: char) {
+    match character {
+      '\u{8}' => {
+        self.player_name.pop();
+      }
+      '\r' | '\n' if !self.player_name.is_empty() => self.submit(),
+      c if !c.is_control() && self.player_name.chars().count() < MAX_NAME_LENGTH => self.player_name.push(c),
+      _ => (),
+    }
+  }
+
+  fn submit(&mut self) {
+    let mut high_scores = load_high_scores(HIGH_SCORE_PATH);
+    insert_high_score(&mut high_scores, HighScore::new(&self.player_name, self.points));
+    if let Err(e) = save_high_scores(HIGH_SCORE_PATH, &high_scores) {
+      println!("{}", e);
+    }
+    self.high_scores = Some(high_scores);
+  }
+}
+
+impl specs::prelude::Component for Score {
+  type Storage = specs::storage::VecStorage<Score>;
+}
+
+pub enum ScoreControl {
+  NameInput(char),
+}
+
+pub struct ScoreSystem {
+  queue: channel::Receiver<ScoreControl>,
+}
+
+impl ScoreSystem {
+  pub fn new() -> (ScoreSystem, channel::Sender<ScoreControl>) {
+    let (tx, rx) = channel::unbounded();
+    (ScoreSystem {
+      queue: rx,
+    }, tx)
+  }
+}
+
+impl<'a> specs::prelude::System<'a> for ScoreSystem {
+  type SystemData = (WriteStorage<'a, Score>,
+                     ReadStorage<'a, CharacterDrawable>,
+                     Read<'a, DeltaTime>);
+
+  fn run(&mut self, (mut score, character, d): Self::SystemData) {
+    use specs::join::Join;
+
+    for (s, c) in (&mut score, &character).join() {
+      let is_game_over = c.stance == Stance::NormalDeath;
+      if !is_game_over {
+        s.update(d.0);
+      }
+      while let Ok(ScoreControl::NameInput(character)) = self.queue.try_recv() {
+        if is_game_over && s.high_scores.is_none() {
+          s.enter_character(character);
+        }
+      }
+    }
+  }
+}
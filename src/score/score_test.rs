@@ -0,0 +1,28 @@
+#[test]
+fn combo_multiplier_test() {
+  use crate::graphics::orientation::Stance;
+  use crate::score::Score;
+
+  let mut score = Score::new();
+  score.register_kill(&Stance::NormalDeath);
+  score.register_kill(&Stance::NormalDeath);
+  assert_eq!(300, score.points, "Second rapid kill should be doubled");
+
+  score.update(10.0);
+  score.register_kill(&Stance::CriticalDeath);
+  assert_eq!(450, score.points, "Combo should reset after combo window");
+  assert_eq!(3, score.kills);
+}
+
+#[test]
+fn high_score_table_test() {
+  use crate::game::constants::HIGH_SCORE_TABLE_SIZE;
+  use crate::score::high_score::{HighScore, insert_high_score};
+
+  let mut high_scores = vec![];
+  for points in 0..10 {
+    insert_high_score(&mut high_scores, HighScore::new("player", points * 100));
+  }
+  assert_eq!(HIGH_SCORE_TABLE_SIZE, high_scores.len(), "Table should be truncated");
+  assert_eq!(900, high_scores[0].points, "Highest score should be first");
+}
@@ -0,0 +1,213 @@
+use cgmath::Point2;
+use crossbeam_channel as channel;
+use specs::prelude::Write;
+
+use crate::game::constants::{CONSOLE_BACKGROUND_COLOR, CONSOLE_TEXT_COLOR, MAX_MOUSE_SENSITIVITY, MIN_MOUSE_SENSITIVITY, MSAA_LEVELS,
+                             RESOLUTIONS, SETTINGS_MENU_FONT_SIZE, SETTINGS_MENU_LINE_HEIGHT, SETTINGS_MENU_WIDTH, SETTINGS_PATH,
+                             SETTINGS_SELECTED_COLOR, VOLUME_STEP};
+use crate::hud::layout::{Anchor, UiRect};
+use crate::hud::TextDrawable;
+use crate::hud::ui::UiBar;
+use crate::settings::{save_settings, Settings};
+
+const ITEM_COUNT: usize = 13;
+const MOUSE_SENSITIVITY_ITEM: usize = 11;
+const DIFFICULTY_ITEM: usize = 12;
+
+#[derive(Clone, Copy)]
+pub enum SettingsControl {
+  Toggle,
+  Previous,
+  Next,
+  Decrease,
+  Increase,
+}
+
+#[derive(Default)]
+pub struct SettingsMenu {
+  is_open: bool,
+  selected: usize,
+  dirty: bool,
+  is_replay_locked: bool,
+  texts: Vec<TextDrawable>,
+  background: Vec<UiBar>,
+}
+
+fn cycle<T: PartialEq + Copy>(values: &[T], current: T, direction: i32) -> T {
+  let idx = values.iter().position(|v| *v == current).unwrap_or(0) as i32;
+  values[(idx + direction).rem_euclid(values.len() as i32) as usize]
+}
+
+fn step(value: f32, direction: i32, step: f32, min: f32, max: f32) -> f32 {
+  let value = ((value + direction as f32 * step) * 100.0).round() / 100.0;
+  value.max(min).min(max)
+}
+
+fn on_off(value: bool) -> &'static str {
+  if value { "on" } else { "off" }
+}
+
+// Command line and replay overrides stay out of the settings file
+fn copy_item(item: usize, settings: &Settings, file_settings: &mut Settings) {
+  match item {
+    0 => file_settings.resolution = settings.resolution,
+    1 => file_settings.fullscreen = settings.fullscreen,
+    2 => file_settings.vsync = settings.vsync,
+    3 => file_settings.msaa = settings.msaa,
+    4 => file_settings.bloom = settings.bloom,
+    5 => file_settings.vignette = settings.vignette,
+    6 => file_settings.color_grading = settings.color_grading,
+    7 => file_settings.film_grain = settings.film_grain,
+    8 => file_settings.master_volume = settings.master_volume,
+    9 => file_settings.sfx_volume = settings.sfx_volume,
+    10 => file_settings.music_volume = settings.music_volume,
+    MOUSE_SENSITIVITY_ITEM => file_settings.mouse_sensitivity = settings.mouse_sensitivity,
+    _ => file_settings.difficulty = settings.difficulty,
+  }
+}
+
+impl SettingsMenu {
+  pub fn new(is_replay_locked: bool) -> SettingsMenu {
+    SettingsMenu {
+      is_replay_locked,
+      ..SettingsMenu::default()
+    }
+  }
+
+  pub fn is_open(&self) -> bool {
+    self.is_open
+  }
+
+  pub fn selected(&self) -> usize {
+    self.selected
+  }
+
+  fn is_locked(&self, item: usize) -> bool {
+    self.is_replay_locked && (item == MOUSE_SENSITIVITY_ITEM || item == DIFFICULTY_ITEM)
+  }
+
+  pub fn apply(&mut self, control: SettingsControl, settings: &mut Settings, file_settings: &mut Settings) {
+    match control {
+      SettingsControl::Toggle => {
+        self.is_open = !self.is_open;
+        self.selected = 0;
+      }
+      SettingsControl::Previous => self.selected = (self.selected + ITEM_COUNT - 1) % ITEM_COUNT,
+      SettingsControl::Next => self.selected = (self.selected + 1) % ITEM_COUNT,
+      SettingsControl::Decrease => self.adjust(settings, file_settings, -1),
+      SettingsControl::Increase => self.adjust(settings, file_settings, 1),
+    }
+  }
+
+  fn adjust(&mut self, settings: &mut Settings, file_settings: &mut Settings, direction: i32) {
+    if self.is_locked(self.selected) {
+      return;
+    }
+    match self.selected {
+      0 => settings.resolution = cycle(&RESOLUTIONS, settings.resolution, direction),
+      1 => settings.fullscreen = !settings.fullscreen,
+      2 => settings.vsync = !settings.vsync,
+      3 => settings.msaa = cycle(&MSAA_LEVELS, settings.msaa, direction),
+      4 => settings.bloom = !settings.bloom,
+      5 => settings.vignette = !settings.vignette,
+      6 => settings.color_grading = !settings.color_grading,
+      7 => settings.film_grain = !settings.film_grain,
+      8 => settings.master_volume = step(settings.master_volume, direction, VOLUME_STEP, 0.0, 1.0),
+      9 => settings.sfx_volume = step(settings.sfx_volume, direction, VOLUME_STEP, 0.0, 1.0),
+      10 => settings.music_volume = step(settings.music_volume, direction, VOLUME_STEP, 0.0, 1.0),
+      MOUSE_SENSITIVITY_ITEM => settings.mouse_sensitivity = step(settings.mouse_sensitivity, direction, 0.1, MIN_MOUSE_SENSITIVITY, MAX_MOUSE_SENSITIVITY),
+      _ => settings.difficulty = if direction < 0 { settings.difficulty.previous() } else { settings.difficulty.next() },
+    }
+    copy_item(self.selected, settings, file_settings);
+    self.dirty = true;
+  }
+
+  pub fn take_dirty(&mut self) -> bool {
+    let dirty = self.dirty;
+    self.dirty = false;
+    dirty
+  }
+
+  pub fn labels(&self, settings: &Settings) -> Vec<String> {
+    let mut labels = vec![
+      format!("Resolution: {}x{} (restart)", settings.resolution.0, settings.resolution.1),
+      format!("Fullscreen: {} (restart)", on_off(settings.fullscreen)),
+      format!("Vsync: {} (restart)", on_off(settings.vsync)),
+      format!("MSAA: {}x (restart)", settings.msaa),
+      format!("Bloom: {}", on_off(settings.bloom)),
+      format!("Vignette: {}", on_off(settings.vignette)),
+      format!("Night color grading: {}", on_off(settings.color_grading)),
+      format!("Film grain: {}", on_off(settings.film_grain)),
+      format!("Master volume: {:.0}%", settings.master_volume * 100.0),
+      format!("SFX volume: {:.0}%", settings.sfx_volume * 100.0),
+      format!("Music volume: {:.0}%", settings.music_volume * 100.0),
+      format!("Mouse sensitivity: {:.1}", settings.mouse_sensitivity),
+      format!("Difficulty: {}", settings.difficulty.name()),
+    ];
+    for (idx, label) in labels.iter_mut().enumerate() {
+      if self.is_locked(idx) {
+        label.push_str(" (replay)");
+      }
+    }
+    labels
+  }
+
+  fn update_layout(&mut self, settings: &Settings) {
+    self.texts.clear();
+    self.background.clear();
+    if !self.is_open {
+      return;
+    }
+    let height = (ITEM_COUNT + 2) as f32 * SETTINGS_MENU_LINE_HEIGHT;
+    self.background.push(UiBar::new(UiRect::new(Anchor::Center, Point2::new(0.0, 0.0), Point2::new(SETTINGS_MENU_WIDTH, height)),
+                                    CONSOLE_BACKGROUND_COLOR));
+    let top = -height / 2.0 + SETTINGS_MENU_LINE_HEIGHT;
+    self.texts.push(TextDrawable::new("Settings", Anchor::Center, Point2::new(0.0, top), SETTINGS_MENU_FONT_SIZE, CONSOLE_TEXT_COLOR));
+    for (idx, label) in self.labels(settings).iter().enumerate() {
+      let color = if idx == self.selected() { SETTINGS_SELECTED_COLOR } else { CONSOLE_TEXT_COLOR };
+      let offset = Point2::new(0.0, top + (idx + 1) as f32 * SETTINGS_MENU_LINE_HEIGHT);
+      self.texts.push(TextDrawable::new(label, Anchor::Center, offset, SETTINGS_MENU_FONT_SIZE, color));
+    }
+  }
+
+  pub fn texts(&self) -> impl Iterator<Item=&TextDrawable> {
+    self.texts.iter()
+  }
+
+  pub fn bars(&self) -> impl Iterator<Item=&UiBar> {
+    self.background.iter()
+  }
+}
+
+pub struct SettingsMenuSystem {
+  queue: channel::Receiver<SettingsControl>,
+  file_settings: Settings,
+}
+
+impl SettingsMenuSystem {
+  pub fn new(file_settings: Settings) -> (SettingsMenuSystem, channel::Sender<SettingsControl>) {
+    let (tx, rx) = channel::unbounded();
+    (SettingsMenuSystem {
+      queue: rx,
+      file_settings,
+    }, tx)
+  }
+}
+
+impl<'a> specs::prelude::System<'a> for SettingsMenuSystem {
+  type SystemData = (Write<'a, SettingsMenu>,
+                     Write<'a, Settings>);
+
+  fn run(&mut self, (mut menu, mut settings): Self::SystemData) {
+    while let Ok(control) = self.queue.try_recv() {
+      menu.apply(control, &mut settings, &mut self.file_settings);
+    }
+    if !menu.is_open() && menu.take_dirty() {
+      match save_settings(SETTINGS_PATH, &self.file_settings) {
+        Ok(()) => println!("Settings saved to {}", SETTINGS_PATH),
+        Err(e) => println!("{}", e),
+      }
+    }
+    menu.update_layout(&settings);
+  }
+}
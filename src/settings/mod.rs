@@ -0,0 +1,192 @@
+use std::fs;
+
+use json::JsonValue;
+
+use crate::game::constants::{MAX_MOUSE_SENSITIVITY, MIN_MOUSE_SENSITIVITY, MSAA_LEVELS, RESOLUTION_X, RESOLUTION_Y};
+
+pub mod menu;
+mod settings_test;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Difficulty {
+  Easy,
+  Normal,
+  Hard,
+}
+
+impl Difficulty {
+  pub fn name(self) -> &'static str {
+    match self {
+      Difficulty::Easy => "easy",
+      Difficulty::Normal => "normal",
+      Difficulty::Hard => "hard",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Difficulty> {
+    match name {
+      "easy" => Some(Difficulty::Easy),
+      "normal" => Some(Difficulty::Normal),
+      "hard" => Some(Difficulty::Hard),
+      _ => None,
+    }
+  }
+
+  pub fn next(self) -> Difficulty {
+    match self {
+      Difficulty::Easy => Difficulty::Normal,
+      Difficulty::Normal => Difficulty::Hard,
+      Difficulty::Hard => Difficulty::Easy,
+    }
+  }
+
+  pub fn previous(self) -> Difficulty {
+    self.next().next()
+  }
+
+  pub fn zombie_speed(self) -> f32 {
+    match self {
+      Difficulty::Easy => 0.75,
+      Difficulty::Normal => 1.0,
+      Difficulty::Hard => 1.35,
+    }
+  }
+
+  pub fn wave_scale(self) -> f32 {
+    match self {
+      Difficulty::Easy => 0.5,
+      Difficulty::Normal => 1.0,
+      Difficulty::Hard => 1.5,
+    }
+  }
+}
+
+impl Default for Difficulty {
+  fn default() -> Difficulty {
+    Difficulty::Normal
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+  pub resolution: (u32, u32),
+  pub fullscreen: bool,
+  pub vsync: bool,
+  pub msaa: u16,
+  pub bloom: bool,
+  pub vignette: bool,
+  pub color_grading: bool,
+  pub film_grain: bool,
+  pub master_volume: f32,
+  pub sfx_volume: f32,
+  pub music_volume: f32,
+  pub mouse_sensitivity: f32,
+  pub difficulty: Difficulty,
+}
+
+impl Default for Settings {
+  fn default() -> Settings {
+    Settings {
+      resolution: (RESOLUTION_X, RESOLUTION_Y),
+      fullscreen: true,
+      vsync: true,
+      msaa: 0,
+      bloom: true,
+      vignette: true,
+      color_grading: true,
+      film_grain: false,
+      master_volume: 1.0,
+      sfx_volume: 1.0,
+      music_volume: 1.0,
+      mouse_sensitivity: 1.0,
+      difficulty: Difficulty::Normal,
+    }
+  }
+}
+
+fn rounded(value: f32) -> JsonValue {
+  ((f64::from(value) * 100.0).round() / 100.0).into()
+}
+
+fn volume(value: &JsonValue, default: f32) -> f32 {
+  value.as_f32().map_or(default, |v| v.max(0.0).min(1.0))
+}
+
+impl Settings {
+  pub fn from_json(settings: &JsonValue) -> Settings {
+    let defaults = Settings::default();
+    let video = &settings["video"];
+    let post_processing = &settings["post_processing"];
+    let audio = &settings["audio"];
+    let resolution = video["resolution"].as_str()
+      .and_then(parse_resolution)
+      .unwrap_or(defaults.resolution);
+    Settings {
+      resolution,
+      fullscreen: video["fullscreen"].as_bool().unwrap_or(defaults.fullscreen),
+      vsync: video["vsync"].as_bool().unwrap_or(defaults.vsync),
+      msaa: video["msaa"].as_u16().filter(|msaa| MSAA_LEVELS.contains(msaa)).unwrap_or(defaults.msaa),
+      bloom: post_processing["bloom"].as_bool().unwrap_or(defaults.bloom),
+      vignette: post_processing["vignette"].as_bool().unwrap_or(defaults.vignette),
+      color_grading: post_processing["color_grading"].as_bool().unwrap_or(defaults.color_grading),
+      film_grain: post_processing["film_grain"].as_bool().unwrap_or(defaults.film_grain),
+      master_volume: volume(&audio["master_volume"], defaults.master_volume),
+      sfx_volume: volume(&audio["sfx_volume"], defaults.sfx_volume),
+      music_volume: volume(&audio["music_volume"], defaults.music_volume),
+      mouse_sensitivity: settings["controls"]["mouse_sensitivity"].as_f32()
+        .map_or(defaults.mouse_sensitivity, |s| s.max(MIN_MOUSE_SENSITIVITY).min(MAX_MOUSE_SENSITIVITY)),
+      difficulty: settings["gameplay"]["difficulty"].as_str()
+        .and_then(Difficulty::from_name)
+        .unwrap_or(defaults.difficulty),
+    }
+  }
+
+  pub fn to_json(&self) -> JsonValue {
+    let mut settings = JsonValue::new_object();
+    settings["video"]["resolution"] = format!("{}x{}", self.resolution.0, self.resolution.1).into();
+    settings["video"]["fullscreen"] = self.fullscreen.into();
+    settings["video"]["vsync"] = self.vsync.into();
+    settings["video"]["msaa"] = self.msaa.into();
+    settings["post_processing"]["bloom"] = self.bloom.into();
+    settings["post_processing"]["vignette"] = self.vignette.into();
+    settings["post_processing"]["color_grading"] = self.color_grading.into();
+    settings["post_processing"]["film_grain"] = self.film_grain.into();
+    settings["audio"]["master_volume"] = rounded(self.master_volume);
+    settings["audio"]["sfx_volume"] = rounded(self.sfx_volume);
+    settings["audio"]["music_volume"] = rounded(self.music_volume);
+    settings["controls"]["mouse_sensitivity"] = rounded(self.mouse_sensitivity);
+    settings["gameplay"]["difficulty"] = self.difficulty.name().into();
+    settings
+  }
+
+  pub fn effects_volume(&self) -> f32 {
+    self.master_volume * self.sfx_volume
+  }
+}
+
+pub fn parse_resolution(resolution: &str) -> Option<(u32, u32)> {
+  let mut parts = resolution.split('x').map(|part| part.trim().parse::<u32>().ok());
+  match (parts.next(), parts.next(), parts.next()) {
+    (Some(Some(width)), Some(Some(height)), None) if width > 0 && height > 0 => Some((width, height)),
+    _ => None,
+  }
+}
+
+pub fn load_settings(filename: &str) -> Settings {
+  let settings = match fs::read_to_string(filename) {
+    Ok(f) => f,
+    Err(_) => return Settings::default(),
+  };
+  match json::parse(&settings) {
+    Ok(res) => Settings::from_json(&res),
+    Err(e) => {
+      println!("Settings file {} parse error {:?}", filename, e);
+      Settings::default()
+    }
+  }
+}
+
+pub fn save_settings(filename: &str, settings: &Settings) -> Result<(), String> {
+  fs::write(filename, settings.to_json().pretty(2))
+    .map_err(|e| format!("Settings file {} write error {}", filename, e))
+}
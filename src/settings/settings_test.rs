@@ -0,0 +1,116 @@
+#[test]
+fn settings_json_test() {
+  use crate::settings::{Difficulty, Settings};
+
+  let settings = Settings {
+    resolution: (1920, 1080),
+    fullscreen: false,
+    vsync: false,
+    msaa: 4,
+    bloom: false,
+    vignette: true,
+    color_grading: false,
+    film_grain: true,
+    master_volume: 0.8,
+    sfx_volume: 0.5,
+    music_volume: 0.3,
+    mouse_sensitivity: 1.5,
+    difficulty: Difficulty::Hard,
+  };
+  assert_eq!(settings, Settings::from_json(&settings.to_json()));
+}
+
+#[test]
+fn settings_defaults_test() {
+  use crate::settings::{Difficulty, Settings};
+
+  let settings = Settings::from_json(&json::parse(r#"{
+    "video": { "resolution": "wide", "msaa": 3 },
+    "audio": { "master_volume": 1.5, "sfx_volume": -1 },
+    "gameplay": { "difficulty": "nightmare" }
+  }"#).unwrap());
+  let defaults = Settings::default();
+  assert_eq!(defaults.resolution, settings.resolution, "Invalid resolution should fall back to default");
+  assert_eq!(defaults.msaa, settings.msaa, "Unsupported MSAA level should fall back to default");
+  assert_eq!(1.0, settings.master_volume, "Volume should be clamped");
+  assert_eq!(0.0, settings.sfx_volume, "Volume should be clamped");
+  assert_eq!(Difficulty::Normal, settings.difficulty);
+}
+
+#[test]
+fn parse_resolution_test() {
+  use crate::settings::parse_resolution;
+
+  assert_eq!(Some((1280, 720)), parse_resolution("1280x720"));
+  assert_eq!(None, parse_resolution("1280"));
+  assert_eq!(None, parse_resolution("0x720"));
+  assert_eq!(None, parse_resolution("1280x720x2"));
+}
+
+#[test]
+fn settings_menu_test() {
+  use crate::settings::{Difficulty, Settings};
+  use crate::settings::menu::{SettingsControl, SettingsMenu};
+
+  let mut menu = SettingsMenu::default();
+  let mut settings = Settings::default();
+  let mut file_settings = Settings::default();
+  menu.apply(SettingsControl::Toggle, &mut settings, &mut file_settings);
+  assert!(menu.is_open());
+
+  menu.apply(SettingsControl::Increase, &mut settings, &mut file_settings);
+  assert_eq!((1920, 1080), settings.resolution);
+  menu.apply(SettingsControl::Increase, &mut settings, &mut file_settings);
+  menu.apply(SettingsControl::Increase, &mut settings, &mut file_settings);
+  assert_eq!((1280, 720), settings.resolution, "Resolution should wrap around");
+
+  menu.apply(SettingsControl::Previous, &mut settings, &mut file_settings);
+  assert_eq!(12, menu.selected());
+  menu.apply(SettingsControl::Decrease, &mut settings, &mut file_settings);
+  assert_eq!(Difficulty::Easy, settings.difficulty);
+
+  for _ in 0..4 {
+    menu.apply(SettingsControl::Previous, &mut settings, &mut file_settings);
+  }
+  menu.apply(SettingsControl::Increase, &mut settings, &mut file_settings);
+  assert_eq!(1.0, settings.master_volume, "Volume should not go above 1.0");
+  menu.apply(SettingsControl::Decrease, &mut settings, &mut file_settings);
+  assert_eq!(0.9, settings.master_volume);
+
+  for _ in 0..5 {
+    menu.apply(SettingsControl::Previous, &mut settings, &mut file_settings);
+  }
+  menu.apply(SettingsControl::Decrease, &mut settings, &mut file_settings);
+  assert_eq!(8, settings.msaa, "MSAA level should wrap around");
+
+  assert!(menu.take_dirty());
+  assert!(!menu.take_dirty(), "Changes should be reported once");
+  assert_eq!(settings, file_settings);
+}
+
+#[test]
+fn settings_menu_overrides_test() {
+  use crate::settings::{Difficulty, Settings};
+  use crate::settings::menu::{SettingsControl, SettingsMenu};
+
+  let mut menu = SettingsMenu::new(true);
+  let file_settings_before = Settings::default();
+  let mut file_settings = file_settings_before.clone();
+  let mut settings = Settings {
+    fullscreen: false,
+    difficulty: Difficulty::Hard,
+    ..Settings::default()
+  };
+  menu.apply(SettingsControl::Toggle, &mut settings, &mut file_settings);
+  menu.apply(SettingsControl::Previous, &mut settings, &mut file_settings);
+  menu.apply(SettingsControl::Decrease, &mut settings, &mut file_settings);
+  assert_eq!(Difficulty::Hard, settings.difficulty, "Replay difficulty should be locked");
+  assert!(!menu.take_dirty());
+
+  for _ in 0..2 {
+    menu.apply(SettingsControl::Previous, &mut settings, &mut file_settings);
+  }
+  menu.apply(SettingsControl::Decrease, &mut settings, &mut file_settings);
+  assert_eq!(0.9, settings.music_volume);
+  assert_eq!(Settings { music_volume: 0.9, ..file_settings_before }, file_settings, "Only the changed item should be saved");
+}
@@ -5,6 +5,8 @@ use cgmath::BaseFloat;
 #[allow(clippy::single_component_path_imports)]
 use gfx;
 
+use crate::gfx_app::HdrColorFormat;
+
 gfx_defines! {
   constant TileMapData {
     data: [f32; 4] = "data",
@@ -14,12 +16,16 @@ gfx_defines! {
     position: [f32; 2] = "a_position",
   }
 
-  constant Rotation {
-    rotation: f32 = "a_rotation",
+  constant LightData {
+    position: [f32; 4] = "position",
+    color: [f32; 4] = "color",
+    direction: [f32; 4] = "direction",
   }
 
-  constant Time {
-    time_modulo: f32 = "a_time",
+  constant LightLocals {
+    ambient: [f32; 4] = "u_Ambient",
+    count: i32 = "u_LightCount",
+    aspect: f32 = "u_Aspect",
   }
 
   constant TilemapSettings {
@@ -27,67 +33,151 @@ gfx_defines! {
     tilesheet_size: [f32; 2] = "u_TilesheetSize",
   }
 
+  constant BlurLocals {
+    direction: [f32; 2] = "u_Direction",
+    threshold: f32 = "u_Threshold",
+  }
+
+  constant PostLocals {
+    bloom: f32 = "u_Bloom",
+    vignette: f32 = "u_Vignette",
+    danger: f32 = "u_Danger",
+    night: f32 = "u_Night",
+    grain: f32 = "u_Grain",
+    time: f32 = "u_Time",
+  }
+
   vertex VertexData {
     pos: [f32; 2] = "a_Pos",
     uv: [f32; 2] = "a_BufPos",
   }
 
-  constant CharacterSheet {
-    x_div: f32 = "x_div",
-    y_div: f32 = "y_div",
-    row_idx: u32 = "a_row",
-    index: f32 = "a_index",
+  vertex ColorVertex {
+    pos: [f32; 2] = "a_Pos",
+    uv: [f32; 2] = "a_Uv",
+    color: [f32; 4] = "a_Color",
+  }
+
+  vertex DebugVertex {
+    pos: [f32; 2] = "a_Pos",
+    color: [f32; 4] = "a_Color",
+  }
+
+  vertex ParticleInstance {
+    offset: [f32; 2] = "a_Offset",
+    size: f32 = "a_Size",
+    frame: f32 = "a_Frame",
+    color: [f32; 4] = "a_Color",
+  }
+
+  vertex SpriteInstance {
+    translate: [f32; 2] = "a_Translate",
+    depth: f32 = "a_Depth",
+    rotation: f32 = "a_Rotation",
+    sprite: [f32; 4] = "a_Sprite",
+    tint: [f32; 4] = "a_Tint",
   }
 
   pipeline bullet_pipeline {
     vbuf: gfx::VertexBuffer<VertexData> = (),
+    instances: gfx::InstanceBuffer<SpriteInstance> = (),
     projection_cb: gfx::ConstantBuffer<Projection> = "b_VsLocals",
-    position_cb: gfx::ConstantBuffer<Position> = "b_BulletPosition",
-    rotation_cb: gfx::ConstantBuffer<Rotation> = "b_BulletRotation",
-    out_color: gfx::RenderTarget<gfx::format::Rgba8> = "Target0",
+    out_color: gfx::RenderTarget<HdrColorFormat> = "Target0",
     out_depth: gfx::DepthTarget<gfx::format::DepthStencil> = gfx::preset::depth::LESS_EQUAL_WRITE,
   }
 
   pipeline critter_pipeline {
     vbuf: gfx::VertexBuffer<VertexData> = (),
+    instances: gfx::InstanceBuffer<SpriteInstance> = (),
     projection_cb: gfx::ConstantBuffer<Projection> = "b_VsLocals",
-    position_cb: gfx::ConstantBuffer<Position> = "b_CharacterPosition",
-    character_sprite_cb: gfx::ConstantBuffer<CharacterSheet> = "b_CharacterSprite",
     charactersheet: gfx::TextureSampler<[f32; 4]> = "t_CharacterSheet",
-    out_color: gfx::RenderTarget<gfx::format::Rgba8> = "Target0",
+    lights: gfx::ConstantBuffer<LightData> = "b_Lights",
+    light_locals: gfx::ConstantBuffer<LightLocals> = "b_LightLocals",
+    out_color: gfx::RenderTarget<HdrColorFormat> = "Target0",
     out_depth: gfx::DepthTarget<gfx::format::DepthStencil> = gfx::preset::depth::LESS_EQUAL_WRITE,
   }
 
   pipeline tilemap_pipeline {
     vbuf: gfx::VertexBuffer<VertexData> = (),
     position_cb: gfx::ConstantBuffer<Position> = "b_TileMapPosition",
-    time_passed_cb: gfx::ConstantBuffer<Time> = "b_TimeModulo",
+    lights: gfx::ConstantBuffer<LightData> = "b_Lights",
+    light_locals: gfx::ConstantBuffer<LightLocals> = "b_LightLocals",
     projection_cb: gfx::ConstantBuffer<Projection> = "b_VsLocals",
     tilemap: gfx::ConstantBuffer<TileMapData> = "b_TileMap",
     tilemap_cb: gfx::ConstantBuffer<TilemapSettings> = "b_PsLocals",
     tilesheet: gfx::TextureSampler<[f32; 4]> = "t_TileSheet",
-    out_color: gfx::RenderTarget<gfx::format::Rgba8> = "Target0",
+    out_color: gfx::RenderTarget<HdrColorFormat> = "Target0",
     out_depth: gfx::DepthTarget<gfx::format::DepthStencil> = gfx::preset::depth::LESS_EQUAL_WRITE,
   }
 
   pipeline static_element_pipeline {
     vbuf: gfx::VertexBuffer<VertexData> = (),
-    position_cb: gfx::ConstantBuffer<Position> = "b_StaticElementPosition",
-    time_passed_cb: gfx::ConstantBuffer<Time> = "b_TimeModulo",
+    instances: gfx::InstanceBuffer<SpriteInstance> = (),
+    lights: gfx::ConstantBuffer<LightData> = "b_Lights",
+    light_locals: gfx::ConstantBuffer<LightLocals> = "b_LightLocals",
     projection_cb: gfx::ConstantBuffer<Projection> = "b_VsLocals",
     static_element_sheet: gfx::TextureSampler<[f32; 4]> = "t_StaticElementSheet",
-    out_color: gfx::RenderTarget<gfx::format::Rgba8> = "Target0",
+    out_color: gfx::RenderTarget<HdrColorFormat> = "Target0",
     out_depth: gfx::DepthTarget<gfx::format::DepthStencil> = gfx::preset::depth::LESS_EQUAL_WRITE,
   }
 
   pipeline text_pipeline {
-    vbuf: gfx::VertexBuffer<VertexData> = (),
-    position_cb: gfx::ConstantBuffer<Position> = "b_TextPosition",
+    vbuf: gfx::VertexBuffer<ColorVertex> = (),
     text_sheet: gfx::TextureSampler<[f32; 4]> = "t_TextSheet",
-    out_color: gfx::RenderTarget<gfx::format::Rgba8> = "Target0",
+    out_color: gfx::BlendTarget<gfx::format::Rgba8> = ("Target0", gfx::state::ColorMask::all(), gfx::preset::blend::ALPHA),
+    out_depth: gfx::DepthTarget<gfx::format::DepthStencil> = gfx::preset::depth::LESS_EQUAL_WRITE,
+  }
+
+  pipeline ui_pipeline {
+    vbuf: gfx::VertexBuffer<ColorVertex> = (),
+    ui_sheet: gfx::TextureSampler<[f32; 4]> = "t_UiSheet",
+    out_color: gfx::BlendTarget<gfx::format::Rgba8> = ("Target0", gfx::state::ColorMask::all(), gfx::preset::blend::ALPHA),
     out_depth: gfx::DepthTarget<gfx::format::DepthStencil> = gfx::preset::depth::LESS_EQUAL_WRITE,
   }
 
+  pipeline debug_pipeline {
+    vbuf: gfx::VertexBuffer<DebugVertex> = (),
+    out_color: gfx::BlendTarget<gfx::format::Rgba8> = ("Target0", gfx::state::ColorMask::all(), gfx::preset::blend::ALPHA),
+  }
+
+  pipeline particle_pipeline {
+    vbuf: gfx::VertexBuffer<VertexData> = (),
+    instances: gfx::InstanceBuffer<ParticleInstance> = (),
+    projection_cb: gfx::ConstantBuffer<Projection> = "b_VsLocals",
+    particle_sheet: gfx::TextureSampler<[f32; 4]> = "t_ParticleSheet",
+    out_color: gfx::BlendTarget<HdrColorFormat> = ("Target0", gfx::state::ColorMask::all(), gfx::preset::blend::ALPHA),
+    out_depth: gfx::DepthTarget<gfx::format::DepthStencil> = gfx::preset::depth::LESS_EQUAL_TEST,
+  }
+
+  pipeline present_pipeline {
+    vbuf: gfx::VertexBuffer<VertexData> = (),
+    frame: gfx::TextureSampler<[f32; 4]> = "t_Frame",
+    out_color: gfx::RenderTarget<gfx::format::Rgba8> = "Target0",
+  }
+
+  pipeline resolve_pipeline {
+    vbuf: gfx::VertexBuffer<VertexData> = (),
+    scene: gfx::ShaderResource<[f32; 4]> = "t_Scene",
+    samples: gfx::Global<i32> = "u_Samples",
+    out_color: gfx::RenderTarget<HdrColorFormat> = "Target0",
+  }
+
+  pipeline blur_pipeline {
+    vbuf: gfx::VertexBuffer<VertexData> = (),
+    source: gfx::TextureSampler<[f32; 4]> = "t_Source",
+    blur_cb: gfx::ConstantBuffer<BlurLocals> = "b_BlurLocals",
+    out_color: gfx::RenderTarget<HdrColorFormat> = "Target0",
+  }
+
+  pipeline post_pipeline {
+    vbuf: gfx::VertexBuffer<VertexData> = (),
+    scene: gfx::TextureSampler<[f32; 4]> = "t_Scene",
+    bloom: gfx::TextureSampler<[f32; 4]> = "t_Bloom",
+    lut: gfx::TextureSampler<[f32; 4]> = "t_Lut",
+    post_cb: gfx::ConstantBuffer<PostLocals> = "b_PostLocals",
+    out_color: gfx::RenderTarget<gfx::format::Rgba8> = "Target0",
+  }
+
   constant Projection {
     model: [[f32; 4]; 4] = "u_Model",
     view: [[f32; 4]; 4] = "u_View",
@@ -95,28 +185,42 @@ gfx_defines! {
   }
 }
 
-impl Time {
-  pub fn new(time_passed: u64) -> Time {
-    let time_modulo = (time_passed % 89) as f32;
-    Time {
-      time_modulo
+impl SpriteInstance {
+  pub fn new(position: Position, depth: f32) -> SpriteInstance {
+    SpriteInstance {
+      translate: position.position,
+      depth,
+      rotation: 0.0,
+      sprite: [0.0, 0.0, 1.0, 1.0],
+      tint: [1.0, 1.0, 1.0, 1.0],
     }
   }
 }
 
-impl Rotation {
-  pub fn new(rotation: f32) -> Rotation {
-    Rotation {
-      rotation
+impl VertexData {
+  pub fn new(pos: [f32; 2], uv: [f32; 2]) -> VertexData {
+    VertexData {
+      pos,
+      uv,
     }
   }
 }
 
-impl VertexData {
-  pub fn new(pos: [f32; 2], uv: [f32; 2]) -> VertexData {
-    VertexData {
+impl ColorVertex {
+  pub fn new(pos: [f32; 2], uv: [f32; 2], color: [f32; 4]) -> ColorVertex {
+    ColorVertex {
       pos,
       uv,
+      color,
+    }
+  }
+}
+
+impl DebugVertex {
+  pub fn new(pos: [f32; 2], color: [f32; 4]) -> DebugVertex {
+    DebugVertex {
+      pos,
+      color,
     }
   }
 }
@@ -1,14 +1,21 @@
 use cgmath::Point2;
+use gfx::format::R8_G8_B8_A8;
 use genmesh::{generators::{IndexedPolygon, Plane, SharedVertex}, Triangulate, Vertices};
+use image::RgbaImage;
 use specs::prelude::{Read, ReadStorage, WriteStorage};
 
+use crate::assets::AssetManager;
 use crate::character::controls::CharacterInputState;
+use crate::error::{GameError, GameResult};
 use crate::game::constants::{ASPECT_RATIO, TILE_SIZE, TILES_PCS_H, TILES_PCS_W, VIEW_DISTANCE};
-use crate::gfx_app::{ColorFormat, DepthFormat};
+use crate::gfx_app::{DepthFormat, HdrColorFormat};
 use crate::graphics::{camera::CameraInputState, can_move_to_tile, coords_to_tile, dimensions::{Dimensions, get_projection, get_view_matrix}};
 use crate::graphics::mesh::TexturedMesh;
-use crate::graphics::texture::{load_texture, Texture};
-use crate::shaders::{Position, Projection, tilemap_pipeline, TilemapSettings, Time, VertexData};
+use crate::graphics::render_target::{RenderTargets, TargetViews};
+use crate::graphics::shader::{create_pipeline_simple, reload_pipeline, ShaderProgram};
+use crate::graphics::texture::{create_updatable_texture, Texture, update_texture};
+use crate::lighting::LightBuffers;
+use crate::shaders::{Position, Projection, TileMapData, tilemap_pipeline, TilemapSettings, VertexData};
 
 pub mod path_finding;
 pub mod tile_map;
@@ -50,19 +57,24 @@ impl specs::prelude::Component for TerrainDrawable {
   type Storage = specs::storage::HashMapStorage<TerrainDrawable>;
 }
 
-const SHADER_VERT: &[u8] = include_bytes!("../shaders/terrain.v.glsl");
-const SHADER_FRAG: &[u8] = include_bytes!("../shaders/terrain.f.glsl");
+const SHADER: ShaderProgram = ShaderProgram::lit("terrain");
 
 pub struct TerrainDrawSystem<R: gfx::Resources> {
   bundle: gfx::pso::bundle::Bundle<R, tilemap_pipeline::Data<R>>,
+  tile_texture: gfx::handle::Texture<R, R8_G8_B8_A8>,
+  pending_tiles: Option<Vec<TileMapData>>,
   is_tile_map_dirty: bool,
 }
 
 impl<R: gfx::Resources> TerrainDrawSystem<R> {
   pub fn new<F>(factory: &mut F,
-                rtv: gfx::handle::RenderTargetView<R, ColorFormat>,
-                dsv: gfx::handle::DepthStencilView<R, DepthFormat>)
-                -> TerrainDrawSystem<R>
+                rtv: gfx::handle::RenderTargetView<R, HdrColorFormat>,
+                dsv: gfx::handle::DepthStencilView<R, DepthFormat>,
+                light_buffers: &LightBuffers<R>,
+                terrain: &tile_map::Terrain,
+                tile_sheet: &RgbaImage,
+                assets: &AssetManager)
+                -> GameResult<TerrainDrawSystem<R>>
     where F: gfx::Factory<R> {
     use gfx::traits::FactoryExt;
 
@@ -91,44 +103,61 @@ impl<R: gfx::Resources> TerrainDrawSystem<R> {
         .map(|i| i as u16)
         .collect::<Vec<u16>>();
 
-    let tile_sheet_bytes = &include_bytes!("../../assets/maps/terrain.png")[..];
-    let tile_texture = load_texture(factory, tile_sheet_bytes);
+    let (tile_texture, tile_texture_view) = create_updatable_texture(factory, tile_sheet);
 
-    let mesh = TexturedMesh::new(factory, &vertex_data.as_slice(), index_data.as_slice(), Texture::new(tile_texture, None));
+    let mesh = TexturedMesh::new(factory, &vertex_data.as_slice(), index_data.as_slice(), Texture::new(tile_texture_view, None));
 
-    let pso = factory.create_pipeline_simple(SHADER_VERT, SHADER_FRAG, tilemap_pipeline::new())
-      .expect("Terrain shader loading error");
-
-    let terrain = tile_map::Terrain::new();
+    let pso = create_pipeline_simple(factory, &SHADER.load(assets)?, tilemap_pipeline::new())
+      .map_err(GameError::Shader)?;
 
     let pipeline_data = tilemap_pipeline::Data {
       vbuf: mesh.vertex_buffer,
       position_cb: factory.create_constant_buffer(1),
-      time_passed_cb: factory.create_constant_buffer(1),
+      lights: light_buffers.lights.clone(),
+      light_locals: light_buffers.locals.clone(),
       projection_cb: factory.create_constant_buffer(1),
-      tilemap: factory.create_buffer_immutable(&terrain.tiles.as_slice(),
-                                               gfx::buffer::Role::Constant,
-                                               gfx::memory::Bind::empty()).unwrap(),
+      tilemap: factory.create_buffer(terrain.tiles.len(),
+                                     gfx::buffer::Role::Constant,
+                                     gfx::memory::Usage::Dynamic,
+                                     gfx::memory::Bind::empty())
+        .expect("Terrain tilemap buffer creation error"),
       tilemap_cb: factory.create_constant_buffer(1),
       tilesheet: (mesh.texture.raw, factory.create_sampler_linear()),
       out_color: rtv,
       out_depth: dsv,
     };
 
-    TerrainDrawSystem {
+    Ok(TerrainDrawSystem {
       bundle: gfx::Bundle::new(mesh.slice, pso, pipeline_data),
+      tile_texture,
+      pending_tiles: Some(terrain.tiles.clone()),
       is_tile_map_dirty: true,
-    }
+    })
+  }
+
+  pub fn reload_shaders<F>(&mut self, factory: &mut F, assets: &AssetManager) -> Result<(), String>
+    where F: gfx::Factory<R> {
+    reload_pipeline(factory, assets, SHADER, tilemap_pipeline::new(), &mut self.bundle.pso)
+  }
+
+  pub fn reload<C>(&mut self, terrain: &tile_map::Terrain, tile_sheet: &RgbaImage, encoder: &mut gfx::Encoder<R, C>) -> Result<(), String>
+    where C: gfx::CommandBuffer<R> {
+    update_texture(encoder, &self.tile_texture, tile_sheet)?;
+    self.pending_tiles = Some(terrain.tiles.clone());
+    Ok(())
   }
 
   pub fn draw<C>(&mut self,
                  drawable: &TerrainDrawable,
-                 time_passed: u64,
                  encoder: &mut gfx::Encoder<R, C>)
     where C: gfx::CommandBuffer<R> {
     encoder.update_constant_buffer(&self.bundle.data.projection_cb, &drawable.projection);
     encoder.update_constant_buffer(&self.bundle.data.position_cb, &drawable.position);
-    encoder.update_constant_buffer(&self.bundle.data.time_passed_cb, &Time::new(time_passed));
+
+    if let Some(tiles) = self.pending_tiles.take() {
+      encoder.update_buffer(&self.bundle.data.tilemap, &tiles, 0)
+        .unwrap_or_else(|e| panic!("Terrain tilemap update error {:?}", e));
+    }
 
     if self.is_tile_map_dirty {
       encoder.update_constant_buffer(&self.bundle.data.tilemap_cb, &TilemapSettings {
@@ -142,6 +171,12 @@ impl<R: gfx::Resources> TerrainDrawSystem<R> {
   }
 }
 
+impl<R: gfx::Resources> RenderTargets<R, HdrColorFormat> for TerrainDrawSystem<R> {
+  fn targets(&mut self) -> Vec<TargetViews<'_, R, HdrColorFormat>> {
+    vec![(&mut self.bundle.data.out_color, &mut self.bundle.data.out_depth)]
+  }
+}
+
 pub struct PreDrawSystem;
 
 impl<'a> specs::prelude::System<'a> for PreDrawSystem {
@@ -2,7 +2,7 @@ use cgmath::Point2;
 use pathfinding::{directed::astar::astar, utils::absdiff};
 
 use crate::game::constants::{TILES_PCS_H, TILES_PCS_W, TERRAIN_OBJECTS};
-use crate::game::get_rand_from_range;
+use crate::game::{GameRng, get_rand_from_range};
 use crate::graphics::coords_to_tile;
 use crate::shaders::Position;
 
@@ -51,7 +51,7 @@ pub fn calc_route(start_point: Position, end_point: Position, impassable_tiles:
         |p: &Point2<i32>| p.x == end.x && p.y == end.y)
 }
 
-pub fn calc_next_movement(start_point: Position, end_point: Position) -> i32 {
+pub fn calc_next_movement(start_point: Position, end_point: Position, rng: &mut GameRng) -> i32 {
   let next_step: Point2<i32> = calc_route(start_point, end_point, &TERRAIN_OBJECTS.to_vec())
     .map_or_else(|| Point2::new(0, 0),
                  |(route, ..)| {
@@ -74,6 +74,6 @@ pub fn calc_next_movement(start_point: Position, end_point: Position) -> i32 {
     (-1, -1) => 90,
     (0, -1) => 45,
     (1, -1) => 0,
-    _ => get_rand_from_range(0, 359),
+    _ => get_rand_from_range(rng, 0, 359),
   }
 }
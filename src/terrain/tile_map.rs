@@ -1,8 +1,11 @@
 use tiled::Map;
 
-use crate::data::{get_map_tile, load_map_file};
-use crate::game::constants::{MAP_FILE_PATH, TILES_PCS_H, TILES_PCS_W};
-use crate::shaders::TileMapData;
+use crate::assets::AssetManager;
+use crate::data::{get_map_objects, get_map_tile, load_map_file};
+use crate::error::{GameError, GameResult};
+use crate::game::constants::{LAMP_OBJECT_TYPE, MAP_FILE_PATH, TILES_PCS_H, TILES_PCS_W};
+use crate::graphics::tile_to_coords;
+use crate::shaders::{Position, TileMapData};
 
 const TILEMAP_BUF_LENGTH: usize = TILES_PCS_H * TILES_PCS_H;
 const QUARTER_BUF_LENGTH: usize = TILEMAP_BUF_LENGTH / 4;
@@ -11,10 +14,12 @@ fn calc_index(x_pos: usize, y_pos: usize) -> usize {
   (y_pos * TILES_PCS_W) + x_pos
 }
 
-fn populate_tile_map<'a>(tiles: &'a mut Vec<TileMapData>, map: &Map) -> &'a mut Vec<TileMapData> {
+fn populate_tile_map<'a>(tiles: &'a mut Vec<TileMapData>, map: &Map) -> GameResult<&'a mut Vec<TileMapData>> {
   for y_pos in 0..TILES_PCS_H {
     for x_pos in 0..TILES_PCS_W {
-      let map_val = get_map_tile(map, 0, x_pos, y_pos) - 1;
+      let map_val = get_map_tile(map, 0, x_pos, y_pos)?
+        .checked_sub(1)
+        .ok_or_else(|| GameError::Map(format!("Empty tile at {}, {}", x_pos, y_pos)))?;
       let idx = calc_index(x_pos, y_pos);
 
       if idx < QUARTER_BUF_LENGTH {
@@ -32,29 +37,38 @@ fn populate_tile_map<'a>(tiles: &'a mut Vec<TileMapData>, map: &Map) -> &'a mut
       }
     }
   }
-  tiles
+  Ok(tiles)
 }
 
 pub struct Terrain {
   pub tiles: Vec<TileMapData>,
   pub tile_sets: [Map; 1],
   pub curr_tile_set_idx: usize,
+  pub lamps: Vec<Position>,
 }
 
 impl Terrain {
-  pub fn new() -> Terrain {
+  pub fn new(assets: &AssetManager) -> GameResult<Terrain> {
     let mut map_data = Vec::with_capacity(TILEMAP_BUF_LENGTH);
 
     for _ in 0..TILEMAP_BUF_LENGTH {
       map_data.push(TileMapData::new_empty());
     }
 
-    let map_a = load_map_file(MAP_FILE_PATH);
+    let map_a = load_map_file(assets, MAP_FILE_PATH)?;
+    let lamps = get_map_objects(&map_a, LAMP_OBJECT_TYPE).into_iter().map(tile_to_coords).collect();
 
-    Terrain {
-      tiles: populate_tile_map(&mut map_data, &map_a).to_vec(),
+    Ok(Terrain {
+      tiles: populate_tile_map(&mut map_data, &map_a)?.to_vec(),
       tile_sets: [map_a],
       curr_tile_set_idx: 0,
-    }
+      lamps,
+    })
+  }
+
+  pub fn tile_at(&self, x_pos: usize, y_pos: usize) -> u32 {
+    let idx = calc_index(x_pos, y_pos);
+    let quarter = idx / QUARTER_BUF_LENGTH;
+    self.tiles[idx - quarter * QUARTER_BUF_LENGTH].data[quarter] as u32
   }
 }
@@ -1,19 +1,24 @@
 use cgmath::Point2;
 use specs::prelude::{Read, ReadStorage, WriteStorage};
 
+use crate::assets::AssetManager;
 use crate::character::controls::CharacterInputState;
+use crate::error::{GameError, GameResult};
 use crate::game::constants::{ASPECT_RATIO, VIEW_DISTANCE};
-use crate::gfx_app::{ColorFormat, DepthFormat};
-use crate::graphics::{camera::CameraInputState, dimensions::{Dimensions, get_projection, get_view_matrix}, texture::load_texture};
+use crate::gfx_app::{DepthFormat, HdrColorFormat};
+use crate::graphics::{atlas::{SpriteAtlas, TextureAtlas}, camera::CameraInputState, dimensions::{Dimensions, get_projection, get_view_matrix}};
 use crate::graphics::mesh::{RectangularTexturedMesh, Geometry};
+use crate::graphics::render_target::{RenderTargets, TargetViews};
+use crate::graphics::shader::{create_pipeline_simple, reload_pipeline, ShaderProgram};
+use crate::graphics::sprite_batch::{create_instance_buffer, SpriteBatch};
 use crate::graphics::texture::Texture;
-use crate::shaders::{Position, Projection, static_element_pipeline, Time};
+use crate::lighting::LightBuffers;
+use crate::shaders::{Position, Projection, SpriteInstance, static_element_pipeline};
 use crate::terrain_object::terrain_objects::TerrainObjects;
 
 pub mod terrain_objects;
 
-const SHADER_VERT: &[u8] = include_bytes!("../shaders/static_element.v.glsl");
-const SHADER_FRAG: &[u8] = include_bytes!("../shaders/static_element.f.glsl");
+const SHADER: ShaderProgram = ShaderProgram::lit("static_element");
 
 pub struct TerrainObjectDrawable {
   projection: Projection,
@@ -34,6 +39,15 @@ impl TerrainObjectDrawable {
     }
   }
 
+  pub fn map_position(&self) -> Position {
+    self.position - self.previous_position
+  }
+
+  pub fn move_to(&mut self, movement: Position) {
+    self.position = self.map_position() + movement;
+    self.previous_position = movement;
+  }
+
   pub fn update(&mut self, world_to_clip: &Projection, ci: &CharacterInputState) {
     self.projection = *world_to_clip;
     self.position = self.position + ci.movement - self.previous_position;
@@ -52,55 +66,97 @@ pub enum TerrainTexture {
   Ammo,
 }
 
+impl TerrainTexture {
+  pub fn sprite_name(self) -> &'static str {
+    match self {
+      TerrainTexture::House => "house",
+      TerrainTexture::Tree => "tree",
+      TerrainTexture::Ammo => "ammo",
+    }
+  }
+}
+
 pub struct TerrainObjectDrawSystem<R: gfx::Resources> {
   bundle: gfx::pso::bundle::Bundle<R, static_element_pipeline::Data<R>>,
+  batch: SpriteBatch,
+  sprite: [f32; 4],
+  texture: TerrainTexture,
 }
 
 impl<R: gfx::Resources> TerrainObjectDrawSystem<R> {
   pub fn new<F>(factory: &mut F,
-                rtv: gfx::handle::RenderTargetView<R, ColorFormat>,
+                rtv: gfx::handle::RenderTargetView<R, HdrColorFormat>,
                 dsv: gfx::handle::DepthStencilView<R, DepthFormat>,
-                texture: TerrainTexture) -> TerrainObjectDrawSystem<R>
+                light_buffers: &LightBuffers<R>,
+                sprite_atlas: &SpriteAtlas<R>,
+                texture: TerrainTexture,
+                assets: &AssetManager) -> GameResult<TerrainObjectDrawSystem<R>>
     where F: gfx::Factory<R> {
     use gfx::traits::FactoryExt;
 
-    let (texture_size, texture_bytes) = match texture {
-      TerrainTexture::Ammo => (Point2::new(5.0, 7.0), &include_bytes!("../../assets/maps/ammo.png")[..]),
-      TerrainTexture::House => (Point2::new(125.0, 125.0), &include_bytes!("../../assets/maps/house.png")[..]),
-      TerrainTexture::Tree => (Point2::new(120.0, 120.0), &include_bytes!("../../assets/maps/tree.png")[..]),
+    let texture_size = match texture {
+      TerrainTexture::Ammo => Point2::new(5.0, 7.0),
+      TerrainTexture::House => Point2::new(125.0, 125.0),
+      TerrainTexture::Tree => Point2::new(120.0, 120.0),
     };
 
-    let terrain_object_texture = load_texture(factory, texture_bytes);
-
-    let mesh = RectangularTexturedMesh::new(factory, Texture::new(terrain_object_texture, None), Geometry::Rectangle, texture_size, None, None, None);
+    let mesh = RectangularTexturedMesh::new(factory, Texture::new(sprite_atlas.texture.clone(), None), Geometry::Rectangle, texture_size, None, None, None);
 
-    let pso = factory.create_pipeline_simple(SHADER_VERT, SHADER_FRAG, static_element_pipeline::new())
-      .expect("Terrain object shader loading error");
+    let pso = create_pipeline_simple(factory, &SHADER.load(assets)?, static_element_pipeline::new())
+      .map_err(GameError::Shader)?;
 
     let pipeline_data = static_element_pipeline::Data {
       vbuf: mesh.mesh.vertex_buffer,
-      position_cb: factory.create_constant_buffer(1),
-      time_passed_cb: factory.create_constant_buffer(1),
+      instances: create_instance_buffer(factory),
+      lights: light_buffers.lights.clone(),
+      light_locals: light_buffers.locals.clone(),
       projection_cb: factory.create_constant_buffer(1),
       static_element_sheet: (mesh.mesh.texture.raw, factory.create_sampler_linear()),
       out_color: rtv,
       out_depth: dsv,
     };
 
-    TerrainObjectDrawSystem {
+    Ok(TerrainObjectDrawSystem {
       bundle: gfx::Bundle::new(mesh.mesh.slice, pso, pipeline_data),
+      batch: SpriteBatch::default(),
+      sprite: sprite_atlas.atlas.uv_rect(texture.sprite_name())
+        .unwrap_or_else(|| panic!("Sprite {} missing from atlas", texture.sprite_name())),
+      texture,
+    })
+  }
+
+  pub fn reload_shaders<F>(&mut self, factory: &mut F, assets: &AssetManager) -> Result<(), String>
+    where F: gfx::Factory<R> {
+    reload_pipeline(factory, assets, SHADER, static_element_pipeline::new(), &mut self.bundle.pso)
+  }
+
+  pub fn reload_data(&mut self, atlas: &TextureAtlas) {
+    match atlas.uv_rect(self.texture.sprite_name()) {
+      Some(sprite) => self.sprite = sprite,
+      None => println!("Sprite {} missing from reloaded atlas", self.texture.sprite_name()),
     }
   }
 
-  pub fn draw<C>(&self,
-                 drawable: &TerrainObjectDrawable,
-                 time_passed: u64,
-                 encoder: &mut gfx::Encoder<R, C>)
+  pub fn push(&mut self, drawable: &TerrainObjectDrawable, depth: f32) {
+    let instance = SpriteInstance {
+      sprite: self.sprite,
+      ..SpriteInstance::new(drawable.position, depth)
+    };
+    self.batch.push(&drawable.projection, instance);
+  }
+
+  pub fn draw<C>(&mut self, encoder: &mut gfx::Encoder<R, C>)
     where C: gfx::CommandBuffer<R> {
-    encoder.update_constant_buffer(&self.bundle.data.projection_cb, &drawable.projection);
-    encoder.update_constant_buffer(&self.bundle.data.position_cb, &drawable.position);
-    encoder.update_constant_buffer(&self.bundle.data.time_passed_cb, &Time::new(time_passed));
-    self.bundle.encode(encoder);
+    if let Some(count) = self.batch.upload(&self.bundle.data.projection_cb, &self.bundle.data.instances, encoder) {
+      self.bundle.slice.instances = Some((count, 0));
+      self.bundle.encode(encoder);
+    }
+  }
+}
+
+impl<R: gfx::Resources> RenderTargets<R, HdrColorFormat> for TerrainObjectDrawSystem<R> {
+  fn targets(&mut self) -> Vec<TargetViews<'_, R, HdrColorFormat>> {
+    vec![(&mut self.bundle.data.out_color, &mut self.bundle.data.out_depth)]
   }
 }
 
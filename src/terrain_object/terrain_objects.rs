@@ -2,6 +2,7 @@
 use crate::game::constants::{AMMO_POSITIONS, HOUSE_POSITIONS, TREE_POSITIONS};
 use crate::terrain_object::{TerrainObjectDrawable, TerrainTexture};
 use crate::graphics::set_position;
+use crate::shaders::Position;
 
 pub struct TerrainObjects {
   pub objects: Vec<TerrainObjectDrawable>,
@@ -25,6 +26,25 @@ impl TerrainObjects {
       ]
     }
   }
+
+  pub fn pickups(&self) -> Vec<Position> {
+    self.objects.iter()
+      .filter(|o| o.object_type == TerrainTexture::Ammo)
+      .map(TerrainObjectDrawable::map_position)
+      .collect()
+  }
+
+  pub fn restore(&mut self, pickups: &[Position], movement: Position) {
+    self.objects.retain(|o| o.object_type != TerrainTexture::Ammo);
+    for o in &mut self.objects {
+      o.move_to(movement);
+    }
+    for (idx, pickup) in pickups.iter().enumerate() {
+      let mut ammo = TerrainObjectDrawable::new(*pickup, TerrainTexture::Ammo);
+      ammo.move_to(movement);
+      self.objects.insert(idx, ammo);
+    }
+  }
 }
 
 impl specs::prelude::Component for TerrainObjects {
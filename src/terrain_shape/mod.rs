@@ -1,21 +1,27 @@
 use cgmath::{Point2, Matrix2};
 use specs::{Read, ReadStorage, WriteStorage};
 
+use crate::assets::AssetManager;
 use crate::character::controls::CharacterInputState;
-use crate::game::constants::{ASPECT_RATIO, VIEW_DISTANCE};
-use crate::gfx_app::{ColorFormat, DepthFormat};
+use crate::error::{GameError, GameResult};
+use crate::game::constants::{ASPECT_RATIO, TERRAIN_SHAPE_SPRITE, VIEW_DISTANCE};
+use crate::gfx_app::{DepthFormat, HdrColorFormat};
+use crate::graphics::atlas::{SpriteAtlas, TextureAtlas};
 use crate::graphics::camera::CameraInputState;
 use crate::graphics::dimensions::{Dimensions, get_projection, get_view_matrix};
 use crate::graphics::mesh::{Geometry, RectangularTexturedMesh};
 use crate::graphics::orientation::Orientation;
-use crate::graphics::texture::{load_texture, Texture};
-use crate::shaders::{Position, Projection, static_element_pipeline, Time};
+use crate::graphics::render_target::{RenderTargets, TargetViews};
+use crate::graphics::shader::{create_pipeline_simple, reload_pipeline, ShaderProgram};
+use crate::graphics::sprite_batch::{create_instance_buffer, SpriteBatch};
+use crate::graphics::texture::Texture;
+use crate::lighting::LightBuffers;
+use crate::shaders::{Position, Projection, SpriteInstance, static_element_pipeline};
 use crate::terrain_shape::terrain_shape_objects::TerrainShapeObjects;
 
 pub mod terrain_shape_objects;
 
-const SHADER_VERT: &[u8] = include_bytes!("../shaders/static_element.v.glsl");
-const SHADER_FRAG: &[u8] = include_bytes!("../shaders/static_element.f.glsl");
+const SHADER: ShaderProgram = ShaderProgram::lit("static_element");
 
 pub struct TerrainShapeDrawable {
   projection: Projection,
@@ -52,23 +58,25 @@ impl specs::prelude::Component for TerrainShapeDrawable {
 }
 
 pub struct TerrainShapeDrawSystem<R: gfx::Resources> {
-  bundle: gfx::pso::bundle::Bundle<R, static_element_pipeline::Data<R>>
+  bundle: gfx::pso::bundle::Bundle<R, static_element_pipeline::Data<R>>,
+  batch: SpriteBatch,
+  sprite: [f32; 4],
 }
 
 impl<R: gfx::Resources> TerrainShapeDrawSystem<R> {
   pub fn new<F>(factory: &mut F,
-                rtv: gfx::handle::RenderTargetView<R, ColorFormat>,
+                rtv: gfx::handle::RenderTargetView<R, HdrColorFormat>,
                 dsv: gfx::handle::DepthStencilView<R, DepthFormat>,
+                light_buffers: &LightBuffers<R>,
+                sprite_atlas: &SpriteAtlas<R>,
                 shape: Orientation,
-  ) -> TerrainShapeDrawSystem<R>
+                assets: &AssetManager,
+  ) -> GameResult<TerrainShapeDrawSystem<R>>
     where F: gfx::Factory<R> {
     use gfx::traits::FactoryExt;
 
-    let terrain_shape_bytes = include_bytes!("../../assets/maps/shape.png");
-    let terrain_shape_texture = load_texture(factory, terrain_shape_bytes);
-
     let size = Point2::new(42.0, 42.0);
-    let texture = Texture::new(terrain_shape_texture, None);
+    let texture = Texture::new(sprite_atlas.texture.clone(), None);
 
     let rotation = match shape {
       Orientation::UpLeft | Orientation::UpRight | Orientation::Down => Some(0.0),
@@ -102,34 +110,60 @@ impl<R: gfx::Resources> TerrainShapeDrawSystem<R> {
       Orientation::Up =>  RectangularTexturedMesh::new(factory, texture, Geometry::Triangle, size, scale, rotation, Some(Orientation::Up)),
     };
 
-    let pso = factory
-      .create_pipeline_simple(SHADER_VERT, SHADER_FRAG, static_element_pipeline::new())
-      .expect("Terrain shape shader loading error");
+    let pso = create_pipeline_simple(factory, &SHADER.load(assets)?, static_element_pipeline::new())
+      .map_err(GameError::Shader)?;
 
     let pipeline_data = static_element_pipeline::Data {
       vbuf: rect_mesh.mesh.vertex_buffer,
-      position_cb: factory.create_constant_buffer(1),
-      time_passed_cb: factory.create_constant_buffer(1),
+      instances: create_instance_buffer(factory),
+      lights: light_buffers.lights.clone(),
+      light_locals: light_buffers.locals.clone(),
       projection_cb: factory.create_constant_buffer(1),
       static_element_sheet: (rect_mesh.mesh.texture.raw, factory.create_sampler_linear()),
       out_color: rtv,
       out_depth: dsv,
     };
 
-    TerrainShapeDrawSystem {
+    Ok(TerrainShapeDrawSystem {
       bundle: gfx::Bundle::new(rect_mesh.mesh.slice, pso, pipeline_data),
+      batch: SpriteBatch::default(),
+      sprite: sprite_atlas.atlas.uv_rect(TERRAIN_SHAPE_SPRITE)
+        .unwrap_or_else(|| panic!("Sprite {} missing from atlas", TERRAIN_SHAPE_SPRITE)),
+    })
+  }
+
+  pub fn reload_shaders<F>(&mut self, factory: &mut F, assets: &AssetManager) -> Result<(), String>
+    where F: gfx::Factory<R> {
+    reload_pipeline(factory, assets, SHADER, static_element_pipeline::new(), &mut self.bundle.pso)
+  }
+
+  pub fn reload_data(&mut self, atlas: &TextureAtlas) {
+    match atlas.uv_rect(TERRAIN_SHAPE_SPRITE) {
+      Some(sprite) => self.sprite = sprite,
+      None => println!("Sprite {} missing from reloaded atlas", TERRAIN_SHAPE_SPRITE),
     }
   }
 
-  pub fn draw<C>(&self,
-                 drawable: &TerrainShapeDrawable,
-                 time_passed: u64,
-                 encoder: &mut gfx::Encoder<R, C>)
+  pub fn push(&mut self, drawable: &TerrainShapeDrawable, depth: f32) {
+    let instance = SpriteInstance {
+      sprite: self.sprite,
+      ..SpriteInstance::new(drawable.position, depth)
+    };
+    self.batch.push(&drawable.projection, instance);
+  }
+
+  pub fn draw<C>(&mut self, encoder: &mut gfx::Encoder<R, C>)
     where C: gfx::CommandBuffer<R> {
-    encoder.update_constant_buffer(&self.bundle.data.projection_cb, &drawable.projection);
-    encoder.update_constant_buffer(&self.bundle.data.position_cb, &drawable.position);
-    encoder.update_constant_buffer(&self.bundle.data.time_passed_cb, &Time::new(time_passed));
-    self.bundle.encode(encoder);
+    if let Some(count) = self.batch.upload(&self.bundle.data.projection_cb, &self.bundle.data.instances, encoder) {
+      self.bundle.slice.instances = Some((count, 0));
+      self.bundle.encode(encoder);
+    }
+  }
+}
+
+impl<R: gfx::Resources> RenderTargets<R, HdrColorFormat> for TerrainShapeDrawSystem<R> {
+  fn targets(&mut self) -> Vec<TargetViews<'_, R, HdrColorFormat>> {
+    vec![(&mut self.bundle.data.out_color, &mut self.bundle.data.out_depth)]
   }
 }
 
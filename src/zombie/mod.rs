@@ -1,25 +1,41 @@
 use cgmath::Point2;
-use specs::prelude::{Read, ReadStorage, WriteStorage};
+use specs::prelude::{Read, ReadStorage, Write, WriteStorage};
+use specs::shred::World;
+use specs::world::WorldExt;
 
-use crate::bullet::{BulletDrawable, bullets::Bullets};
+use crate::animation::{AnimationEvent, AnimationLibrary, Animator};
+use crate::assets::AssetManager;
+use crate::bullet::{BulletDrawable, bullets::Bullets, collision::Collision};
 use crate::character::controls::CharacterInputState;
-use crate::critter::CritterData;
+use crate::console::registry::{CommandRegistry, CommandResult, parse_arg};
+use crate::critter::sheet_rows;
 use crate::data;
-use crate::game::constants::{ASPECT_RATIO, NORMAL_DEATH_SPRITE_OFFSET, SMALL_HILLS, SPRITE_OFFSET, VIEW_DISTANCE, ZOMBIE_SHEET_TOTAL_WIDTH, ZOMBIE_STILL_SPRITE_OFFSET};
-use crate::game::get_random_bool;
-use crate::gfx_app::{ColorFormat, DepthFormat};
-use crate::graphics::{camera::CameraInputState, can_move_to_tile, check_terrain_elevation, direction, direction_movement, direction_movement_180, distance, GameTime, get_nearest_random_tile_position, orientation::{Orientation, Stance}, orientation_to_direction, overlaps};
+use crate::error::{GameError, GameResult};
+use crate::game::constants::{ASPECT_RATIO, BULLET_DAMAGE, SMALL_HILLS, VIEW_DISTANCE, WAVE_SPAWN_DISTANCE, ZOMBIE_AGGRO_RADIUS, ZOMBIE_JSON_PATH, ZOMBIE_SHEET,
+                             ZOMBIE_SPAWN_DISTANCE, ZOMBIES_PER_WAVE};
+use crate::game::{GameRng, get_rand_from_range, get_random_bool};
+use crate::gfx_app::{DepthFormat, HdrColorFormat};
+use crate::graphics::{camera::CameraInputState, can_move_to_tile, check_terrain_elevation, DeltaTime, direction, direction_movement, direction_movement_180, distance, GameTime, get_nearest_random_tile_position, orientation::{Orientation, Stance}, orientation_to_direction, overlaps};
+use crate::graphics::atlas::{SpriteAtlas, TextureAtlas};
 use crate::graphics::dimensions::{Dimensions, get_projection, get_view_matrix};
 use crate::graphics::mesh::{Geometry, RectangularTexturedMesh};
-use crate::graphics::texture::{load_texture, Texture};
-use crate::shaders::{CharacterSheet, critter_pipeline, Position, Projection};
+use crate::graphics::render_target::{RenderTargets, TargetViews};
+use crate::graphics::shader::{create_pipeline_simple, reload_pipeline, ShaderProgram};
+use crate::graphics::sprite_batch::{create_instance_buffer, SpriteBatch};
+use crate::graphics::texture::Texture;
+use crate::hud::combat_feed::{CombatEvent, CombatEvents};
+use crate::lighting::LightBuffers;
+use crate::particle::{EmitterKind, ParticleEvents};
+use crate::save::SavedZombie;
+use crate::score::Score;
+use crate::settings::Settings;
+use crate::shaders::{critter_pipeline, Position, Projection, SpriteInstance};
 use crate::terrain::path_finding::calc_next_movement;
 use crate::zombie::zombies::Zombies;
 
 pub mod zombies;
 
-const SHADER_VERT: &[u8] = include_bytes!("../shaders/character.v.glsl");
-const SHADER_FRAG: &[u8] = include_bytes!("../shaders/character.f.glsl");
+const SHADER: ShaderProgram = ShaderProgram::lit("character");
 
 pub struct ZombieDrawable {
   projection: Projection,
@@ -31,8 +47,7 @@ pub struct ZombieDrawable {
   direction: Orientation,
   last_decision: i64,
   pub movement_direction: Point2<f32>,
-  zombie_idx: usize,
-  zombie_death_idx: usize,
+  animator: Animator,
   movement_speed: f32,
   health: f32,
 }
@@ -51,14 +66,37 @@ impl ZombieDrawable {
       last_decision: -2,
       movement_direction: Point2::new(0.0, 0.0),
       previous_elevation: 0.0,
-      zombie_idx: 0,
-      zombie_death_idx: 0,
+      animator: Animator::default(),
       movement_speed: 0.0,
       health: 1.0,
     }
   }
 
-  pub fn update(&mut self, world_to_clip: &Projection, ci: &CharacterInputState, game_time: u64) {
+  pub fn from_save(saved: &SavedZombie, movement: Position) -> ZombieDrawable {
+    let mut zombie = ZombieDrawable::new(saved.position + movement);
+    zombie.previous_position = movement;
+    zombie.previous_elevation = saved.elevation;
+    zombie.health = saved.health;
+    zombie.stance = saved.stance.clone();
+    zombie
+  }
+
+  pub fn spawn(map_position: Position, movement: Position) -> ZombieDrawable {
+    let mut zombie = ZombieDrawable::new(movement - map_position);
+    zombie.previous_position = movement;
+    zombie
+  }
+
+  pub fn to_save(&self) -> SavedZombie {
+    SavedZombie {
+      position: self.position - self.previous_position,
+      elevation: self.previous_elevation,
+      health: self.health,
+      stance: self.stance.clone(),
+    }
+  }
+
+  pub fn update(&mut self, world_to_clip: &Projection, ci: &CharacterInputState, game_time: u64, speed: f32, rng: &mut GameRng) {
     self.projection = *world_to_clip;
 
     let elevated_pos_y = check_terrain_elevation(ci.movement - self.position, &SMALL_HILLS);
@@ -75,15 +113,15 @@ impl ZombieDrawable {
     if is_alive {
       let zombie_pos = ci.movement - self.position;
 
-      if distance_to_player < 400.0 {
-        let dir = calc_next_movement(zombie_pos, self.previous_position) as f32;
+      if distance_to_player < ZOMBIE_AGGRO_RADIUS {
+        let dir = calc_next_movement(zombie_pos, self.previous_position, rng) as f32;
         self.direction = orientation_to_direction(dir);
         self.movement_direction = direction_movement(dir);
         self.stance = Stance::Running;
-        self.movement_speed = 2.0 * self.health;
+        self.movement_speed = 2.0 * self.health * speed;
       } else {
-        self.idle_direction_movement(zombie_pos, game_time as i64);
-        self.movement_speed = self.health;
+        self.idle_direction_movement(zombie_pos, game_time as i64, rng);
+        self.movement_speed = self.health * speed;
       }
     } else {
       self.movement_direction = Point2::new(0.0, 0.0);
@@ -95,7 +133,7 @@ impl ZombieDrawable {
 
   }
 
-  fn idle_direction_movement(&mut self, zombie_pos: Position, game_time: i64) {
+  fn idle_direction_movement(&mut self, zombie_pos: Position, game_time: i64, rng: &mut GameRng) {
     if !can_move_to_tile(zombie_pos) {
       let dir = direction(self.movement_direction, Point2::new(0.0, 0.0));
       self.movement_direction = direction_movement_180(self.movement_direction);
@@ -106,18 +144,18 @@ impl ZombieDrawable {
     if self.last_decision + 2 < game_time {
       self.stance = Stance::Walking;
       self.last_decision = game_time;
-      let end_point = get_nearest_random_tile_position(zombie_pos);
-      let dir = calc_next_movement(zombie_pos, end_point) as f32;
+      let end_point = get_nearest_random_tile_position(zombie_pos, rng);
+      let dir = calc_next_movement(zombie_pos, end_point, rng) as f32;
       self.movement_direction = direction_movement(dir);
       self.direction = orientation_to_direction(dir);
     }
   }
 
-  fn handle_bullet_hit(&mut self) {
-    self.health -= 0.5;
+  fn handle_bullet_hit(&mut self, rng: &mut GameRng) {
+    self.health -= BULLET_DAMAGE;
     if self.health <= 0.0 {
       self.stance =
-        if get_random_bool() {
+        if get_random_bool(rng) {
           Stance::NormalDeath
         } else {
           Stance::CriticalDeath
@@ -125,117 +163,137 @@ impl ZombieDrawable {
     }
   }
 
-  fn check_bullet_hits(&mut self, bullets: &[BulletDrawable]) {
-    bullets.iter().for_each(|bullet| {
-      if overlaps(self.position, bullet.position, 15.0, 15.0) && self.stance != Stance::NormalDeath && self.stance != Stance::CriticalDeath {
-        self.handle_bullet_hit()
+  fn check_bullet_hits(&mut self,
+                       bullets: &mut [BulletDrawable],
+                       movement: Position,
+                       score: &mut Score,
+                       combat_events: &mut CombatEvents,
+                       particle_events: &mut ParticleEvents,
+                       rng: &mut GameRng) {
+    bullets.iter_mut().for_each(|bullet| {
+      if bullet.status == Collision::Flying && overlaps(self.position, bullet.position, 15.0, 15.0) &&
+        self.stance != Stance::NormalDeath && self.stance != Stance::CriticalDeath {
+        bullet.status = Collision::Hit;
+        score.register_hit();
+        self.handle_bullet_hit(rng);
+        let map_position = movement - self.position;
+        particle_events.emit(EmitterKind::Blood, map_position, Point2::new(bullet.movement_direction.x, -bullet.movement_direction.y));
+        if self.stance == Stance::NormalDeath || self.stance == Stance::CriticalDeath {
+          let points = score.register_kill(&self.stance);
+          combat_events.events.push(CombatEvent::Kill { map_position, is_critical: self.stance == Stance::CriticalDeath, points });
+        } else {
+          combat_events.events.push(CombatEvent::Damage { map_position, amount: (BULLET_DAMAGE * 100.0) as u32 });
+        }
       }
     });
   }
 
-  pub fn update_alive_idx(&mut self, max_idx: usize) {
-    if self.zombie_idx < max_idx {
-      self.zombie_idx += 1;
-    } else {
-      self.zombie_idx = 0;
+  fn animation_clip(&self) -> &'static str {
+    match self.stance {
+      Stance::Still => "still",
+      Stance::Running => "run",
+      Stance::NormalDeath => "normal_death",
+      Stance::CriticalDeath => "critical_death",
+      _ => "walk",
     }
   }
 
-  pub fn update_death_idx(&mut self, max_idx: usize) {
-    if self.zombie_death_idx < max_idx {
-      self.zombie_death_idx += 1;
-    }
+  fn animate(&mut self, animations: &AnimationLibrary, delta: f64) -> Option<AnimationEvent> {
+    let clip = self.animation_clip();
+    self.animator.play(animations.clips(ZOMBIE_SHEET), clip);
+    self.animator.update(delta)
   }
 }
 
 pub struct ZombieDrawSystem<R: gfx::Resources> {
   bundle: gfx::pso::bundle::Bundle<R, critter_pipeline::Data<R>>,
-  data: Vec<CritterData>,
+  frames: Vec<Vec<[f32; 4]>>,
+  batch: SpriteBatch,
+}
+
+fn zombie_frames(atlas: &TextureAtlas, assets: &AssetManager) -> GameResult<Vec<Vec<[f32; 4]>>> {
+  data::load_sprite_sheet(assets, ZOMBIE_JSON_PATH)
+    .map(|sheet| sheet_rows(atlas, ZOMBIE_SHEET, &sheet.frames))
 }
 
 impl<R: gfx::Resources> ZombieDrawSystem<R> {
   pub fn new<F>(factory: &mut F,
-                rtv: gfx::handle::RenderTargetView<R, ColorFormat>,
-                dsv: gfx::handle::DepthStencilView<R, DepthFormat>) -> ZombieDrawSystem<R>
+                rtv: gfx::handle::RenderTargetView<R, HdrColorFormat>,
+                dsv: gfx::handle::DepthStencilView<R, DepthFormat>,
+                light_buffers: &LightBuffers<R>,
+                sprite_atlas: &SpriteAtlas<R>,
+                assets: &AssetManager) -> GameResult<ZombieDrawSystem<R>>
     where F: gfx::Factory<R> {
     use gfx::traits::FactoryExt;
 
-    let zombie_bytes = include_bytes!("../../assets/zombie.png");
-    let char_texture = load_texture(factory, zombie_bytes);
-
     let rect_mesh =
-      RectangularTexturedMesh::new(factory, Texture::new(char_texture, None), Geometry::Rectangle, Point2::new(25.0, 35.0), None, None, None);
+      RectangularTexturedMesh::new(factory, Texture::new(sprite_atlas.texture.clone(), None), Geometry::Rectangle, Point2::new(25.0, 35.0), None, None, None);
 
-    let pso =
-      factory.create_pipeline_simple(SHADER_VERT, SHADER_FRAG, critter_pipeline::new())
-        .expect("Zombie shader loading error");
+    let pso = create_pipeline_simple(factory, &SHADER.load(assets)?, critter_pipeline::new())
+      .map_err(GameError::Shader)?;
 
     let pipeline_data = critter_pipeline::Data {
       vbuf: rect_mesh.mesh.vertex_buffer,
+      instances: create_instance_buffer(factory),
       projection_cb: factory.create_constant_buffer(1),
-      position_cb: factory.create_constant_buffer(1),
-      character_sprite_cb: factory.create_constant_buffer(1),
       charactersheet: (rect_mesh.mesh.texture.raw, factory.create_sampler_linear()),
+      lights: light_buffers.lights.clone(),
+      light_locals: light_buffers.locals.clone(),
       out_color: rtv,
       out_depth: dsv,
     };
 
-    let data = data::load_zombie();
-
-    ZombieDrawSystem {
+    Ok(ZombieDrawSystem {
       bundle: gfx::Bundle::new(rect_mesh.mesh.slice, pso, pipeline_data),
-      data,
-    }
+      frames: zombie_frames(&sprite_atlas.atlas, assets)?,
+      batch: SpriteBatch::default(),
+    })
   }
 
-  fn get_next_sprite(&self, drawable: &mut ZombieDrawable) -> CharacterSheet {
-    let sprite_idx = match drawable.stance {
-      Stance::Still => {
-        drawable.direction as usize * 4 + drawable.zombie_idx
-      }
-      Stance::Walking if drawable.orientation != Orientation::Normal => {
-        drawable.direction as usize * 8 + drawable.zombie_idx + ZOMBIE_STILL_SPRITE_OFFSET
-      }
-      Stance::Running if drawable.orientation != Orientation::Normal => {
-        drawable.direction as usize * 8 + drawable.zombie_idx + ZOMBIE_STILL_SPRITE_OFFSET
-      }
-      Stance::NormalDeath if drawable.orientation != Orientation::Normal => {
-        drawable.direction as usize * 6 + drawable.zombie_death_idx + NORMAL_DEATH_SPRITE_OFFSET
-      }
-      Stance::CriticalDeath if drawable.orientation != Orientation::Normal => {
-        drawable.direction as usize * 8 + drawable.zombie_death_idx
-      }
-      _ => {
-        drawable.direction = drawable.orientation;
-        drawable.orientation as usize * 8 + drawable.zombie_idx + ZOMBIE_STILL_SPRITE_OFFSET
-      }
-    } as usize;
+  pub fn reload_shaders<F>(&mut self, factory: &mut F, assets: &AssetManager) -> Result<(), String>
+    where F: gfx::Factory<R> {
+    reload_pipeline(factory, assets, SHADER, critter_pipeline::new(), &mut self.bundle.pso)
+  }
 
-    let (y_div, row_idx) =
-      if drawable.stance == Stance::NormalDeath || drawable.stance == Stance::CriticalDeath {
-        (0.0, 2)
-      } else {
-        (1.0, 2)
-      };
-
-    let elements_x = ZOMBIE_SHEET_TOTAL_WIDTH / (self.data[sprite_idx].data[2] + SPRITE_OFFSET);
-    CharacterSheet {
-      x_div: elements_x,
-      y_div,
-      row_idx,
-      index: sprite_idx as f32,
+  pub fn reload_data(&mut self, atlas: &TextureAtlas, assets: &AssetManager) {
+    match zombie_frames(atlas, assets) {
+      Ok(frames) => self.frames = frames,
+      Err(e) => println!("Zombie sprite reload error {}", e),
     }
   }
 
-  pub fn draw<C>(&mut self,
-                 mut drawable: &mut ZombieDrawable,
-                 encoder: &mut gfx::Encoder<R, C>)
+  fn get_next_sprite(&self, drawable: &mut ZombieDrawable) -> [f32; 4] {
+    if drawable.direction == Orientation::Normal {
+      drawable.direction = drawable.orientation;
+    }
+
+    drawable.animator.cell(drawable.direction as usize)
+      .and_then(|(row, cell)| self.frames.get(row)?.get(cell).cloned())
+      .unwrap_or([0.0, 0.0, 0.0, 0.0])
+  }
+
+  pub fn push(&mut self, mut drawable: &mut ZombieDrawable, depth: f32) {
+    let damage = 1.0 - drawable.health.max(0.0).min(1.0);
+    let instance = SpriteInstance {
+      sprite: self.get_next_sprite(&mut drawable),
+      tint: [1.0, 1.0 - 0.4 * damage, 1.0 - 0.4 * damage, 1.0],
+      ..SpriteInstance::new(drawable.position, depth)
+    };
+    self.batch.push(&drawable.projection, instance);
+  }
+
+  pub fn draw<C>(&mut self, encoder: &mut gfx::Encoder<R, C>)
     where C: gfx::CommandBuffer<R> {
-    encoder.update_constant_buffer(&self.bundle.data.projection_cb, &drawable.projection);
-    encoder.update_constant_buffer(&self.bundle.data.position_cb, &drawable.position);
-    encoder.update_constant_buffer(&self.bundle.data.character_sprite_cb,
-                                   &self.get_next_sprite(&mut drawable));
-    self.bundle.encode(encoder);
+    if let Some(count) = self.batch.upload(&self.bundle.data.projection_cb, &self.bundle.data.instances, encoder) {
+      self.bundle.slice.instances = Some((count, 0));
+      self.bundle.encode(encoder);
+    }
+  }
+}
+
+impl<R: gfx::Resources> RenderTargets<R, HdrColorFormat> for ZombieDrawSystem<R> {
+  fn targets(&mut self) -> Vec<TargetViews<'_, R, HdrColorFormat>> {
+    vec![(&mut self.bundle.data.out_color, &mut self.bundle.data.out_depth)]
   }
 }
 
@@ -245,20 +303,91 @@ impl<'a> specs::prelude::System<'a> for PreDrawSystem {
   type SystemData = (WriteStorage<'a, Zombies>,
                      ReadStorage<'a, CameraInputState>,
                      ReadStorage<'a, CharacterInputState>,
-                     ReadStorage<'a, Bullets>,
+                     WriteStorage<'a, Bullets>,
+                     WriteStorage<'a, Score>,
                      Read<'a, Dimensions>,
-                     Read<'a, GameTime>);
-
-  fn run(&mut self, (mut zombies, camera_input, character_input, bullets, dim, gt): Self::SystemData) {
+                     Read<'a, GameTime>,
+                     Read<'a, DeltaTime>,
+                     Read<'a, AnimationLibrary>,
+                     Write<'a, CombatEvents>,
+                     Write<'a, ParticleEvents>,
+                     Write<'a, GameRng>,
+                     Read<'a, Settings>);
+
+  fn run(&mut self, (mut zombies, camera_input, character_input, mut bullets, mut score, dim, gt, dt, animations, mut combat_events, mut particle_events, mut rng, settings): Self::SystemData) {
     use specs::join::Join;
 
-    for (zs, camera, ci, bs) in (&mut zombies, &camera_input, &character_input, &bullets).join() {
+    for (zs, camera, ci, bs, s) in (&mut zombies, &camera_input, &character_input, &mut bullets, &mut score).join() {
       let world_to_clip = dim.world_to_projection(camera);
 
       for z in &mut zs.zombies {
-        z.update(&world_to_clip, ci, gt.0);
-        z.check_bullet_hits(&bs.bullets);
+        z.update(&world_to_clip, ci, gt.0, settings.difficulty.zombie_speed(), &mut rng);
+        z.check_bullet_hits(&mut bs.bullets, ci.movement, s, &mut combat_events, &mut particle_events, &mut rng);
       }
+      zs.zombies = zs.zombies.drain(..)
+        .filter_map(|mut z| match z.animate(&animations, dt.0) {
+          Some(AnimationEvent::Finished) => None,
+          None => Some(z),
+        })
+        .collect();
     }
   }
 }
+
+pub fn register_commands(registry: &mut CommandRegistry) {
+  registry.register("spawn", SPAWN_USAGE, spawn);
+  registry.register("wave", WAVE_USAGE, wave);
+}
+
+const SPAWN_USAGE: &str = "spawn zombie [count]";
+const WAVE_USAGE: &str = "wave <number>";
+const MAX_SPAWN_COUNT: usize = 100;
+
+fn spawn_zombies(world: &mut World, offsets: &[Point2<f32>]) -> usize {
+  use specs::join::Join;
+
+  let character_input = world.read_storage::<CharacterInputState>();
+  let mut zombies = world.write_storage::<Zombies>();
+  let mut spawned = 0;
+  for (ci, zs) in (&character_input, &mut zombies).join() {
+    for offset in offsets {
+      let map_position = ci.movement + Position::new(offset.x, offset.y);
+      if can_move_to_tile(map_position) {
+        zs.zombies.push(ZombieDrawable::spawn(map_position, ci.movement));
+        spawned += 1;
+      }
+    }
+  }
+  spawned
+}
+
+fn spawn(world: &mut World, args: &[&str]) -> CommandResult {
+  if args.first() != Some(&"zombie") {
+    return Err(format!("Usage: {}", SPAWN_USAGE));
+  }
+  let count = if args.len() > 1 { parse_arg::<usize>(args, 1, SPAWN_USAGE)? } else { 1 };
+  let offsets = {
+    let mut rng = world.write_resource::<GameRng>();
+    (0..count.min(MAX_SPAWN_COUNT))
+      .map(|_| {
+        let dir = direction_movement(get_rand_from_range(&mut rng, 0, 360) as f32);
+        Point2::new(dir.x * ZOMBIE_SPAWN_DISTANCE, dir.y * ZOMBIE_SPAWN_DISTANCE)
+      })
+      .collect::<Vec<_>>()
+  };
+  Ok(format!("Spawned {} zombies", spawn_zombies(world, &offsets)))
+}
+
+fn wave(world: &mut World, args: &[&str]) -> CommandResult {
+  let wave = parse_arg::<usize>(args, 0, WAVE_USAGE)?;
+  let scale = world.read_resource::<Settings>().difficulty.wave_scale();
+  let count = ((wave * ZOMBIES_PER_WAVE) as f32 * scale).ceil() as usize;
+  let count = count.min(MAX_SPAWN_COUNT);
+  let offsets = (0..count)
+    .map(|idx| {
+      let dir = direction_movement(idx as f32 / count as f32 * 360.0);
+      Point2::new(dir.x * WAVE_SPAWN_DISTANCE, dir.y * WAVE_SPAWN_DISTANCE)
+    })
+    .collect::<Vec<_>>();
+  Ok(format!("Wave {} spawned {} zombies", wave, spawn_zombies(world, &offsets)))
+}